@@ -14,6 +14,7 @@ class MerkleDeltaTree:
     def __init__(self, hash_function: str = "sha256") -> None:
         self.hash_function = hash_function
         self._leaves: list[Bytes32] = []
+        self._leaf_count = 0
         self._tree: list[list[Bytes32]] = []
         self._root: Bytes32 = b""
 
@@ -25,14 +26,16 @@ class MerkleDeltaTree:
         
         # Hash leaves
         self._leaves = [self._hash(d) for d in deltas]
+        self._leaf_count = len(self._leaves)
         
-        # Pad to power of 2
+        # Pad to power of 2, each slot with its own index
         target_size = 1
         while target_size < len(self._leaves):
             target_size *= 2
         
         while len(self._leaves) < target_size:
-            self._leaves.append(self._hash(b"padding"))
+            index = len(self._leaves).to_bytes(8, "little")
+            self._leaves.append(self._hash(b"padding" + index))
         
         # Build tree bottom-up
         self._tree = [self._leaves]
@@ -61,6 +64,8 @@ class MerkleDeltaTree:
         model_version: str,
     ) -> VerificationProof:
         """Generate merkle proof for a specific delta."""
+        if self._leaf_count <= index < len(self._leaves):
+            raise IndexError(f"Index {index} is padding; the tree has {self._leaf_count} leaves")
         if index >= len(self._leaves):
             raise IndexError(f"Index {index} out of range")
         
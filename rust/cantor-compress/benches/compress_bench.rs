@@ -1,5 +1,5 @@
-use criterion::{black_box, criterion_group, criterion_main, Criterion, BenchmarkId};
-use cantor_compress::{DeltaEncoder, CompressionMethod};
+use criterion::{black_box, criterion_group, criterion_main, Criterion, BenchmarkId, Throughput};
+use cantor_compress::{le_bytes, DeltaEncoder, CompressionMethod, Lz4Mode};
 
 fn bench_compression(c: &mut Criterion) {
     let mut group = c.benchmark_group("compression");
@@ -14,7 +14,15 @@ fn bench_compression(c: &mut Criterion) {
         .map(|i| (i as f32 * 0.001).sin())
         .collect();
     
-    for method in [CompressionMethod::Lz4, CompressionMethod::Varint, CompressionMethod::RunLength] {
+    for method in [
+        CompressionMethod::Lz4,
+        CompressionMethod::Varint,
+        CompressionMethod::RunLength,
+        CompressionMethod::SegmentedSparse,
+        CompressionMethod::VarintRans,
+        #[cfg(feature = "zstd")]
+        CompressionMethod::Zstd,
+    ] {
         let encoder = DeltaEncoder::new(method);
         
         group.bench_with_input(
@@ -33,6 +41,12 @@ fn bench_compression(c: &mut Criterion) {
             },
         );
     }
+
+    // Cold-storage setting: the slowest, smallest LZ4 encode.
+    let encoder = DeltaEncoder::with_lz4_mode(Lz4Mode::HighCompression(12));
+    group.bench_with_input(BenchmarkId::new("Lz4Hc12_dense", 4096), &dense, |b, data| {
+        b.iter(|| encoder.encode(black_box(data)));
+    });
     
     group.finish();
 }
@@ -47,6 +61,143 @@ fn bench_decompression(c: &mut Criterion) {
     });
 }
 
-criterion_group!(benches, bench_compression, bench_decompression);
+fn bench_decode_methods(c: &mut Criterion) {
+    let mut group = c.benchmark_group("decode");
+    
+    // 1024 elements fit LZ4's stack buffer; the larger sizes do not.
+    for size in [1024, 4096, 16384] {
+        let delta: Vec<f32> = (0..size)
+            .map(|i| if i % 4 == 0 { (i as f32 * 0.001).sin() } else { 0.0 })
+            .collect();
+        group.throughput(Throughput::Elements(size as u64));
+        
+        for method in [
+            CompressionMethod::Lz4,
+            CompressionMethod::Varint,
+            CompressionMethod::RunLength,
+            CompressionMethod::SegmentedSparse,
+            CompressionMethod::VarintRans,
+            #[cfg(feature = "zstd")]
+            CompressionMethod::Zstd,
+        ] {
+            let encoder = DeltaEncoder::new(method);
+            let encoded = encoder.encode(&delta).unwrap();
+            
+            group.bench_with_input(
+                BenchmarkId::new(format!("{:?}", method), size),
+                &encoded,
+                |b, data| {
+                    b.iter(|| encoder.decode(black_box(data)));
+                },
+            );
+        }
+    }
+    
+    group.finish();
+}
+
+/// Repeated decoding of small deltas, allocating per call against
+/// reusing one buffer.
+fn bench_decode_into(c: &mut Criterion) {
+    let mut group = c.benchmark_group("decode_reuse");
+    let delta: Vec<f32> = (0..1024)
+        .map(|i| if i % 4 == 0 { (i as f32 * 0.001).sin() } else { 0.0 })
+        .collect();
+    group.throughput(Throughput::Elements(delta.len() as u64));
+
+    for method in [
+        CompressionMethod::Varint,
+        CompressionMethod::RunLength,
+        CompressionMethod::SegmentedSparse,
+    ] {
+        let encoder = DeltaEncoder::new(method);
+        let encoded = encoder.encode(&delta).unwrap();
+        group.bench_with_input(
+            BenchmarkId::new("decode", format!("{:?}", method)),
+            &encoded,
+            |b, data| {
+                b.iter(|| encoder.decode(black_box(data)));
+            },
+        );
+        let mut out = Vec::new();
+        group.bench_with_input(
+            BenchmarkId::new("decode_into", format!("{:?}", method)),
+            &encoded,
+            |b, data| {
+                b.iter(|| encoder.decode_into(black_box(data), &mut out));
+            },
+        );
+    }
+
+    group.finish();
+}
+
+/// The per-element `f32` <-> little-endian conversion against the bulk
+/// one the `Lz4` and `Zstd` bodies use, which is only a cast with
+/// `--features simd`.
+fn bench_le_bytes(c: &mut Criterion) {
+    let mut group = c.benchmark_group("le_bytes");
+    let dense: Vec<f32> = (0..4096).map(|i| (i as f32 * 0.001).sin()).collect();
+    let mut bytes = Vec::new();
+    le_bytes::extend_bytes(&dense, &mut bytes);
+    group.throughput(Throughput::Elements(dense.len() as u64));
+
+    let mut out = Vec::with_capacity(bytes.len());
+    group.bench_function("to_bytes_scalar", |b| {
+        b.iter(|| {
+            out.clear();
+            le_bytes::extend_bytes_scalar(black_box(&dense), &mut out);
+        });
+    });
+    group.bench_function("to_bytes_fast", |b| {
+        b.iter(|| {
+            out.clear();
+            le_bytes::extend_bytes(black_box(&dense), &mut out);
+        });
+    });
+    let mut out = Vec::with_capacity(dense.len());
+    group.bench_function("from_bytes_scalar", |b| {
+        b.iter(|| {
+            out.clear();
+            le_bytes::extend_floats_scalar(black_box(&bytes), &mut out);
+        });
+    });
+    group.bench_function("from_bytes_fast", |b| {
+        b.iter(|| {
+            out.clear();
+            le_bytes::extend_floats(black_box(&bytes), &mut out);
+        });
+    });
+
+    group.finish();
+}
+
+/// Serial encoding against `encode_batch`, which only runs in parallel
+/// with `--features parallel`.
+fn bench_encode_batch(c: &mut Criterion) {
+    let mut group = c.benchmark_group("encode_batch");
+    let deltas: Vec<Vec<f32>> = (0..1000)
+        .map(|t| (0..1024).map(|i| if i % 8 == t % 8 { (i as f32 * 0.001).sin() } else { 0.0 }).collect())
+        .collect();
+    let refs: Vec<&[f32]> = deltas.iter().map(|d| d.as_slice()).collect();
+    let encoder = DeltaEncoder::new(CompressionMethod::Lz4);
+    group.throughput(Throughput::Elements(refs.len() as u64));
+
+    group.bench_function("serial_1000", |b| {
+        b.iter(|| {
+            black_box(&refs)
+                .iter()
+                .map(|d| encoder.encode(d))
+                .collect::<Result<Vec<_>, _>>()
+        });
+    });
+    group.bench_function("batch_1000", |b| {
+        b.iter(|| encoder.encode_batch(black_box(&refs)));
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_compression, bench_decompression, bench_decode_methods, bench_decode_into, bench_le_bytes, bench_encode_batch);
 criterion_main!(benches);
 
@@ -0,0 +1,216 @@
+//! `BitPack`: quantized deltas packed at the narrowest common bit width.
+
+use cantor_core::{CantorError, CompressionMethod, QuantizationParams, Result};
+
+use crate::DeltaEncoder;
+
+/// Most elements a zero-width body may declare. With nothing packed, the
+/// body's length does not bound its count.
+const MAX_ZERO_WIDTH_LEN: u32 = 1 << 24;
+
+/// Bit width for `len` values whose zigzagged bits OR to `widest`. A long
+/// all-zero delta takes width one rather than the zero width decoding
+/// refuses past [`MAX_ZERO_WIDTH_LEN`].
+fn packed_width(widest: u32, len: usize) -> u32 {
+    match 32 - widest.leading_zeros() {
+        0 if len > MAX_ZERO_WIDTH_LEN as usize => 1,
+        width => width,
+    }
+}
+
+/// Packed bytes needed for `len` values of `width` bits.
+fn packed_len(len: usize, width: u32) -> usize {
+    (len as u64 * width as u64).div_ceil(8) as usize
+}
+
+impl DeltaEncoder {
+    /// `BitPack` encoder storing each value as `round(value * scale)`.
+    ///
+    /// Every element is packed at the width of the widest zigzagged value,
+    /// so a delta quantized to `-8..=7` takes half a byte per element
+    /// where `Varint` takes a whole one. The range limits and frame header
+    /// handling are those of [`Self::with_varint_scale`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `scale` is not finite and positive.
+    pub fn with_bitpack_scale(scale: f32) -> Self {
+        assert!(
+            scale.is_finite() && scale > 0.0,
+            "bitpack scale must be finite and positive"
+        );
+        Self::from_params(QuantizationParams {
+            scale,
+            ..QuantizationParams::for_method(CompressionMethod::BitPack)
+        })
+    }
+
+    /// Layout: the bit width `w` (0 to 32) as one byte, the element count
+    /// as a varint, then each zigzagged value in `w` bits, least
+    /// significant first, padded with zero bits to a whole byte. An
+    /// all-zero delta has width zero and no packed bytes, unless it is
+    /// over [`MAX_ZERO_WIDTH_LEN`] elements long, when it packs at width
+    /// one.
+    pub(crate) fn encode_bitpack(&self, delta: &[f32], out: &mut Vec<u8>) -> Result<()> {
+        let len = u32::try_from(delta.len()).map_err(|_| {
+            CantorError::CompressionFailed("delta too long for BitPack".to_string())
+        })?;
+        let mut widest = 0;
+        for (i, &val) in delta.iter().enumerate() {
+            widest |= Self::zigzag_encode(self.quantize(i, val)?);
+        }
+        let width = packed_width(widest, delta.len());
+        out.push(width as u8);
+        Self::write_varint(out, len);
+        out.reserve(packed_len(delta.len(), width));
+
+        let mut pending = 0u64;
+        let mut bits = 0;
+        for (i, &val) in delta.iter().enumerate() {
+            pending |= (Self::zigzag_encode(self.quantize(i, val)?) as u64) << bits;
+            bits += width;
+            while bits >= 8 {
+                out.push(pending as u8);
+                pending >>= 8;
+                bits -= 8;
+            }
+        }
+        if bits > 0 {
+            out.push(pending as u8);
+        }
+        Ok(())
+    }
+
+    /// Width, element count and packed bytes of a `BitPack` body.
+    fn bitpack_parts(data: &[u8]) -> Result<(u32, usize, &[u8])> {
+        let (&width, rest) = data
+            .split_first()
+            .ok_or(CantorError::InvalidDeltaEncoding)?;
+        let width = width as u32;
+        let (len, consumed) = Self::varint_at(data, 1)?;
+        let packed = &rest[consumed..];
+        if width > 32
+            || (width == 0 && len > MAX_ZERO_WIDTH_LEN)
+            || packed.len() != packed_len(len as usize, width)
+        {
+            return Err(CantorError::InvalidDeltaEncoding);
+        }
+        Ok((width, len as usize, packed))
+    }
+
+    /// The `width`-bit value at `index` of `packed`.
+    fn unpack(packed: &[u8], width: u32, index: usize) -> u32 {
+        let bit = index as u64 * width as u64;
+        let start = (bit / 8) as usize;
+        let end = packed.len().min(start + 8);
+        let mut window = [0u8; 8];
+        window[..end - start].copy_from_slice(&packed[start..end]);
+        let mask = (1u64 << width) - 1;
+        ((u64::from_le_bytes(window) >> (bit % 8)) & mask) as u32
+    }
+
+    pub(crate) fn decode_bitpack(&self, data: &[u8], out: &mut Vec<f32>) -> Result<()> {
+        let (width, len, packed) = Self::bitpack_parts(data)?;
+        if width == 0 {
+            out.resize(out.len() + len, 0.0);
+            return Ok(());
+        }
+        out.reserve(len);
+        out.extend(
+            (0..len).map(|i| self.dequantize(Self::zigzag_decode(Self::unpack(packed, width, i)))),
+        );
+        Ok(())
+    }
+
+    pub(crate) fn decode_bitpack_at(&self, data: &[u8], index: usize) -> Result<Option<f32>> {
+        let (width, len, packed) = Self::bitpack_parts(data)?;
+        if index >= len {
+            return Ok(None);
+        }
+        if width == 0 {
+            return Ok(Some(0.0));
+        }
+        Ok(Some(self.dequantize(Self::zigzag_decode(Self::unpack(
+            packed, width, index,
+        )))))
+    }
+
+    /// Exact `BitPack` body length for quantized values `quantized`.
+    pub(crate) fn bitpack_size(quantized: impl Iterator<Item = i32>) -> usize {
+        let (len, widest) = quantized.fold((0, 0), |(len, widest), q| {
+            (len + 1, widest | Self::zigzag_encode(q))
+        });
+        let width = packed_width(widest, len);
+        1 + Self::varint_len(len as u32) + packed_len(len, width)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frame;
+
+    #[test]
+    fn test_bitpack_roundtrip_at_4_and_12_bits() {
+        for (width, scale, range) in [(4u32, 1.0f32, -8i32..=7), (12, 100.0, -2048..=2047)] {
+            let quantized: Vec<i32> = (0..1001)
+                .map(|i| range.start() + (i * 7919) % (range.end() - range.start() + 1))
+                .collect();
+            let delta: Vec<f32> = quantized.iter().map(|&q| q as f32 / scale).collect();
+
+            let encoder = DeltaEncoder::with_bitpack_scale(scale);
+            let encoded = encoder.encode(&delta).unwrap();
+            let body = &encoded[frame::header_size(&encoder)..];
+            assert_eq!(body[0] as u32, width);
+            assert_eq!(body.len(), 1 + 2 + packed_len(delta.len(), width));
+            assert_eq!(encoder.estimated_size(&delta), encoded.len());
+
+            let varint = DeltaEncoder::with_varint_scale(scale);
+            let plain = varint.encode(&delta).unwrap();
+            let decoded = DeltaEncoder::decode_any(&encoded).unwrap();
+            assert_eq!(decoded, varint.decode(&plain).unwrap());
+            assert_eq!(encoder.decode_at(&encoded, 500).unwrap(), decoded[500]);
+            assert!(encoder.decode_at(&encoded, 1001).is_err());
+            assert!(
+                encoded.len() < plain.len(),
+                "{}-bit: {} vs {}",
+                width,
+                encoded.len(),
+                plain.len()
+            );
+        }
+    }
+
+    #[test]
+    fn test_bitpack_empty_and_all_zero() {
+        let encoder = DeltaEncoder::new(CompressionMethod::BitPack);
+        let header = frame::header_size(&encoder);
+        for delta in [vec![], vec![0.0f32; 300], vec![1e-5; 5]] {
+            let encoded = encoder.encode(&delta).unwrap();
+            assert_eq!(encoded[header], 0, "width");
+            assert_eq!(
+                encoded.len(),
+                header + 1 + DeltaEncoder::varint_len(delta.len() as u32)
+            );
+            assert_eq!(encoder.decode(&encoded).unwrap(), vec![0.0; delta.len()]);
+        }
+
+        let encoded = encoder.encode(&[0.5, -0.25]).unwrap();
+        let mut bad_width = encoded.clone();
+        bad_width[header] = 33;
+        assert!(encoder.decode(&bad_width).is_err());
+        assert!(encoder.decode(&encoded[..encoded.len() - 1]).is_err());
+        let mut huge = encoded[..header].to_vec();
+        huge.push(0);
+        DeltaEncoder::write_varint(&mut huge, u32::MAX);
+        assert!(encoder.decode(&huge).is_err());
+
+        // Past that limit an all-zero delta packs at width one, so it
+        // still decodes.
+        let long = vec![0.0f32; MAX_ZERO_WIDTH_LEN as usize + 1];
+        let encoded = encoder.encode(&long).unwrap();
+        assert_eq!(encoded[header], 1, "width");
+        assert_eq!(encoder.estimated_size(&long), encoded.len());
+        assert_eq!(encoder.decode(&encoded).unwrap(), long);
+    }
+}
@@ -0,0 +1,94 @@
+//! One-pass block building: encode deltas and accumulate the tree root.
+
+use cantor_core::{Hash32, Result, RootAccumulator};
+
+use crate::{DeltaEncoder, ReusableEncoder};
+
+/// Encodes a block's deltas in order while folding each payload into the
+/// delta tree root, so the delta set is traversed only once.
+///
+/// The root from [`finish`](Self::finish) is the accumulator's over the
+/// returned payloads; with `cantor_merkle::MerkleAccumulator` it equals
+/// `MerkleDeltaTree::build` over them, or `build_with_hasher` for an
+/// accumulator from `MerkleAccumulator::with_hasher`.
+pub struct StreamingBlockBuilder<A> {
+    encoder: ReusableEncoder,
+    encoded: Vec<Vec<u8>>,
+    tree: A,
+}
+
+impl<A: RootAccumulator> StreamingBlockBuilder<A> {
+    /// Builder committing to its payloads through `tree`, which should be
+    /// empty. Proofs of the block must record the tree's hasher as their
+    /// `tree_hasher`.
+    pub fn new(encoder: DeltaEncoder, tree: A) -> Self {
+        Self {
+            encoder: ReusableEncoder::new(encoder),
+            encoded: Vec::new(),
+            tree,
+        }
+    }
+
+    /// Encode the next delta and add its leaf to the tree.
+    pub fn push(&mut self, delta: &[f32]) -> Result<&[u8]> {
+        let payload = self.encoder.encode(delta)?;
+        self.tree.push_delta(payload);
+        self.encoded.push(payload.to_vec());
+        Ok(self.encoded.last().unwrap())
+    }
+
+    /// Number of deltas pushed so far.
+    pub fn len(&self) -> usize {
+        self.encoded.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.encoded.is_empty()
+    }
+
+    /// The encoded payloads in push order and the delta tree root.
+    pub fn finish(self) -> (Vec<Vec<u8>>, Hash32) {
+        let root = self.tree.root();
+        (self.encoded, root)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CompressionMethod;
+    use cantor_core::{HashAlgorithm, TreeHasher, TreeVersion};
+    use cantor_merkle::{MerkleAccumulator, MerkleDeltaTree};
+
+    #[test]
+    fn test_streaming_root_matches_build() {
+        let deltas: Vec<Vec<f32>> = (0..11)
+            .map(|i| (0..64).map(|j| ((i * j) % 7) as f32 * 0.125).collect())
+            .collect();
+
+        let mut builder = StreamingBlockBuilder::new(
+            DeltaEncoder::new(CompressionMethod::Lz4),
+            MerkleAccumulator::new(),
+        );
+        for delta in &deltas {
+            builder.push(delta).unwrap();
+        }
+        assert_eq!(builder.len(), deltas.len());
+        let (encoded, root) = builder.finish();
+
+        let refs: Vec<&[u8]> = encoded.iter().map(|e| e.as_slice()).collect();
+        assert_eq!(root, MerkleDeltaTree::build(&refs).root());
+        let encoder = DeltaEncoder::new(CompressionMethod::Lz4);
+        for (payload, delta) in encoded.iter().zip(&deltas) {
+            assert_eq!(&encoder.decode(payload).unwrap(), delta);
+        }
+
+        let v2 = TreeHasher::new(HashAlgorithm::Blake3, TreeVersion::V2);
+        let mut builder = StreamingBlockBuilder::new(encoder, MerkleAccumulator::with_hasher(v2));
+        for delta in &deltas {
+            builder.push(delta).unwrap();
+        }
+        let (_, v2_root) = builder.finish();
+        assert_eq!(v2_root, MerkleDeltaTree::build_with_hasher(&refs, v2).root());
+    }
+}
@@ -0,0 +1,172 @@
+//! Shared LZ4 dictionaries for small, similar deltas.
+//!
+//! An LZ4 block only finds matches within itself, so a delta of a few
+//! hundred floats compresses poorly however much it resembles the last
+//! one. A dictionary is history both sides agree on: the compressor may
+//! reference it as if it preceded the block, and the decompressor must
+//! supply the same bytes. Frames compressed against one record its id so
+//! a decoder holding a different dictionary, or none, fails instead of
+//! producing garbage.
+
+use std::sync::Arc;
+
+use cantor_core::CompressionMethod;
+
+use crate::{le_bytes, lz4_block, DeltaEncoder};
+
+/// Most history an LZ4 match can reach; dictionary bytes further back are
+/// never used.
+const LZ4_WINDOW: usize = 64 * 1024;
+
+/// A dictionary's bytes and the id frames record for it.
+#[derive(Clone, Debug)]
+pub(crate) struct Dictionary {
+    pub id: u32,
+    pub bytes: Arc<[u8]>,
+}
+
+impl Dictionary {
+    fn new(bytes: Vec<u8>) -> Self {
+        Self {
+            id: fnv1a(&bytes),
+            bytes: bytes.into(),
+        }
+    }
+}
+
+/// 32-bit FNV-1a, enough to tell dictionaries apart, not to authenticate
+/// them.
+fn fnv1a(bytes: &[u8]) -> u32 {
+    bytes.iter().fold(0x811c_9dc5, |hash, &b| {
+        (hash ^ b as u32).wrapping_mul(0x0100_0193)
+    })
+}
+
+impl DeltaEncoder {
+    /// LZ4 encoder compressing against `dictionary`, typically from
+    /// [`Self::train_dictionary`].
+    ///
+    /// Decoding needs the same dictionary: decode with this encoder, or
+    /// another built from the same bytes. The frame header records the
+    /// dictionary's id, so [`Self::decode_any`], a streaming decoder or an
+    /// encoder with a different dictionary reject the payload. Only the
+    /// last 64 KiB of `dictionary` are reachable. Dictionary blocks are
+    /// always compressed by `lz4_flex`, which has a single mode.
+    pub fn with_dictionary(dictionary: Vec<u8>) -> Self {
+        Self {
+            dictionary: Some(Dictionary::new(dictionary)),
+            ..Self::new(CompressionMethod::Lz4)
+        }
+    }
+
+    /// A dictionary of at most `size` bytes (and at most 64 KiB, LZ4's
+    /// window) for [`Self::with_dictionary`], built from typical deltas.
+    ///
+    /// The dictionary is the raw bytes of whole samples, so later deltas
+    /// can copy runs of elements they share with them. Samples are taken
+    /// from the last, which end up nearest the data and cheapest to
+    /// reference; one the dictionary already covers (that would compress
+    /// to under a quarter of its size against it) is skipped to leave room
+    /// for variety.
+    pub fn train_dictionary(samples: &[&[f32]], size: usize) -> Vec<u8> {
+        let size = size.min(LZ4_WINDOW);
+        let mut dictionary = Vec::new();
+        let mut compressed = Vec::new();
+        for sample in samples.iter().rev() {
+            let mut bytes = Vec::new();
+            le_bytes::extend_bytes(sample, &mut bytes);
+            if bytes.is_empty() || dictionary.len() + bytes.len() > size {
+                continue;
+            }
+            compressed.clear();
+            if lz4_block::compress_with_dict_into(&bytes, &dictionary, &mut compressed).is_ok()
+                && compressed.len() * 4 < bytes.len()
+            {
+                continue;
+            }
+            dictionary.splice(0..0, bytes);
+        }
+        dictionary
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Deltas sharing a common shape with small per-step perturbations.
+    fn similar_deltas(count: usize) -> Vec<Vec<f32>> {
+        let base: Vec<f32> = (0..300)
+            .map(|i| match i % 7 {
+                0 | 3 => 0.0,
+                _ => ((i as f32) * 0.173).sin() * 0.25,
+            })
+            .collect();
+        (0..count)
+            .map(|step| {
+                let mut delta = base.clone();
+                for k in 0..6 {
+                    let i = (step * 37 + k * 53) % delta.len();
+                    delta[i] = (step * 6 + k) as f32 * 1e-3;
+                }
+                delta
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_dictionary_shrinks_similar_deltas() {
+        let deltas = similar_deltas(110);
+        let (training, deltas) = deltas.split_at(10);
+        let samples: Vec<&[f32]> = training.iter().map(Vec::as_slice).collect();
+        let dictionary = DeltaEncoder::train_dictionary(&samples, 16 * 1024);
+        assert!(!dictionary.is_empty() && dictionary.len() <= 16 * 1024);
+
+        let plain = DeltaEncoder::new(CompressionMethod::Lz4);
+        let with_dict = DeltaEncoder::with_dictionary(dictionary.clone());
+        let (mut plain_total, mut dict_total) = (0, 0);
+        for delta in deltas {
+            let encoded = with_dict.encode(delta).unwrap();
+            assert_eq!(with_dict.decode(&encoded).unwrap(), *delta);
+            assert_eq!(
+                DeltaEncoder::with_dictionary(dictionary.clone())
+                    .decode(&encoded)
+                    .unwrap(),
+                *delta
+            );
+            dict_total += encoded.len();
+            plain_total += plain.encode(delta).unwrap().len();
+        }
+        assert!(
+            dict_total * 2 < plain_total,
+            "{} vs {}",
+            dict_total,
+            plain_total
+        );
+    }
+
+    #[test]
+    fn test_dictionary_payload_needs_its_dictionary() {
+        let deltas = similar_deltas(3);
+        let dictionary = DeltaEncoder::train_dictionary(&[&deltas[0]], 4096);
+        let encoded = DeltaEncoder::with_dictionary(dictionary)
+            .encode(&deltas[1])
+            .unwrap();
+
+        assert!(DeltaEncoder::decode_any(&encoded).is_err());
+        assert!(DeltaEncoder::new(CompressionMethod::Lz4)
+            .decode(&encoded)
+            .is_err());
+        let other = DeltaEncoder::train_dictionary(&[&deltas[2]], 4096);
+        assert!(DeltaEncoder::with_dictionary(other)
+            .decode(&encoded)
+            .is_err());
+
+        // A dictionary decoder still reads plain LZ4 payloads.
+        let plain = DeltaEncoder::new(CompressionMethod::Lz4)
+            .encode(&deltas[1])
+            .unwrap();
+        let decoder = DeltaEncoder::with_dictionary(vec![1, 2, 3, 4, 5]);
+        assert_eq!(decoder.decode(&plain).unwrap(), deltas[1]);
+    }
+}
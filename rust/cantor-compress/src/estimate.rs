@@ -0,0 +1,103 @@
+//! Payload size estimates that do not encode the delta.
+
+use cantor_core::CompressionMethod;
+
+use crate::{frame, lz4_block, rans, zstd_frame, DeltaEncoder, AUTO_CANDIDATES, RLE_MAX_COUNT};
+
+impl DeltaEncoder {
+    /// Predicted length of [`Self::encode`]'s output for `delta`, without
+    /// producing it, e.g. to fill a block under a byte budget.
+    ///
+    /// The estimate never falls short of the real length. It is exact for
+    /// `Varint`, `DeltaOfDelta`, `BitPack`, `RunLength` and
+    /// `SegmentedSparse`; `VarintRans` gets an entropy bound from the varint
+    /// byte histogram, and `Lz4`, `Float16` and `Zstd` the worst case of
+    /// their backend, which compressible input comes in far under. `Auto`
+    /// takes the smallest candidate estimate, which is loose when `Zstd`
+    /// would win.
+    ///
+    /// Deltas that [`Self::encode`] rejects still get an estimate.
+    pub fn estimated_size(&self, delta: &[f32]) -> usize {
+        let header = if self.legacy {
+            0
+        } else {
+            frame::header_size(self)
+        };
+        let body = match self.method {
+            CompressionMethod::Lz4 => lz4_block::compressed_len_bound(delta.len() * 4),
+            CompressionMethod::Varint => self
+                .quantized(delta)
+                .map(|quantized| Self::varint_len(Self::zigzag_encode(quantized)))
+                .sum(),
+            CompressionMethod::RunLength => self.rle_size(delta),
+            CompressionMethod::SegmentedSparse => {
+                Self::varint_len(delta.len() as u32)
+                    + self
+                        .segments(delta)
+                        .map(|segment| {
+                            Self::varint_len(segment.start as u32)
+                                + Self::varint_len(segment.len() as u32)
+                                + segment.len() * 4
+                        })
+                        .sum::<usize>()
+            }
+            CompressionMethod::VarintRans => {
+                let mut counts = [0u64; 256];
+                for quantized in self.quantized(delta) {
+                    let mut n = Self::zigzag_encode(quantized);
+                    while n >= 0x80 {
+                        counts[(n as u8 | 0x80) as usize] += 1;
+                        n >>= 7;
+                    }
+                    counts[n as usize] += 1;
+                }
+                rans::encoded_len_bound(&counts)
+            }
+            CompressionMethod::Zstd => zstd_frame::compressed_len_bound(delta.len() * 4),
+            CompressionMethod::Float16 => lz4_block::compressed_len_bound(delta.len() * 2),
+            CompressionMethod::DeltaOfDelta => {
+                let mut previous = 0i32;
+                self.quantized(delta)
+                    .map(|quantized| {
+                        let step = quantized.wrapping_sub(previous);
+                        previous = quantized;
+                        Self::varint_len(Self::zigzag_encode(step))
+                    })
+                    .sum()
+            }
+            CompressionMethod::BitPack => Self::bitpack_size(self.quantized(delta)),
+            CompressionMethod::Auto => {
+                return AUTO_CANDIDATES
+                    .iter()
+                    .map(|&method| Self { method, ..self.clone() }.estimated_size(delta))
+                    .min()
+                    .unwrap();
+            }
+        };
+        header + body
+    }
+
+    /// Quantized values of `delta`, normalized if this encoder normalizes,
+    /// with `i32::MIN` (the widest varint) standing in for any that do not
+    /// quantize.
+    fn quantized<'a>(&self, delta: &'a [f32]) -> impl Iterator<Item = i32> + 'a {
+        let encoder = self.normalized_for(delta).unwrap_or_else(|_| self.clone());
+        delta
+            .iter()
+            .enumerate()
+            .map(move |(i, &val)| encoder.quantize(i, val).unwrap_or(i32::MIN))
+    }
+
+    /// `RunLength` length: per run of zeros or non-zeros, one header byte
+    /// per [`RLE_MAX_COUNT`] elements plus four bytes per literal.
+    fn rle_size(&self, delta: &[f32]) -> usize {
+        let is_zero = |v: f32| v.abs() < self.zero_threshold;
+        delta
+            .chunk_by(|&a, &b| is_zero(a) == is_zero(b))
+            .map(|run| {
+                let literals = if is_zero(run[0]) { 0 } else { run.len() * 4 };
+                run.len().div_ceil(RLE_MAX_COUNT) + literals
+            })
+            .sum()
+    }
+}
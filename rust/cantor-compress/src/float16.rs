@@ -0,0 +1,191 @@
+//! `Float16`: deltas rounded to a 16-bit float, then LZ4-packed.
+
+use cantor_core::{CantorError, CompressionMethod, Result};
+use half::{bf16, f16};
+
+use crate::{lz4_block, DeltaEncoder};
+
+/// 16-bit float format used by [`CompressionMethod::Float16`].
+///
+/// Each element is rounded to nearest, so the relative error of a
+/// decoded value is at most half an ulp: `2^-11` (about 4.9e-4) for
+/// `Ieee` and `2^-8` (about 3.9e-3) for `BFloat16`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum HalfFormat {
+    /// IEEE 754 binary16: normal magnitudes from about 6.1e-5 to 65504.
+    #[default]
+    Ieee,
+    /// bfloat16: the `f32` exponent range with an 8-bit significand.
+    BFloat16,
+}
+
+impl HalfFormat {
+    /// Byte stored in the frame header.
+    pub(crate) fn tag(self) -> u8 {
+        match self {
+            HalfFormat::Ieee => 0,
+            HalfFormat::BFloat16 => 1,
+        }
+    }
+
+    pub(crate) fn from_tag(tag: u8) -> Option<Self> {
+        match tag {
+            0 => Some(HalfFormat::Ieee),
+            1 => Some(HalfFormat::BFloat16),
+            _ => None,
+        }
+    }
+
+    /// Bits of `value` in this format, or `None` if it would overflow or
+    /// land in the subnormal range.
+    fn to_bits(self, value: f32) -> Option<u16> {
+        let (bits, normal) = match self {
+            HalfFormat::Ieee => {
+                let h = f16::from_f32(value);
+                (h.to_bits(), h.is_normal())
+            }
+            HalfFormat::BFloat16 => {
+                let h = bf16::from_f32(value);
+                (h.to_bits(), h.is_normal())
+            }
+        };
+        normal.then_some(bits)
+    }
+
+    fn to_f32(self, bits: u16) -> f32 {
+        match self {
+            HalfFormat::Ieee => f16::from_bits(bits).to_f32(),
+            HalfFormat::BFloat16 => bf16::from_bits(bits).to_f32(),
+        }
+    }
+}
+
+impl DeltaEncoder {
+    /// `Float16` encoder storing each element in `format`.
+    ///
+    /// Values below the zero threshold are stored as zero. Any other value
+    /// that would overflow the format or round to a subnormal fails with
+    /// `CantorError::CompressionFailed`. The
+    /// format is written to the frame header, so any decoder reads it back.
+    pub fn with_half_format(format: HalfFormat) -> Self {
+        Self {
+            half_format: format,
+            ..Self::new(CompressionMethod::Float16)
+        }
+    }
+
+    pub(crate) fn encode_float16(
+        &self,
+        delta: &[f32],
+        scratch: &mut Vec<u8>,
+        out: &mut Vec<u8>,
+    ) -> Result<()> {
+        scratch.clear();
+        scratch.reserve(delta.len() * 2);
+        for &value in delta {
+            let bits = if value.abs() < self.zero_threshold || value == 0.0 {
+                0
+            } else {
+                self.half_format.to_bits(value).ok_or_else(|| {
+                    CantorError::CompressionFailed(format!(
+                        "{} is not a normal {:?} half-precision value",
+                        value, self.half_format
+                    ))
+                })?
+            };
+            scratch.extend_from_slice(&bits.to_le_bytes());
+        }
+        lz4_block::compress_into(scratch, self.lz4_mode, out)
+    }
+
+    pub(crate) fn decode_float16(&self, data: &[u8], out: &mut Vec<f32>) -> Result<()> {
+        let decompressed = lz4_block::decompress(data)?;
+        if decompressed.len() % 2 != 0 {
+            return Err(CantorError::InvalidDeltaEncoding);
+        }
+        out.reserve(decompressed.len() / 2);
+        out.extend(decompressed.chunks_exact(2).map(|chunk| {
+            self.half_format
+                .to_f32(u16::from_le_bytes(chunk.try_into().unwrap()))
+        }));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_float16_roundtrip_error_bounds() {
+        let delta: Vec<f32> = (0..2048)
+            .map(|i| match i % 5 {
+                0 => 0.0,
+                _ => ((i as f32 * 0.731).sin() * 0.999).clamp(-1.0, 1.0),
+            })
+            .filter(|v| *v == 0.0 || v.abs() > 1e-4)
+            .collect();
+
+        for (format, epsilon) in [
+            (HalfFormat::Ieee, 2f32.powi(-11)),
+            (HalfFormat::BFloat16, 2f32.powi(-8)),
+        ] {
+            let encoder = DeltaEncoder::with_half_format(format);
+            let encoded = encoder.encode(&delta).unwrap();
+            let decoded = DeltaEncoder::decode_any(&encoded).unwrap();
+            assert_eq!(decoded.len(), delta.len());
+            for (d, v) in decoded.iter().zip(&delta) {
+                assert!(
+                    (d - v).abs() <= v.abs() * epsilon,
+                    "{:?}: {} -> {}",
+                    format,
+                    v,
+                    d
+                );
+            }
+            // The header names the format, so a default decoder gets it right.
+            let ieee_decoder = DeltaEncoder::new(CompressionMethod::Float16);
+            assert_eq!(ieee_decoder.decode(&encoded).unwrap(), decoded);
+        }
+
+        let dense: Vec<f32> = (1..4097).map(|i| (i as f32 * 0.001).sin()).collect();
+        let half = DeltaEncoder::new(CompressionMethod::Float16)
+            .encode(&dense)
+            .unwrap();
+        let full = DeltaEncoder::new(CompressionMethod::Lz4)
+            .encode(&dense)
+            .unwrap();
+        assert!(
+            half.len() * 10 < full.len() * 6,
+            "{} vs {}",
+            half.len(),
+            full.len()
+        );
+    }
+
+    #[test]
+    fn test_float16_rejects_unrepresentable() {
+        let ieee = DeltaEncoder::with_half_format(HalfFormat::Ieee);
+        let bf = DeltaEncoder::with_half_format(HalfFormat::BFloat16);
+        for value in [70000.0f32, -1.0e5, 1.0e-5] {
+            let err = ieee.encode(&[0.5, value]).unwrap_err();
+            assert!(
+                matches!(err, CantorError::CompressionFailed(_)),
+                "{}",
+                value
+            );
+        }
+        // bfloat16 keeps the f32 exponent range, short of rounding up
+        // past its largest finite value.
+        assert!(bf.encode(&[70000.0, 1.0e-5, 1.0e30]).is_ok());
+        assert!(bf.encode(&[f32::MAX]).is_err());
+
+        // Values under the zero threshold are stored as zero.
+        let encoded = ieee.encode(&[1.0e-7, -0.0]).unwrap();
+        assert_eq!(ieee.decode(&encoded).unwrap(), [0.0, 0.0]);
+
+        let mut unknown_format = encoded;
+        unknown_format[crate::FRAME_MAGIC.len() + 2] = 7;
+        assert!(DeltaEncoder::decode_any(&unknown_format).is_err());
+    }
+}
@@ -0,0 +1,302 @@
+//! Self-describing payload header.
+//!
+//! Every payload [`DeltaEncoder::encode`] produces starts with
+//! [`FRAME_MAGIC`], a version byte and a one-byte method tag. `Varint`,
+//! `VarintRans`, `DeltaOfDelta` and `BitPack` frames then carry their
+//! quantization scale as an `f32` LE, and `Float16` frames one byte naming
+//! the [`HalfFormat`] (0 IEEE, 1 bfloat16), since decoding depends on
+//! them. A tag with the high bit set marks a
+//! [normalized](DeltaEncoder::with_normalization) frame of one of the
+//! quantizing methods, whose scale is followed by the delta's max-abs
+//! divisor as an `f32` LE. An `Lz4` tag with bit 6 set marks a block
+//! compressed against a [dictionary](DeltaEncoder::with_dictionary), whose
+//! 32-bit id follows as a `u32` LE. Bit 5 marks a body of `f64` elements
+//! written by [`DeltaEncoder::encode_f64`], which `f32` decoding rejects.
+//! The method's own encoding follows.
+//!
+//! | tag | method            |
+//! |-----|-------------------|
+//! | 0   | `Lz4`             |
+//! | 1   | `Varint`          |
+//! | 2   | `RunLength`       |
+//! | 3   | `SegmentedSparse` |
+//! | 4   | `VarintRans`      |
+//! | 5   | `Zstd`            |
+//! | 6   | `Float16`         |
+//! | 7   | `DeltaOfDelta`    |
+//! | 8   | `BitPack`         |
+
+use cantor_core::{CantorError, CompressionMethod, QuantizationParams, Result};
+
+use crate::dictionary::Dictionary;
+use crate::{DeltaEncoder, HalfFormat};
+
+/// First bytes of every framed payload.
+pub const FRAME_MAGIC: [u8; 4] = *b"CNTR";
+
+/// Current header layout version.
+pub const FRAME_VERSION: u8 = 1;
+
+/// Method tag bit marking a normalized frame.
+const NORMALIZED: u8 = 0x80;
+/// Method tag bit marking an `Lz4` frame that needs a dictionary.
+const DICTIONARY: u8 = 0x40;
+/// Method tag bit marking a body of `f64` elements.
+const WIDE: u8 = 0x20;
+/// Method tag bits that are flags rather than the method.
+const FLAGS: u8 = NORMALIZED | DICTIONARY | WIDE;
+
+fn method_tag(method: CompressionMethod) -> u8 {
+    match method {
+        CompressionMethod::Lz4 => 0,
+        CompressionMethod::Varint => 1,
+        CompressionMethod::RunLength => 2,
+        CompressionMethod::SegmentedSparse => 3,
+        CompressionMethod::VarintRans => 4,
+        CompressionMethod::Zstd => 5,
+        CompressionMethod::Float16 => 6,
+        CompressionMethod::DeltaOfDelta => 7,
+        CompressionMethod::BitPack => 8,
+        CompressionMethod::Auto => unreachable!("Auto is resolved before framing"),
+    }
+}
+
+fn method_from_tag(tag: u8) -> Option<CompressionMethod> {
+    Some(match tag {
+        0 => CompressionMethod::Lz4,
+        1 => CompressionMethod::Varint,
+        2 => CompressionMethod::RunLength,
+        3 => CompressionMethod::SegmentedSparse,
+        4 => CompressionMethod::VarintRans,
+        5 => CompressionMethod::Zstd,
+        6 => CompressionMethod::Float16,
+        7 => CompressionMethod::DeltaOfDelta,
+        8 => CompressionMethod::BitPack,
+        _ => return None,
+    })
+}
+
+fn carries_scale(method: CompressionMethod) -> bool {
+    matches!(
+        method,
+        CompressionMethod::Varint
+            | CompressionMethod::VarintRans
+            | CompressionMethod::DeltaOfDelta
+            | CompressionMethod::BitPack
+    )
+}
+
+/// Whether `method` has an `f64` form, see [`DeltaEncoder::encode_f64`].
+pub(crate) fn has_wide_form(method: CompressionMethod) -> bool {
+    matches!(
+        method,
+        CompressionMethod::Lz4
+            | CompressionMethod::Zstd
+            | CompressionMethod::Varint
+            | CompressionMethod::VarintRans
+    )
+}
+
+/// Bytes of settings that follow the method tag for `method`.
+fn settings_len(method: CompressionMethod) -> usize {
+    if carries_scale(method) {
+        4
+    } else if method == CompressionMethod::Float16 {
+        1
+    } else {
+        0
+    }
+}
+
+/// Length of the header [`write_header`] writes for `encoder`.
+pub(crate) fn header_size(encoder: &DeltaEncoder) -> usize {
+    let divisor_len = if encoder.normalizes() { 4 } else { 0 };
+    let dictionary_len = if dictionary_id(encoder).is_some() {
+        4
+    } else {
+        0
+    };
+    FRAME_MAGIC.len() + 2 + settings_len(encoder.method) + divisor_len + dictionary_len
+}
+
+/// Id of the dictionary `encoder`'s blocks are compressed against, if any.
+fn dictionary_id(encoder: &DeltaEncoder) -> Option<u32> {
+    encoder
+        .dictionary
+        .as_ref()
+        .filter(|_| encoder.method == CompressionMethod::Lz4)
+        .map(|dictionary| dictionary.id)
+}
+
+/// Append the header for `encoder`'s method and the settings it carries.
+pub(crate) fn write_header(out: &mut Vec<u8>, encoder: &DeltaEncoder) {
+    out.extend_from_slice(&FRAME_MAGIC);
+    out.push(FRAME_VERSION);
+    let dictionary_id = dictionary_id(encoder);
+    let mut tag = method_tag(encoder.method);
+    if encoder.normalizes() {
+        tag |= NORMALIZED;
+    }
+    if dictionary_id.is_some() {
+        tag |= DICTIONARY;
+    }
+    out.push(tag);
+    if carries_scale(encoder.method) {
+        out.extend_from_slice(&encoder.scale.to_le_bytes());
+    }
+    if encoder.normalizes() {
+        out.extend_from_slice(&encoder.max_abs.to_le_bytes());
+    }
+    if encoder.method == CompressionMethod::Float16 {
+        out.push(encoder.half_format.tag());
+    }
+    if let Some(id) = dictionary_id {
+        out.extend_from_slice(&id.to_le_bytes());
+    }
+}
+
+/// [`write_header`] for a body of `f64` elements.
+pub(crate) fn write_wide_header(out: &mut Vec<u8>, encoder: &DeltaEncoder) {
+    let start = out.len();
+    write_header(out, encoder);
+    out[start + FRAME_MAGIC.len() + 1] |= WIDE;
+}
+
+/// Length of the header at the start of `data`, or `None` until the
+/// method tag, which decides it, has arrived.
+pub(crate) fn header_len(data: &[u8]) -> Option<usize> {
+    let tag = *data.get(FRAME_MAGIC.len() + 1)?;
+    let settings_len = method_from_tag(tag & !FLAGS).map_or(0, settings_len);
+    let divisor_len = if tag & NORMALIZED != 0 { 4 } else { 0 };
+    let dictionary_len = if tag & DICTIONARY != 0 { 4 } else { 0 };
+    Some(FRAME_MAGIC.len() + 2 + settings_len + divisor_len + dictionary_len)
+}
+
+/// A parsed header and the payload body after it.
+pub(crate) struct Frame<'a> {
+    pub method: CompressionMethod,
+    scale: f32,
+    /// Max-abs divisor of a normalized frame, otherwise one.
+    max_abs: Option<f32>,
+    half_format: HalfFormat,
+    /// Id of the dictionary an `Lz4` body was compressed against.
+    dictionary_id: Option<u32>,
+    /// Whether the body holds `f64` elements.
+    pub wide: bool,
+    pub body: &'a [u8],
+}
+
+impl Frame<'_> {
+    /// Decoder configured from the header, given the `dictionary` the
+    /// caller holds. Fails if the body needs a dictionary other than it.
+    pub fn decoder(&self, dictionary: Option<&Dictionary>) -> Result<DeltaEncoder> {
+        let dictionary = match (self.dictionary_id, dictionary) {
+            (None, _) => None,
+            (Some(id), Some(dictionary)) if dictionary.id == id => Some(dictionary.clone()),
+            (Some(id), _) => {
+                return Err(CantorError::DecompressionFailed(format!(
+                    "payload needs LZ4 dictionary {:08x}",
+                    id
+                )))
+            }
+        };
+        Ok(DeltaEncoder {
+            half_format: self.half_format,
+            normalize: self.max_abs.is_some(),
+            max_abs: self.max_abs.unwrap_or(1.0),
+            dictionary,
+            ..DeltaEncoder::from_params(QuantizationParams {
+                scale: self.scale,
+                ..QuantizationParams::for_method(self.method)
+            })
+        })
+    }
+}
+
+/// Parse the header of an `f32` payload, or `None` if `data` does not
+/// start with [`FRAME_MAGIC`]. A frame of `f64` elements is an error.
+pub(crate) fn parse(data: &[u8]) -> Result<Option<Frame<'_>>> {
+    match parse_any(data)? {
+        Some(frame) if frame.wide => Err(CantorError::DecompressionFailed(
+            "payload holds f64 elements; decode it with decode_f64".to_string(),
+        )),
+        frame => Ok(frame),
+    }
+}
+
+/// [`parse`], accepting frames of either element width.
+pub(crate) fn parse_any(data: &[u8]) -> Result<Option<Frame<'_>>> {
+    let Some(rest) = data.strip_prefix(&FRAME_MAGIC) else {
+        return Ok(None);
+    };
+    let (&version, rest) = rest
+        .split_first()
+        .ok_or(CantorError::InvalidDeltaEncoding)?;
+    if version != FRAME_VERSION {
+        return Err(CantorError::DecompressionFailed(format!(
+            "unsupported frame version {}",
+            version
+        )));
+    }
+    let (&tag, rest) = rest
+        .split_first()
+        .ok_or(CantorError::InvalidDeltaEncoding)?;
+    let method = method_from_tag(tag & !FLAGS)
+        .filter(|&method| tag & NORMALIZED == 0 || carries_scale(method))
+        .filter(|&method| tag & DICTIONARY == 0 || method == CompressionMethod::Lz4)
+        .filter(|&method| tag & WIDE == 0 || has_wide_form(method))
+        .ok_or_else(|| CantorError::DecompressionFailed(format!("unknown method tag {}", tag)))?;
+
+    let (scale, rest) = if carries_scale(method) {
+        read_positive_f32(rest)?
+    } else {
+        (QuantizationParams::DEFAULT_SCALE, rest)
+    };
+    let (max_abs, rest) = if tag & NORMALIZED != 0 {
+        let (max_abs, rest) = read_positive_f32(rest)?;
+        (Some(max_abs), rest)
+    } else {
+        (None, rest)
+    };
+
+    let (half_format, body) = if method == CompressionMethod::Float16 {
+        let (&tag, rest) = rest
+            .split_first()
+            .ok_or(CantorError::InvalidDeltaEncoding)?;
+        let format = HalfFormat::from_tag(tag).ok_or_else(|| {
+            CantorError::DecompressionFailed(format!("unknown half format {}", tag))
+        })?;
+        (format, rest)
+    } else {
+        (HalfFormat::default(), rest)
+    };
+    let (dictionary_id, body) = if tag & DICTIONARY != 0 {
+        let bytes = body.get(..4).ok_or(CantorError::InvalidDeltaEncoding)?;
+        (
+            Some(u32::from_le_bytes(bytes.try_into().unwrap())),
+            &body[4..],
+        )
+    } else {
+        (None, body)
+    };
+
+    Ok(Some(Frame {
+        method,
+        scale,
+        max_abs,
+        half_format,
+        dictionary_id,
+        wide: tag & WIDE != 0,
+        body,
+    }))
+}
+
+/// A finite, positive `f32` LE setting at the start of `data`.
+fn read_positive_f32(data: &[u8]) -> Result<(f32, &[u8])> {
+    let bytes = data.get(..4).ok_or(CantorError::InvalidDeltaEncoding)?;
+    let value = f32::from_le_bytes(bytes.try_into().unwrap());
+    if !(value.is_finite() && value > 0.0) {
+        return Err(CantorError::InvalidDeltaEncoding);
+    }
+    Ok((value, &data[4..]))
+}
@@ -0,0 +1,93 @@
+//! Bulk conversion between `f32`s and their little-endian bytes, as the
+//! `Lz4` and `Zstd` bodies store them.
+//!
+//! With the `simd` feature on a little-endian target, the conversion is a
+//! `bytemuck` cast: the in-memory layout already is the wire layout, so
+//! it becomes a single copy the compiler vectorizes. Otherwise each
+//! element goes through `to_le_bytes` / `from_le_bytes`, which is also
+//! what big-endian targets need.
+
+/// Append the little-endian bytes of `values` to `out`.
+pub fn extend_bytes(values: &[f32], out: &mut Vec<u8>) {
+    #[cfg(all(feature = "simd", target_endian = "little"))]
+    out.extend_from_slice(bytemuck::cast_slice(values));
+    #[cfg(not(all(feature = "simd", target_endian = "little")))]
+    extend_bytes_scalar(values, out);
+}
+
+/// Append the `f32`s whose little-endian bytes are `bytes` to `out`.
+/// Trailing bytes short of a whole element are ignored.
+pub fn extend_floats(bytes: &[u8], out: &mut Vec<f32>) {
+    #[cfg(all(feature = "simd", target_endian = "little"))]
+    {
+        // Heap buffers such as a decompressed body are aligned for `f32`
+        // in practice; a slice into one at an odd offset is not, and takes
+        // the element-by-element path.
+        match bytemuck::try_cast_slice(&bytes[..bytes.len() / 4 * 4]) {
+            Ok(values) => out.extend_from_slice(values),
+            Err(_) => extend_floats_scalar(bytes, out),
+        }
+    }
+    #[cfg(not(all(feature = "simd", target_endian = "little")))]
+    extend_floats_scalar(bytes, out);
+}
+
+/// The element-by-element form of [`extend_bytes`], for any target.
+pub fn extend_bytes_scalar(values: &[f32], out: &mut Vec<u8>) {
+    out.reserve(values.len() * 4);
+    out.extend(values.iter().flat_map(|f| f.to_le_bytes()));
+}
+
+/// The element-by-element form of [`extend_floats`], for any target.
+pub fn extend_floats_scalar(bytes: &[u8], out: &mut Vec<f32>) {
+    out.reserve(bytes.len() / 4);
+    out.extend(
+        bytes
+            .chunks_exact(4)
+            .map(|chunk| f32::from_le_bytes(chunk.try_into().unwrap())),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fast_conversion_matches_scalar() {
+        let values: Vec<f32> = (0..1001)
+            .map(|i| (i as f32 * 0.37).sin() * 1e3)
+            .chain([0.0, -0.0, f32::MIN_POSITIVE / 2.0, f32::INFINITY])
+            .chain([f32::from_bits(0x7fc0_1234)])
+            .collect();
+
+        let (mut fast, mut scalar) = (vec![0xaa], vec![0xaa]);
+        extend_bytes(&values, &mut fast);
+        extend_bytes_scalar(&values, &mut scalar);
+        assert_eq!(fast, scalar);
+        // The wire layout is little-endian whatever the target's.
+        assert_eq!(&fast[1..5], 0.0f32.to_le_bytes());
+        let mut one = Vec::new();
+        extend_bytes(&[1.0], &mut one);
+        assert_eq!(one, [0x00, 0x00, 0x80, 0x3f]);
+
+        // Decode from a fresh buffer and from an odd offset, each with and
+        // without a partial element left over.
+        let mut aligned = Vec::new();
+        extend_bytes(&values, &mut aligned);
+        for (input, tail) in [
+            (&aligned[..], 0),
+            (&aligned[..], 3),
+            (&fast[1..], 0),
+            (&fast[1..], 3),
+        ] {
+            let input = &input[..input.len() - tail];
+            let (mut fast, mut scalar) = (vec![7.0f32], vec![7.0f32]);
+            extend_floats(input, &mut fast);
+            extend_floats_scalar(input, &mut scalar);
+            let bits = |v: &[f32]| v.iter().map(|f| f.to_bits()).collect::<Vec<_>>();
+            assert_eq!(bits(&fast), bits(&scalar));
+            let expected = values.len() - (tail > 0) as usize;
+            assert_eq!(bits(&fast[1..]), bits(&values[..expected]));
+        }
+    }
+}
@@ -0,0 +1,251 @@
+//! LZ4 block backend.
+//!
+//! Payloads are LZ4 blocks prefixed with their decompressed size as a
+//! little-endian `u32`. With the default `lz4` feature the C library does
+//! the work; without it the pure-Rust `lz4_flex` produces and reads the
+//! same format, so payloads interoperate across builds. The two
+//! compressors may choose different matches, so the bytes (and a tree root
+//! over them) can differ between backends for the same input.
+//!
+//! [`Lz4Mode`] selects the C library's compressor. `lz4_flex` has a single
+//! mode and ignores it.
+//!
+//! Blocks compressed against a dictionary always go through `lz4_flex`,
+//! which the C crate's bindings have no dictionary API to match; they
+//! ignore the mode too.
+
+use cantor_core::{CantorError, Result};
+
+/// Library behind `CompressionMethod::Lz4`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Lz4Backend {
+    /// The C library, with the `lz4` feature.
+    C,
+    /// The pure-Rust `lz4_flex`.
+    Flex,
+}
+
+/// The backend this build compresses with. Features are unified across a
+/// dependency graph, so this, not a dependent crate's own feature flags,
+/// says which one is in use.
+#[cfg(feature = "lz4")]
+pub const LZ4_BACKEND: Lz4Backend = Lz4Backend::C;
+#[cfg(not(feature = "lz4"))]
+pub const LZ4_BACKEND: Lz4Backend = Lz4Backend::Flex;
+
+/// LZ4 compressor setting, trading encode time for ratio.
+///
+/// Blocks are self-contained, so every mode decodes the same way and the
+/// mode only changes encode latency and output size.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Lz4Mode {
+    /// The library's default: acceleration 1.
+    #[default]
+    Default,
+    /// Fast mode with the given acceleration: higher values encode
+    /// faster and compress less. Values below 1 act as 1.
+    Fast(i32),
+    /// LZ4HC at the given level, 1 to 12: higher levels search harder
+    /// for matches, several times slower to encode at the top. Levels
+    /// above 12 act as 12, and 0 or below selects the library default, 9.
+    HighCompression(i32),
+}
+
+#[cfg(feature = "lz4")]
+impl Lz4Mode {
+    fn compression_mode(self) -> lz4::block::CompressionMode {
+        match self {
+            Lz4Mode::Default => lz4::block::CompressionMode::DEFAULT,
+            Lz4Mode::Fast(acceleration) => lz4::block::CompressionMode::FAST(acceleration),
+            Lz4Mode::HighCompression(level) => lz4::block::CompressionMode::HIGHCOMPRESSION(level),
+        }
+    }
+}
+
+/// Worst-case payload length for `len` input bytes: the size prefix plus
+/// LZ4's own bound, `len + len / 255 + 16`. Both backends stay within it.
+pub(crate) fn compressed_len_bound(len: usize) -> usize {
+    4 + len + len / 255 + 16
+}
+
+/// Compress `src` in `mode`, appending the block to `out`.
+#[cfg(feature = "lz4")]
+pub(crate) fn compress_into(src: &[u8], mode: Lz4Mode, out: &mut Vec<u8>) -> Result<()> {
+    let bound = lz4::block::compress_bound(src.len())
+        .map_err(|e| CantorError::CompressionFailed(e.to_string()))?;
+    let start = out.len();
+    out.resize(start + bound + 4, 0);
+    let written =
+        lz4::block::compress_to_buffer(src, Some(mode.compression_mode()), true, &mut out[start..])
+            .map_err(|e| CantorError::CompressionFailed(e.to_string()))?;
+    out.truncate(start + written);
+    Ok(())
+}
+
+/// Decompress a size-prefixed block.
+#[cfg(feature = "lz4")]
+pub(crate) fn decompress(data: &[u8]) -> Result<Vec<u8>> {
+    lz4::block::decompress(data, None).map_err(|e| CantorError::DecompressionFailed(e.to_string()))
+}
+
+/// Compress `src`, appending the block to `out`. `lz4_flex` has no modes.
+#[cfg(not(feature = "lz4"))]
+pub(crate) fn compress_into(src: &[u8], _mode: Lz4Mode, out: &mut Vec<u8>) -> Result<()> {
+    let size = u32::try_from(src.len())
+        .map_err(|_| CantorError::CompressionFailed("input too long for LZ4".to_string()))?;
+    out.extend_from_slice(&size.to_le_bytes());
+    let start = out.len();
+    out.resize(
+        start + lz4_flex::block::get_maximum_output_size(src.len()),
+        0,
+    );
+    let written = lz4_flex::block::compress_into(src, &mut out[start..])
+        .map_err(|e| CantorError::CompressionFailed(e.to_string()))?;
+    out.truncate(start + written);
+    Ok(())
+}
+
+/// Decompress a size-prefixed block.
+#[cfg(not(feature = "lz4"))]
+pub(crate) fn decompress(data: &[u8]) -> Result<Vec<u8>> {
+    lz4_flex::block::decompress_size_prepended(data)
+        .map_err(|e| CantorError::DecompressionFailed(e.to_string()))
+}
+
+/// Decompressed length a size-prefixed block declares.
+pub(crate) fn declared_len(data: &[u8]) -> Result<usize> {
+    let prefix: [u8; 4] = data
+        .get(..4)
+        .ok_or_else(|| CantorError::DecompressionFailed("LZ4 block has no size prefix".to_string()))?
+        .try_into()
+        .unwrap();
+    Ok(u32::from_le_bytes(prefix) as usize)
+}
+
+/// Decompress a size-prefixed block into `buf`, returning its length.
+/// A block declaring more than `buf` holds, or decoding to other than
+/// what it declares, is an error.
+#[cfg(feature = "lz4")]
+pub(crate) fn decompress_to_buffer(data: &[u8], buf: &mut [u8]) -> Result<usize> {
+    let len = declared_len(data)?;
+    let written = lz4::block::decompress_to_buffer(data, None, buf)
+        .map_err(|e| CantorError::DecompressionFailed(e.to_string()))?;
+    check_written(written, len)
+}
+
+/// Decompress a size-prefixed block into `buf`, returning its length.
+/// A block declaring more than `buf` holds, or decoding to other than
+/// what it declares, is an error.
+#[cfg(not(feature = "lz4"))]
+pub(crate) fn decompress_to_buffer(data: &[u8], buf: &mut [u8]) -> Result<usize> {
+    let len = declared_len(data)?;
+    let buf = buf.get_mut(..len).ok_or_else(|| {
+        CantorError::DecompressionFailed(format!("LZ4 block of {} bytes overflows the buffer", len))
+    })?;
+    let written = lz4_flex::block::decompress_into(&data[4..], buf)
+        .map_err(|e| CantorError::DecompressionFailed(e.to_string()))?;
+    check_written(written, len)
+}
+
+fn check_written(written: usize, len: usize) -> Result<usize> {
+    match written == len {
+        true => Ok(len),
+        false => Err(CantorError::DecompressionFailed(format!(
+            "LZ4 block declares {} bytes but holds {}",
+            len, written
+        ))),
+    }
+}
+
+/// Compress `src` against `dict`, appending the size-prefixed block to
+/// `out`. Matches may reach back into the last 64 KiB of `dict`.
+pub(crate) fn compress_with_dict_into(src: &[u8], dict: &[u8], out: &mut Vec<u8>) -> Result<()> {
+    let size = u32::try_from(src.len())
+        .map_err(|_| CantorError::CompressionFailed("input too long for LZ4".to_string()))?;
+    out.extend_from_slice(&size.to_le_bytes());
+    let start = out.len();
+    out.resize(
+        start + lz4_flex::block::get_maximum_output_size(src.len()),
+        0,
+    );
+    let written = lz4_flex::block::compress_into_with_dict(src, &mut out[start..], usable(dict))
+        .map_err(|e| CantorError::CompressionFailed(e.to_string()))?;
+    out.truncate(start + written);
+    Ok(())
+}
+
+/// Decompress a size-prefixed block compressed against `dict`.
+pub(crate) fn decompress_with_dict(data: &[u8], dict: &[u8]) -> Result<Vec<u8>> {
+    lz4_flex::block::decompress_size_prepended_with_dict(data, usable(dict))
+        .map_err(|e| CantorError::DecompressionFailed(e.to_string()))
+}
+
+/// `lz4_flex` matches at least 4 bytes, so a shorter dictionary is no
+/// dictionary at all.
+fn usable(dict: &[u8]) -> &[u8] {
+    if dict.len() < 4 {
+        &[]
+    } else {
+        dict
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Vec<u8> {
+        (0..4096u32)
+            .flat_map(|i| ((i % 37) as f32 * 0.5).to_le_bytes())
+            .collect()
+    }
+
+    #[test]
+    fn test_backend_roundtrip() {
+        let src = sample();
+        for mode in [
+            Lz4Mode::Default,
+            Lz4Mode::Fast(8),
+            Lz4Mode::HighCompression(12),
+        ] {
+            let mut out = Vec::new();
+            compress_into(&src, mode, &mut out).unwrap();
+            assert_eq!(&out[..4], &(src.len() as u32).to_le_bytes());
+            assert_eq!(decompress(&out).unwrap(), src, "{:?}", mode);
+        }
+    }
+
+    #[test]
+    fn test_decompress_to_buffer() {
+        let src = sample();
+        let mut block = Vec::new();
+        compress_into(&src, Lz4Mode::Default, &mut block).unwrap();
+        assert_eq!(declared_len(&block).unwrap(), src.len());
+
+        let mut buf = vec![0; src.len() + 7];
+        assert_eq!(decompress_to_buffer(&block, &mut buf).unwrap(), src.len());
+        assert_eq!(&buf[..src.len()], &src[..]);
+        assert!(decompress_to_buffer(&block, &mut buf[..src.len() - 1]).is_err());
+
+        // A prefix larger than the block's contents.
+        block[..4].copy_from_slice(&(src.len() as u32 + 4).to_le_bytes());
+        assert!(decompress_to_buffer(&block, &mut buf).is_err());
+        assert!(declared_len(&block[..3]).is_err());
+    }
+
+    #[cfg(feature = "lz4")]
+    #[test]
+    fn test_c_backend_output_reads_with_lz4_flex() {
+        let src = sample();
+        let mut out = Vec::new();
+        compress_into(&src, Lz4Mode::HighCompression(9), &mut out).unwrap();
+        assert_eq!(
+            lz4_flex::block::decompress_size_prepended(&out).unwrap(),
+            src
+        );
+        assert_eq!(
+            decompress(&lz4_flex::block::compress_prepend_size(&src)).unwrap(),
+            src
+        );
+    }
+}
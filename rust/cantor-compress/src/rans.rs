@@ -0,0 +1,279 @@
+//! Static-model byte-wise rANS entropy coder.
+//!
+//! Used as a second stage behind quantization: the symbol frequencies of
+//! the input are measured once, normalized to [`PROB_SCALE`] and stored in
+//! the header, then the bytes are coded with a single 32-bit rANS state.
+//!
+//! Layout: `varint(len)`, `varint(symbols)`, then per symbol `u8` and
+//! `varint(freq)`, then the coder output (initial state as `u32` LE,
+//! followed by renormalization bytes). An empty input is just `varint(0)`.
+
+use cantor_core::{CantorError, Result};
+
+use crate::DeltaEncoder;
+
+const PROB_BITS: u32 = 12;
+const PROB_SCALE: u32 = 1 << PROB_BITS;
+/// Lower bound of the normalized state interval.
+const RANS_L: u32 = 1 << 23;
+/// Most bytes a payload may declare. A symbol at the full [`PROB_SCALE`]
+/// codes in zero bits, so the coded length does not bound the output.
+const MAX_LEN: u32 = 1 << 24;
+
+/// Entropy-code `input`, appending the payload to `out`. Inputs over
+/// [`MAX_LEN`] bytes, which [`decode`] would refuse, fail with
+/// `CantorError::CompressionFailed`.
+pub(crate) fn encode(input: &[u8], out: &mut Vec<u8>) -> Result<()> {
+    if input.len() > MAX_LEN as usize {
+        return Err(CantorError::CompressionFailed(format!(
+            "{} varint bytes exceed the VarintRans limit of {}",
+            input.len(),
+            MAX_LEN
+        )));
+    }
+    DeltaEncoder::write_varint(out, input.len() as u32);
+    if input.is_empty() {
+        return Ok(());
+    }
+
+    let freqs = normalized_freqs(input);
+    let mut cum = [0u32; 256];
+    let mut total = 0;
+    for (symbol, &freq) in freqs.iter().enumerate() {
+        cum[symbol] = total;
+        total += freq;
+    }
+
+    let present: Vec<usize> = (0..256).filter(|&s| freqs[s] > 0).collect();
+    DeltaEncoder::write_varint(out, present.len() as u32);
+    for &symbol in &present {
+        out.push(symbol as u8);
+        DeltaEncoder::write_varint(out, freqs[symbol]);
+    }
+
+    // rANS emits in reverse; write backwards and flip the coded tail once.
+    let header_len = out.len();
+    let mut x = RANS_L;
+    for &byte in input.iter().rev() {
+        let (freq, start) = (freqs[byte as usize], cum[byte as usize]);
+        let x_max = ((RANS_L >> PROB_BITS) << 8) * freq;
+        while x >= x_max {
+            out.push(x as u8);
+            x >>= 8;
+        }
+        x = ((x / freq) << PROB_BITS) + (x % freq) + start;
+    }
+    out.extend(x.to_be_bytes());
+    out[header_len..].reverse();
+    Ok(())
+}
+
+/// Decode a payload produced by [`encode`]. Payloads declaring more than
+/// [`MAX_LEN`] bytes are rejected before anything is allocated.
+pub(crate) fn decode(data: &[u8]) -> Result<Vec<u8>> {
+    let (len, mut pos) = DeltaEncoder::varint_at(data, 0)?;
+    if len > MAX_LEN {
+        return Err(CantorError::InvalidDeltaEncoding);
+    }
+    if len == 0 {
+        return match pos == data.len() {
+            true => Ok(Vec::new()),
+            false => Err(CantorError::InvalidDeltaEncoding),
+        };
+    }
+
+    let (count, consumed) = DeltaEncoder::varint_at(data, pos)?;
+    pos += consumed;
+
+    let mut slots = Vec::with_capacity(PROB_SCALE as usize);
+    let mut table = [(0u32, 0u32); 256];
+    for _ in 0..count {
+        let symbol = *data.get(pos).ok_or(CantorError::InvalidDeltaEncoding)?;
+        pos += 1;
+        let (freq, consumed) = DeltaEncoder::varint_at(data, pos)?;
+        pos += consumed;
+
+        if freq == 0
+            || table[symbol as usize].0 != 0
+            || slots.len() + freq as usize > PROB_SCALE as usize
+        {
+            return Err(CantorError::InvalidDeltaEncoding);
+        }
+        table[symbol as usize] = (freq, slots.len() as u32);
+        slots.resize(slots.len() + freq as usize, symbol);
+    }
+    if slots.len() != PROB_SCALE as usize {
+        return Err(CantorError::InvalidDeltaEncoding);
+    }
+
+    let state: [u8; 4] = data
+        .get(pos..pos + 4)
+        .ok_or(CantorError::InvalidDeltaEncoding)?
+        .try_into()
+        .unwrap();
+    let mut x = u32::from_le_bytes(state);
+    pos += 4;
+    if !(RANS_L..RANS_L << 8).contains(&x) {
+        return Err(CantorError::InvalidDeltaEncoding);
+    }
+
+    let mut out = Vec::with_capacity(len as usize);
+    for _ in 0..len {
+        let slot = x & (PROB_SCALE - 1);
+        let symbol = slots[slot as usize];
+        let (freq, start) = table[symbol as usize];
+        x = freq * (x >> PROB_BITS) + slot - start;
+        while x < RANS_L {
+            let byte = *data.get(pos).ok_or(CantorError::InvalidDeltaEncoding)?;
+            x = (x << 8) | byte as u32;
+            pos += 1;
+        }
+        out.push(symbol);
+    }
+
+    // The encoder started from RANS_L, so a complete stream ends there.
+    if x != RANS_L || pos != data.len() {
+        return Err(CantorError::InvalidDeltaEncoding);
+    }
+    Ok(out)
+}
+
+/// Upper bound on the length of [`encode`]'s output for an input with
+/// these byte counts, computed without coding it.
+pub(crate) fn encoded_len_bound(counts: &[u64; 256]) -> usize {
+    let len: u64 = counts.iter().sum();
+    let mut size = DeltaEncoder::varint_len(len as u32);
+    if len == 0 {
+        return size;
+    }
+
+    let freqs = normalize(counts, len);
+    let present = freqs.iter().filter(|&&freq| freq > 0).count();
+    size += DeltaEncoder::varint_len(present as u32);
+    size += freqs
+        .iter()
+        .filter(|&&freq| freq > 0)
+        .map(|&freq| 1 + DeltaEncoder::varint_len(freq))
+        .sum::<usize>();
+
+    // Coding a symbol multiplies the state by less than
+    // `PROB_SCALE / freq * (1 + 2^-11)`, the state after renormalization
+    // being at least `2^11 * freq`, and it ends no smaller than it started.
+    // The renormalization bytes hold at most those bits; one spare byte
+    // covers float rounding.
+    let slack = (1.0 + 2f64.powi(-11)).log2();
+    let bits: f64 = counts
+        .iter()
+        .zip(&freqs)
+        .filter(|(&count, _)| count > 0)
+        .map(|(&count, &freq)| count as f64 * ((PROB_SCALE as f64 / freq as f64).log2() + slack))
+        .sum();
+    size + 4 + (bits / 8.0) as usize + 1
+}
+
+/// Byte frequencies scaled to sum to exactly [`PROB_SCALE`], keeping every
+/// present symbol at a frequency of at least one.
+fn normalized_freqs(input: &[u8]) -> [u32; 256] {
+    let mut counts = [0u64; 256];
+    for &byte in input {
+        counts[byte as usize] += 1;
+    }
+    normalize(&counts, input.len() as u64)
+}
+
+fn normalize(counts: &[u64; 256], len: u64) -> [u32; 256] {
+    let mut freqs = [0u32; 256];
+    for (freq, &count) in freqs.iter_mut().zip(counts) {
+        if count > 0 {
+            *freq = ((count * PROB_SCALE as u64 / len) as u32).max(1);
+        }
+    }
+
+    let mut total: u32 = freqs.iter().sum();
+    while total != PROB_SCALE {
+        let largest = (0..256).max_by_key(|&s| freqs[s]).unwrap();
+        if total > PROB_SCALE {
+            // Only 256 symbols exist, so an overshoot leaves the largest above one.
+            let step = (total - PROB_SCALE).min(freqs[largest] - 1);
+            freqs[largest] -= step;
+            total -= step;
+        } else {
+            freqs[largest] += PROB_SCALE - total;
+            total = PROB_SCALE;
+        }
+    }
+    freqs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode, MAX_LEN};
+    use crate::DeltaEncoder;
+    use cantor_core::{CantorError, CompressionMethod};
+
+    fn encode(input: &[u8]) -> Vec<u8> {
+        let mut out = Vec::new();
+        super::encode(input, &mut out).unwrap();
+        out
+    }
+
+    #[test]
+    fn test_rans_roundtrip() {
+        let skewed: Vec<u8> = (0..5000u32)
+            .map(|i| if i % 9 == 0 { (i % 251) as u8 } else { 2 })
+            .collect();
+        for input in [
+            vec![],
+            vec![7u8],
+            vec![3u8; 1000],
+            skewed,
+            (0..=255).collect(),
+        ] {
+            assert_eq!(decode(&encode(&input)).unwrap(), input);
+        }
+    }
+
+    #[test]
+    fn test_rans_rejects_truncated() {
+        let encoded = encode(&[1, 2, 3, 1, 1, 1, 2, 9]);
+        assert!(decode(&encoded[..encoded.len() - 3]).is_err());
+        assert!(decode(&[5, 1, 0, 1]).is_err());
+        assert!(decode(&[0, 7]).is_err());
+        // Symbol 4 listed twice.
+        assert!(decode(&[1, 2, 4, 0x80, 0x10, 4, 0x80, 0x10, 0, 0, 0x80, 0]).is_err());
+    }
+
+    #[test]
+    fn test_rans_rejects_oversized_len() {
+        // One symbol at the full scale never renormalizes, so twelve
+        // bytes would otherwise declare 2^27 of them.
+        let body = [0x80, 0x80, 0x80, 0x40, 1, 0, 0x80, 0x20, 0, 0, 0x80, 0];
+        assert!(decode(&body).is_err());
+
+        let encoder = DeltaEncoder::new(CompressionMethod::VarintRans);
+        let mut payload = Vec::new();
+        crate::frame::write_header(&mut payload, &encoder);
+        payload.extend(body);
+        assert!(DeltaEncoder::decode_any(&payload).is_err());
+
+        let mut wide = Vec::new();
+        crate::frame::write_wide_header(&mut wide, &encoder);
+        wide.extend(body);
+        assert!(encoder.decode_f64(&wide).is_err());
+    }
+
+    #[test]
+    fn test_rans_encodes_only_what_decodes() {
+        let at_limit = vec![0u8; MAX_LEN as usize];
+        assert_eq!(decode(&encode(&at_limit)).unwrap(), at_limit);
+        assert!(super::encode(&[0; MAX_LEN as usize + 1], &mut Vec::new()).is_err());
+
+        // A zero quantizes to a one-byte varint, so this delta's varints
+        // pass the limit by one.
+        let encoder = DeltaEncoder::new(CompressionMethod::VarintRans);
+        assert!(matches!(
+            encoder.encode(&vec![0.0; MAX_LEN as usize + 1]),
+            Err(CantorError::CompressionFailed(_))
+        ));
+    }
+}
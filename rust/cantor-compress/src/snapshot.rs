@@ -0,0 +1,170 @@
+//! Periodic state snapshots for fast state reconstruction.
+//!
+//! Replaying a block applies its deltas in order, each against the state
+//! produced by the one before it. A [`SnapshotStore`] keeps a full
+//! `StateVector` every `interval` blocks so that reaching block `n` only
+//! replays the blocks since the nearest snapshot at or below `n`.
+
+use std::collections::BTreeMap;
+
+use cantor_core::{CantorError, CompressionResult, Result, StateVector};
+
+use crate::DeltaReconstruct;
+
+/// How often a full snapshot is taken.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SnapshotPolicy {
+    pub interval: u64,
+}
+
+impl SnapshotPolicy {
+    /// Snapshot every `interval` blocks. An interval of zero is treated as one.
+    pub fn every(interval: u64) -> Self {
+        Self {
+            interval: interval.max(1),
+        }
+    }
+
+    pub fn is_snapshot_block(&self, block_number: u64) -> bool {
+        block_number.is_multiple_of(self.interval)
+    }
+}
+
+/// Apply every delta of `block`, in order, on top of `state`.
+pub fn apply_block(state: &StateVector, block: &CompressionResult) -> Result<StateVector> {
+    let mut data = state.data.clone();
+    for delta in &block.deltas {
+        data = delta.reconstruct(&data)?;
+    }
+    Ok(StateVector::new(data))
+}
+
+/// Snapshots taken under a [`SnapshotPolicy`], plus the blocks between them.
+pub struct SnapshotStore {
+    policy: SnapshotPolicy,
+    snapshots: BTreeMap<u64, StateVector>,
+    blocks: BTreeMap<u64, CompressionResult>,
+}
+
+impl SnapshotStore {
+    /// Replay `results` from `genesis`, which is recorded as the snapshot
+    /// for block 0. Blocks must arrive in increasing block order.
+    pub fn build(
+        policy: SnapshotPolicy,
+        genesis: StateVector,
+        results: impl IntoIterator<Item = CompressionResult>,
+    ) -> Result<Self> {
+        let mut state = genesis.clone();
+        let mut snapshots = BTreeMap::from([(0, genesis)]);
+        let mut blocks = BTreeMap::new();
+
+        for result in results {
+            if blocks
+                .keys()
+                .next_back()
+                .is_some_and(|&last| result.block_number <= last)
+            {
+                return Err(CantorError::StateReconstructionFailed(format!(
+                    "block {} is out of order",
+                    result.block_number
+                )));
+            }
+
+            state = apply_block(&state, &result)?;
+            if policy.is_snapshot_block(result.block_number) {
+                snapshots.insert(result.block_number, state.clone());
+            }
+            blocks.insert(result.block_number, result);
+        }
+
+        Ok(Self {
+            policy,
+            snapshots,
+            blocks,
+        })
+    }
+
+    pub fn policy(&self) -> SnapshotPolicy {
+        self.policy
+    }
+
+    /// Snapshot at exactly `block_number`, if one was taken.
+    pub fn snapshot(&self, block_number: u64) -> Option<&StateVector> {
+        self.snapshots.get(&block_number)
+    }
+
+    /// State after `block_number`, replayed from the nearest snapshot.
+    pub fn state_at(&self, block_number: u64) -> Result<StateVector> {
+        let (&base, snapshot) = self
+            .snapshots
+            .range(..=block_number)
+            .next_back()
+            .ok_or(CantorError::BlockNotFound(block_number))?;
+
+        if base != block_number && !self.blocks.contains_key(&block_number) {
+            return Err(CantorError::BlockNotFound(block_number));
+        }
+
+        let mut state = snapshot.clone();
+        for block in self.blocks.range(base + 1..=block_number).map(|(_, b)| b) {
+            state = apply_block(&state, block)?;
+        }
+        Ok(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CompressionMethod, DeltaEncoder};
+    use cantor_core::{Hash32, QuantizationParams, StateDelta};
+
+    fn block(number: u64) -> CompressionResult {
+        let encoder = DeltaEncoder::new(CompressionMethod::Lz4);
+        let deltas = (0..2)
+            .map(|i| {
+                let delta: Vec<f32> = (0..4)
+                    .map(|d| (number * 10 + i + d) as f32 * 0.25)
+                    .collect();
+                StateDelta {
+                    tx_hash: Hash32([number as u8; 32]),
+                    predicted_root: Hash32::ZERO,
+                    actual_root: Hash32::ZERO,
+                    delta_bytes: encoder.encode(&delta).unwrap().into(),
+                    confidence: 1.0,
+                    method: CompressionMethod::Lz4,
+                }
+            })
+            .collect();
+
+        CompressionResult {
+            block_number: number,
+            original_size: 0,
+            compressed_size: 0,
+            delta_tree_root: Hash32::ZERO,
+            deltas,
+            proofs: vec![],
+            quantization: QuantizationParams::default(),
+        }
+    }
+
+    #[test]
+    fn test_snapshot_replay_matches_full_replay() {
+        let genesis = StateVector::zeros(4);
+        let blocks: Vec<CompressionResult> = (1..=9).map(block).collect();
+        let store = SnapshotStore::build(SnapshotPolicy::every(5), genesis.clone(), blocks.clone())
+            .unwrap();
+
+        assert!(store.snapshot(5).is_some());
+        assert!(store.snapshot(7).is_none());
+
+        let mut full = genesis;
+        for b in &blocks[..7] {
+            full = apply_block(&full, b).unwrap();
+        }
+
+        let from_snapshot = store.state_at(7).unwrap();
+        assert_eq!(from_snapshot.data, full.data);
+        assert!(store.state_at(12).is_err());
+    }
+}
@@ -0,0 +1,104 @@
+//! Best-effort detection of the method behind an untagged payload.
+
+use cantor_core::CompressionMethod;
+
+use crate::{frame, rans, DeltaEncoder};
+
+/// Zstandard frame magic, little-endian `0xFD2FB528`.
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+/// Largest decoded size, in bytes, a sniffed payload may claim.
+const MAX_SNIFF_BYTES: u64 = 16 * 1024 * 1024;
+
+/// Whether a decoded value looks like a real delta element rather than
+/// reinterpreted bytes of another format, which tend to land on NaNs,
+/// infinities, subnormals or absurd exponents.
+fn plausible(value: f32) -> bool {
+    value == 0.0 || (value.is_finite() && (1e-15..=1e15).contains(&value.abs()))
+}
+
+fn decodes_plausibly(method: CompressionMethod, data: &[u8]) -> bool {
+    DeltaEncoder::new(method)
+        .with_legacy_format()
+        .decode(data)
+        .is_ok_and(|values| values.into_iter().all(plausible))
+}
+
+impl DeltaEncoder {
+    /// Guess which method produced `data`, for archives that recorded none.
+    ///
+    /// A framed payload is answered from its header. For headerless
+    /// (legacy) payloads the method is guessed: Zstd frames are recognized
+    /// by their magic. The structured formats (LZ4's size prefix,
+    /// VarintRans's frequency table and final state, SegmentedSparse's
+    /// ordered in-bounds segments) must parse exactly and decode to
+    /// plausible floats; if more than one does, the payload is ambiguous.
+    /// Otherwise RunLength is preferred when its literals are plausible,
+    /// since almost any byte string parses as Varint, which is the last
+    /// resort. Returns `None` for empty or ambiguous input.
+    ///
+    /// This is a heuristic. Tag payloads with their method whenever
+    /// possible and use this only to migrate untagged ones.
+    pub fn sniff_method(data: &[u8]) -> Option<CompressionMethod> {
+        if data.is_empty() {
+            return None;
+        }
+        if let Ok(Some(frame)) = frame::parse_any(data) {
+            return Some(frame.method);
+        }
+        if data.starts_with(&ZSTD_MAGIC) {
+            return Some(CompressionMethod::Zstd);
+        }
+
+        let structured: Vec<CompressionMethod> = [
+            (CompressionMethod::Lz4, Self::sniff_lz4 as fn(&[u8]) -> bool),
+            (CompressionMethod::VarintRans, Self::sniff_rans),
+            (CompressionMethod::SegmentedSparse, Self::sniff_segmented),
+        ]
+        .into_iter()
+        .filter(|(_, looks_like)| looks_like(data))
+        .map(|(method, _)| method)
+        .collect();
+        match structured[..] {
+            [method] => return Some(method),
+            [] => {}
+            _ => return None,
+        }
+
+        [CompressionMethod::RunLength, CompressionMethod::Varint]
+            .into_iter()
+            .find(|&method| decodes_plausibly(method, data))
+    }
+
+    fn sniff_lz4(data: &[u8]) -> bool {
+        let Some(prefix) = data.get(..4) else {
+            return false;
+        };
+        let size = u32::from_le_bytes(prefix.try_into().unwrap()) as u64;
+        // LZ4 cannot expand more than about 255x.
+        size.is_multiple_of(4)
+            && size <= MAX_SNIFF_BYTES.min(255 * data.len() as u64)
+            && decodes_plausibly(CompressionMethod::Lz4, data)
+    }
+
+    fn sniff_rans(data: &[u8]) -> bool {
+        let Some((len, _)) = Self::read_varint(data) else {
+            return false;
+        };
+        len as u64 <= MAX_SNIFF_BYTES
+            && rans::decode(data).is_ok()
+            && decodes_plausibly(CompressionMethod::VarintRans, data)
+    }
+
+    fn sniff_segmented(data: &[u8]) -> bool {
+        let mut values_plausible = true;
+        let walked = Self::walk_segments(data, |_, values| {
+            values_plausible &= values
+                .chunks_exact(4)
+                .all(|chunk| plausible(f32::from_le_bytes(chunk.try_into().unwrap())));
+            values_plausible
+        });
+        // At least one segment, so a lone length varint does not qualify.
+        walked.is_ok_and(|len| len > 0) && values_plausible && data.len() > 1
+    }
+}
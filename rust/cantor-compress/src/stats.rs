@@ -0,0 +1,255 @@
+//! Compression statistics for deltas and blocks.
+
+use std::collections::HashMap;
+
+use cantor_core::{CompressionResult, Result};
+
+use crate::{CompressionMethod, DeltaEncoder};
+
+/// Size accounting for a single encoded delta.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CompressionStats {
+    pub method: CompressionMethod,
+    pub original_size: usize,
+    pub compressed_size: usize,
+}
+
+impl CompressionStats {
+    pub fn compression_ratio(&self) -> f64 {
+        self.original_size as f64 / self.compressed_size.max(1) as f64
+    }
+}
+
+/// Running totals accumulated while compressing the deltas of a block.
+#[derive(Clone, Debug, Default)]
+pub struct BlockStats {
+    original_size: usize,
+    compressed_size: usize,
+    delta_count: usize,
+    method_counts: HashMap<CompressionMethod, usize>,
+}
+
+impl BlockStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add one delta's stats to the running totals.
+    pub fn record(&mut self, stats: &CompressionStats) {
+        self.original_size += stats.original_size;
+        self.compressed_size += stats.compressed_size;
+        self.delta_count += 1;
+        *self.method_counts.entry(stats.method).or_insert(0) += 1;
+    }
+
+    pub fn finalize(self) -> BlockSummary {
+        BlockSummary {
+            original_size: self.original_size,
+            compressed_size: self.compressed_size,
+            delta_count: self.delta_count,
+            method_counts: self.method_counts,
+        }
+    }
+}
+
+/// Aggregate compression figures for a block.
+///
+/// `original_size` and `compressed_size` are the block totals
+/// [`apply_to`](Self::apply_to) records in a `CompressionResult`.
+#[derive(Clone, Debug, Default)]
+pub struct BlockSummary {
+    pub original_size: usize,
+    pub compressed_size: usize,
+    pub delta_count: usize,
+    pub method_counts: HashMap<CompressionMethod, usize>,
+}
+
+impl BlockSummary {
+    pub fn compression_ratio(&self) -> f64 {
+        self.original_size as f64 / self.compressed_size.max(1) as f64
+    }
+
+    /// Number of deltas encoded with `method`.
+    pub fn method_count(&self, method: CompressionMethod) -> usize {
+        self.method_counts.get(&method).copied().unwrap_or(0)
+    }
+
+    /// Record the block totals in `result`'s size fields, so its
+    /// `compression_ratio` matches [`Self::compression_ratio`].
+    pub fn apply_to(&self, result: &mut CompressionResult) {
+        result.original_size = self.original_size;
+        result.compressed_size = self.compressed_size;
+    }
+}
+
+/// Compress every delta of a block with `method` and return the aggregate
+/// `(total_original, total_compressed, ratio)`.
+///
+/// The ratio is taken over the block totals, not averaged per delta, so
+/// large deltas weigh in proportion to their size.
+pub fn block_compression_ratio(
+    deltas: &[Vec<f32>],
+    method: CompressionMethod,
+) -> Result<(usize, usize, f64)> {
+    let encoder = DeltaEncoder::new(method);
+    let mut block = BlockStats::new();
+    for delta in deltas {
+        let (_, stats) = encoder.encode_with_stats(delta)?;
+        block.record(&stats);
+    }
+
+    let summary = block.finalize();
+    Ok((
+        summary.original_size,
+        summary.compressed_size,
+        summary.compression_ratio(),
+    ))
+}
+
+/// Aggregate activity of one state dimension across a sequence of deltas.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct DimStats {
+    /// Deltas that changed this dimension.
+    pub nonzero_count: usize,
+    /// Mean absolute change over every delta covering this dimension.
+    pub mean_abs: f32,
+    pub max_abs: f32,
+}
+
+/// Per-dimension statistics over decoded deltas, indexed by dimension.
+///
+/// Deltas may differ in length; the result covers the longest, and each
+/// dimension's mean is taken over the deltas long enough to include it.
+pub fn dimension_stats(deltas: &[Vec<f32>]) -> Vec<DimStats> {
+    let dimension = deltas.iter().map(Vec::len).max().unwrap_or(0);
+    let mut stats = vec![DimStats::default(); dimension];
+    let mut sums = vec![0.0f64; dimension];
+    let mut covering = vec![0usize; dimension];
+
+    for delta in deltas {
+        for (i, &value) in delta.iter().enumerate() {
+            let magnitude = value.abs();
+            if value != 0.0 {
+                stats[i].nonzero_count += 1;
+            }
+            stats[i].max_abs = stats[i].max_abs.max(magnitude);
+            sums[i] += magnitude as f64;
+            covering[i] += 1;
+        }
+    }
+
+    for ((dim, sum), count) in stats.iter_mut().zip(sums).zip(covering) {
+        dim.mean_abs = (sum / count as f64) as f32;
+    }
+    stats
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats(method: CompressionMethod, original: usize, compressed: usize) -> CompressionStats {
+        CompressionStats {
+            method,
+            original_size: original,
+            compressed_size: compressed,
+        }
+    }
+
+    #[test]
+    fn test_block_stats_aggregate() {
+        let mut block = BlockStats::new();
+        block.record(&stats(CompressionMethod::Lz4, 400, 100));
+        block.record(&stats(CompressionMethod::Lz4, 400, 200));
+        block.record(&stats(CompressionMethod::RunLength, 800, 100));
+
+        let summary = block.finalize();
+        assert_eq!(summary.original_size, 1600);
+        assert_eq!(summary.compressed_size, 400);
+        assert_eq!(summary.delta_count, 3);
+        assert!((summary.compression_ratio() - 4.0).abs() < f64::EPSILON);
+        assert_eq!(summary.method_count(CompressionMethod::Lz4), 2);
+        assert_eq!(summary.method_count(CompressionMethod::RunLength), 1);
+        assert_eq!(summary.method_count(CompressionMethod::Varint), 0);
+    }
+
+    #[test]
+    fn test_block_stats_from_encoder() {
+        use crate::DeltaEncoder;
+
+        let mut block = BlockStats::new();
+        let deltas = [vec![0.0f32; 64], vec![0.5f32; 64]];
+        let mut expected_compressed = 0;
+        for delta in &deltas {
+            let (encoded, s) = DeltaEncoder::new(CompressionMethod::RunLength)
+                .encode_with_stats(delta)
+                .unwrap();
+            expected_compressed += encoded.len();
+            block.record(&s);
+        }
+
+        let summary = block.finalize();
+        assert_eq!(summary.original_size, 2 * 64 * 4);
+        assert_eq!(summary.compressed_size, expected_compressed);
+        assert_eq!(summary.method_count(CompressionMethod::RunLength), 2);
+
+        let mut result = CompressionResult {
+            block_number: 1,
+            original_size: 0,
+            compressed_size: 0,
+            delta_tree_root: cantor_core::Hash32::ZERO,
+            deltas: Vec::new(),
+            proofs: Vec::new(),
+            quantization: Default::default(),
+        };
+        summary.apply_to(&mut result);
+        assert_eq!(result.original_size, summary.original_size);
+        assert_eq!(result.compressed_size, expected_compressed);
+        assert_eq!(result.compression_ratio(), summary.compression_ratio());
+    }
+
+    #[test]
+    fn test_block_compression_ratio() {
+        // Heavily-zero block: one non-zero entry per 1024-element delta.
+        let deltas: Vec<Vec<f32>> = (0..8)
+            .map(|i| {
+                let mut delta = vec![0.0f32; 1024];
+                delta[i * 100] = 0.5;
+                delta
+            })
+            .collect();
+
+        let (original, compressed, ratio) =
+            block_compression_ratio(&deltas, CompressionMethod::RunLength).unwrap();
+        let encoder = DeltaEncoder::new(CompressionMethod::RunLength);
+        let summed: usize = deltas.iter().map(|d| encoder.encode(d).unwrap().len()).sum();
+        assert_eq!(original, 8 * 1024 * 4);
+        assert_eq!(compressed, summed);
+        assert!((ratio - original as f64 / summed as f64).abs() < f64::EPSILON);
+
+        let (_, _, lz4_ratio) = block_compression_ratio(&deltas, CompressionMethod::Lz4).unwrap();
+        assert!(ratio > lz4_ratio, "rle {} <= lz4 {}", ratio, lz4_ratio);
+    }
+
+    #[test]
+    fn test_dimension_stats() {
+        let deltas = vec![
+            vec![0.5, 0.0, -2.0, 0.0],
+            vec![-1.5, 0.0, 0.0, 0.0],
+            vec![0.25, 0.0, 1.0, 3.0, -4.0],
+        ];
+
+        let stats = dimension_stats(&deltas);
+        assert_eq!(stats.len(), 5);
+
+        let counts: Vec<usize> = stats.iter().map(|s| s.nonzero_count).collect();
+        assert_eq!(counts, [3, 0, 2, 1, 1]);
+        let max: Vec<f32> = stats.iter().map(|s| s.max_abs).collect();
+        assert_eq!(max, [1.5, 0.0, 2.0, 3.0, 4.0]);
+
+        assert!((stats[0].mean_abs - 0.75).abs() < 1e-6);
+        assert!((stats[2].mean_abs - 1.0).abs() < 1e-6);
+        assert!((stats[4].mean_abs - 4.0).abs() < 1e-6);
+        assert!(dimension_stats(&[]).is_empty());
+    }
+}
@@ -0,0 +1,495 @@
+//! Incremental decoding of framed payloads that arrive in chunks.
+
+use cantor_core::{CantorError, CompressionMethod, Result};
+
+use crate::{frame, DeltaEncoder, RLE_LITERALS};
+
+/// Longest `Varint` encoding of a 32-bit value.
+const MAX_VARINT_LEN: usize = 5;
+
+/// Farthest back an LZ4 match may reach.
+const LZ4_WINDOW: usize = 64 * 1024;
+
+enum State {
+    /// Waiting for the rest of the frame header.
+    Header,
+    /// Decoding the body with the header's settings.
+    Body(DeltaEncoder),
+    /// Decoding an `Lz4` body sequence by sequence.
+    Lz4(Lz4Stream),
+    Finished,
+}
+
+/// Where an [`Lz4Stream`] is within the size-prefixed block.
+#[derive(Clone, Copy)]
+enum Lz4Step {
+    /// Waiting for the decompressed size.
+    Size,
+    /// Waiting for a sequence token and its literal length.
+    Token,
+    /// Copying `left` literal bytes, then a match of `match_nibble`.
+    Literals { left: usize, match_nibble: u8 },
+    /// Waiting for a match offset and its length.
+    Match { match_nibble: u8 },
+    /// The declared size has been produced.
+    Done,
+}
+
+/// Incremental reader of an `Lz4` body: the block is parsed sequence by
+/// sequence as it arrives, and only the last [`LZ4_WINDOW`] bytes of
+/// output, which later matches may copy from, are kept.
+struct Lz4Stream {
+    step: Lz4Step,
+    /// Decompressed bytes still to come.
+    remaining: usize,
+    /// Tail of the output: the match window, then any bytes of a value
+    /// not yet complete.
+    window: Vec<u8>,
+    /// Bytes of `window` already turned into values.
+    emitted: usize,
+}
+
+/// Decoder for a framed payload fed in arbitrary chunks.
+///
+/// `Varint`, `RunLength` and `Lz4` bodies are decoded as bytes arrive:
+/// only a token split across chunks is held back, and values become
+/// available from [`drain`](Self::drain) as soon as their token is
+/// complete. An LZ4 block is read sequence by sequence, keeping the last
+/// 64 KiB of output for its matches to copy from, so a large block never
+/// has to be held whole. The other methods are not streamable in their
+/// current formats, so their body is buffered and decoded by
+/// [`finish`](Self::finish).
+///
+/// The method and settings come from the frame header, as with
+/// [`DeltaEncoder::decode_any`]; headerless payloads are not supported,
+/// nor are blocks compressed against a
+/// [dictionary](DeltaEncoder::with_dictionary).
+pub struct DeltaDecoder {
+    state: State,
+    pending: Vec<u8>,
+    decoded: Vec<f32>,
+}
+
+impl Default for DeltaDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DeltaDecoder {
+    pub fn new() -> Self {
+        Self {
+            state: State::Header,
+            pending: Vec::new(),
+            decoded: Vec::new(),
+        }
+    }
+
+    /// Feed the next chunk of the payload.
+    pub fn feed(&mut self, bytes: &[u8]) -> Result<()> {
+        if matches!(self.state, State::Finished) {
+            return Err(CantorError::DecompressionFailed(
+                "chunk fed after finish".to_string(),
+            ));
+        }
+        self.pending.extend_from_slice(bytes);
+
+        if let State::Header = self.state {
+            match frame::header_len(&self.pending) {
+                Some(len) if self.pending.len() >= len => {
+                    let frame = frame::parse(&self.pending[..len])?
+                        .ok_or_else(DeltaEncoder::missing_header)?;
+                    let decoder = frame.decoder(None)?;
+                    self.state = match decoder.method {
+                        CompressionMethod::Lz4 => State::Lz4(Lz4Stream::new()),
+                        _ => State::Body(decoder),
+                    };
+                    self.pending.drain(..len);
+                }
+                _ => return Ok(()),
+            }
+        }
+
+        let decoder = match &mut self.state {
+            State::Body(decoder) => decoder,
+            State::Lz4(stream) => {
+                let consumed = stream.decode(&self.pending, &mut self.decoded)?;
+                self.pending.drain(..consumed);
+                return Ok(());
+            }
+            _ => unreachable!("the header has been parsed"),
+        };
+        let consumed = match decoder.method {
+            CompressionMethod::Varint => {
+                Self::decode_varints(decoder, &self.pending, &mut self.decoded)?
+            }
+            CompressionMethod::RunLength => {
+                Self::decode_rle_tokens(&self.pending, &mut self.decoded)?
+            }
+            _ => 0,
+        };
+        self.pending.drain(..consumed);
+        Ok(())
+    }
+
+    /// Values decoded so far, removed from the decoder as they are read.
+    pub fn drain(&mut self) -> std::vec::Drain<'_, f32> {
+        self.decoded.drain(..)
+    }
+
+    /// Mark the end of the payload, decoding a buffered body.
+    ///
+    /// A stream that ends inside the header, inside a `Varint` or
+    /// `RunLength` token, or before an LZ4 block has produced its declared
+    /// size fails with [`CantorError::InvalidDeltaEncoding`].
+    /// As with [`DeltaEncoder::decode`], a stream cut exactly at a token
+    /// boundary is indistinguishable from a shorter delta.
+    pub fn finish(&mut self) -> Result<()> {
+        match std::mem::replace(&mut self.state, State::Finished) {
+            State::Header => Err(CantorError::InvalidDeltaEncoding),
+            State::Body(decoder) => match decoder.method {
+                CompressionMethod::Varint | CompressionMethod::RunLength => {
+                    if self.pending.is_empty() {
+                        Ok(())
+                    } else {
+                        Err(CantorError::InvalidDeltaEncoding)
+                    }
+                }
+                _ => {
+                    let body = std::mem::take(&mut self.pending);
+                    self.decoded.extend(decoder.decode_body(&body)?);
+                    Ok(())
+                }
+            },
+            State::Lz4(stream) => match stream.step {
+                Lz4Step::Done => Ok(()),
+                _ => Err(CantorError::InvalidDeltaEncoding),
+            },
+            State::Finished => Ok(()),
+        }
+    }
+
+    /// Decode every complete varint in `data`, returning the bytes used.
+    fn decode_varints(decoder: &DeltaEncoder, data: &[u8], out: &mut Vec<f32>) -> Result<usize> {
+        let mut pos = 0;
+        while pos < data.len() {
+            match DeltaEncoder::read_varint(&data[pos..]) {
+                Some((value, consumed)) => {
+                    out.push(decoder.dequantize(DeltaEncoder::zigzag_decode(value)));
+                    pos += consumed;
+                }
+                None if data.len() - pos < MAX_VARINT_LEN => break,
+                None => return Err(CantorError::InvalidDeltaEncoding),
+            }
+        }
+        Ok(pos)
+    }
+
+    /// Decode every complete `RunLength` token in `data`, returning the
+    /// bytes used.
+    fn decode_rle_tokens(data: &[u8], out: &mut Vec<f32>) -> Result<usize> {
+        let mut pos = 0;
+        while let Some(&header) = data.get(pos) {
+            let count = (header & !RLE_LITERALS) as usize;
+            if count == 0 {
+                return Err(CantorError::InvalidDeltaEncoding);
+            }
+            if header & RLE_LITERALS == 0 {
+                out.extend(std::iter::repeat_n(0.0, count));
+                pos += 1;
+                continue;
+            }
+            let Some(bytes) = data.get(pos + 1..pos + 1 + count * 4) else {
+                break;
+            };
+            out.extend(
+                bytes
+                    .chunks_exact(4)
+                    .map(|chunk| f32::from_le_bytes(chunk.try_into().unwrap())),
+            );
+            pos += 1 + bytes.len();
+        }
+        Ok(pos)
+    }
+}
+
+impl Lz4Stream {
+    fn new() -> Self {
+        Self {
+            step: Lz4Step::Size,
+            remaining: 0,
+            window: Vec::new(),
+            emitted: 0,
+        }
+    }
+
+    /// Decode every complete sequence in `data`, and the available part
+    /// of a literal run, returning the bytes used. Values go to `out` as
+    /// their four bytes are produced.
+    fn decode(&mut self, data: &[u8], out: &mut Vec<f32>) -> Result<usize> {
+        let invalid = || CantorError::InvalidDeltaEncoding;
+        let mut pos = 0;
+        loop {
+            match self.step {
+                Lz4Step::Size => {
+                    let Some(size) = data.get(pos..pos + 4) else {
+                        break;
+                    };
+                    self.remaining = u32::from_le_bytes(size.try_into().unwrap()) as usize;
+                    if !self.remaining.is_multiple_of(4) {
+                        return Err(invalid());
+                    }
+                    pos += 4;
+                    self.step = Lz4Step::Token;
+                }
+                Lz4Step::Token => {
+                    let Some(&token) = data.get(pos) else {
+                        break;
+                    };
+                    let Some((left, used)) = Self::length(token >> 4, 0, &data[pos + 1..]) else {
+                        break;
+                    };
+                    if left > self.remaining {
+                        return Err(invalid());
+                    }
+                    pos += 1 + used;
+                    self.step = Lz4Step::Literals {
+                        left,
+                        match_nibble: token & 0x0f,
+                    };
+                }
+                Lz4Step::Literals { left, match_nibble } => {
+                    let take = left.min(data.len() - pos);
+                    self.window.extend_from_slice(&data[pos..pos + take]);
+                    pos += take;
+                    self.remaining -= take;
+                    if take < left {
+                        self.step = Lz4Step::Literals {
+                            left: left - take,
+                            match_nibble,
+                        };
+                        break;
+                    }
+                    // The last sequence is literals alone.
+                    self.step = if self.remaining == 0 {
+                        Lz4Step::Done
+                    } else {
+                        Lz4Step::Match { match_nibble }
+                    };
+                }
+                Lz4Step::Match { match_nibble } => {
+                    let Some(offset) = data.get(pos..pos + 2) else {
+                        break;
+                    };
+                    let offset = u16::from_le_bytes(offset.try_into().unwrap()) as usize;
+                    let Some((len, used)) = Self::length(match_nibble, 4, &data[pos + 2..]) else {
+                        break;
+                    };
+                    if offset == 0 || offset > self.window.len() || len > self.remaining {
+                        return Err(invalid());
+                    }
+                    let start = self.window.len() - offset;
+                    if offset >= len {
+                        self.window.extend_from_within(start..start + len);
+                    } else {
+                        // The match overlaps the bytes it produces.
+                        for i in start..start + len {
+                            self.window.push(self.window[i]);
+                        }
+                    }
+                    pos += 2 + used;
+                    self.remaining -= len;
+                    self.step = if self.remaining == 0 {
+                        Lz4Step::Done
+                    } else {
+                        Lz4Step::Token
+                    };
+                }
+                Lz4Step::Done if pos < data.len() => return Err(invalid()),
+                Lz4Step::Done => break,
+            }
+        }
+
+        let complete = (self.window.len() - self.emitted) / 4 * 4;
+        out.extend(
+            self.window[self.emitted..self.emitted + complete]
+                .chunks_exact(4)
+                .map(|chunk| f32::from_le_bytes(chunk.try_into().unwrap())),
+        );
+        self.emitted += complete;
+        if self.window.len() > 2 * LZ4_WINDOW {
+            let stale = self.window.len() - LZ4_WINDOW;
+            self.window.drain(..stale);
+            self.emitted -= stale;
+        }
+        Ok(pos)
+    }
+
+    /// A literal or match length: `nibble + base`, extended by the bytes
+    /// that follow in `data` when the nibble is 15. Returns the length and
+    /// the extension bytes read, or `None` if `data` ends first.
+    fn length(nibble: u8, base: usize, data: &[u8]) -> Option<(usize, usize)> {
+        let mut len = nibble as usize + base;
+        if nibble < 15 {
+            return Some((len, 0));
+        }
+        for (i, &byte) in data.iter().enumerate() {
+            len = len.saturating_add(byte as usize);
+            if byte < 255 {
+                return Some((len, i + 1));
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn delta() -> Vec<f32> {
+        (0..1000)
+            .map(|i| match i % 9 {
+                0..=2 => 0.0,
+                n => (i as f32 * 0.37).sin() * n as f32,
+            })
+            .chain([123.456])
+            .collect()
+    }
+
+    #[test]
+    fn test_chunked_decode_matches_decode() {
+        let delta = delta();
+        for method in [
+            CompressionMethod::Lz4,
+            CompressionMethod::Varint,
+            CompressionMethod::RunLength,
+            CompressionMethod::SegmentedSparse,
+            CompressionMethod::VarintRans,
+            #[cfg(feature = "zstd")]
+            CompressionMethod::Zstd,
+            CompressionMethod::DeltaOfDelta,
+            CompressionMethod::BitPack,
+        ] {
+            let encoder = DeltaEncoder::new(method);
+            let encoded = encoder.encode(&delta).unwrap();
+            let expected = encoder.decode(&encoded).unwrap();
+
+            for chunk_size in [1, 3, 7, 64, encoded.len()] {
+                let mut decoder = DeltaDecoder::new();
+                let mut values = Vec::new();
+                let mut before_finish = 0;
+                for chunk in encoded.chunks(chunk_size) {
+                    decoder.feed(chunk).unwrap();
+                    values.extend(decoder.drain());
+                    before_finish = values.len();
+                }
+                decoder.finish().unwrap();
+                values.extend(decoder.drain());
+                assert_eq!(
+                    values, expected,
+                    "{:?} in {}-byte chunks",
+                    method, chunk_size
+                );
+
+                let streamed = matches!(
+                    method,
+                    CompressionMethod::Varint
+                        | CompressionMethod::RunLength
+                        | CompressionMethod::Lz4
+                );
+                assert_eq!(before_finish == values.len(), streamed, "{:?}", method);
+            }
+        }
+    }
+
+    #[test]
+    fn test_lz4_streams_past_its_window() {
+        // Long enough that matches reach back across the trimmed window.
+        let delta: Vec<f32> = (0..200_000)
+            .map(|i| ((i % 1000) as f32 * 0.01).sin() + (i / 7000) as f32)
+            .collect();
+        for encoder in [
+            DeltaEncoder::new(CompressionMethod::Lz4),
+            DeltaEncoder::with_lz4_mode(crate::Lz4Mode::HighCompression(12)),
+        ] {
+            let encoded = encoder.encode(&delta).unwrap();
+            let mut decoder = DeltaDecoder::new();
+            let mut values = Vec::new();
+            for chunk in encoded.chunks(4093) {
+                decoder.feed(chunk).unwrap();
+                let before = values.len();
+                values.extend(decoder.drain());
+                // Memory is the window plus this chunk's output.
+                if let State::Lz4(stream) = &decoder.state {
+                    let produced = 4 * (values.len() - before);
+                    assert!(stream.window.len() <= 2 * LZ4_WINDOW + produced);
+                }
+            }
+            decoder.finish().unwrap();
+            assert_eq!(values, delta);
+
+            // A stream that stops early, or runs on, is rejected.
+            let mut decoder = DeltaDecoder::new();
+            decoder.feed(&encoded[..encoded.len() - 1]).unwrap();
+            assert!(matches!(
+                decoder.finish(),
+                Err(CantorError::InvalidDeltaEncoding)
+            ));
+            let mut decoder = DeltaDecoder::new();
+            decoder.feed(&encoded).unwrap();
+            assert!(decoder.feed(&[0]).is_err());
+        }
+
+        // Corrupt blocks fail or decode to something, but never panic.
+        let encoded = DeltaEncoder::new(CompressionMethod::Lz4)
+            .encode(&delta[..500])
+            .unwrap();
+        for i in 0..encoded.len() {
+            let mut flipped = encoded.clone();
+            flipped[i] ^= 0x5a;
+            let mut decoder = DeltaDecoder::new();
+            let fed = flipped.chunks(7).try_for_each(|chunk| decoder.feed(chunk));
+            let _ = fed.and_then(|()| decoder.finish());
+        }
+
+        // A match reaching before the start of the output.
+        let header = &DeltaEncoder::new(CompressionMethod::Lz4)
+            .encode(&[])
+            .unwrap()[..];
+        let body_start = header.len() - 5;
+        let mut forged = header[..body_start].to_vec();
+        forged.extend_from_slice(&[8, 0, 0, 0, 0x40, 1, 2, 3, 4, 9, 0]);
+        let mut decoder = DeltaDecoder::new();
+        assert!(matches!(
+            decoder.feed(&forged),
+            Err(CantorError::InvalidDeltaEncoding)
+        ));
+    }
+
+    #[test]
+    fn test_truncated_stream() {
+        let delta = delta();
+        for method in [CompressionMethod::Varint, CompressionMethod::RunLength] {
+            let encoded = DeltaEncoder::new(method).encode(&delta).unwrap();
+            // Both end on a multi-byte token: a 3-byte varint or a literal.
+            let mut decoder = DeltaDecoder::new();
+            decoder.feed(&encoded[..encoded.len() - 1]).unwrap();
+            assert!(matches!(
+                decoder.finish(),
+                Err(CantorError::InvalidDeltaEncoding)
+            ));
+            assert!(decoder.feed(&encoded[encoded.len() - 1..]).is_err());
+        }
+
+        let mut decoder = DeltaDecoder::new();
+        decoder.feed(&crate::FRAME_MAGIC).unwrap();
+        assert!(matches!(
+            decoder.finish(),
+            Err(CantorError::InvalidDeltaEncoding)
+        ));
+
+        let mut decoder = DeltaDecoder::new();
+        assert!(decoder.feed(b"not a frame").is_err());
+    }
+}
@@ -0,0 +1,282 @@
+//! `f64` deltas, for state channels that accumulate in double precision.
+//!
+//! The bodies mirror their `f32` forms with 8-byte elements: `Lz4` and
+//! `Zstd` compress the little-endian `f64` bytes, and `Varint` and
+//! `VarintRans` store `round(value * scale)` as a 64-bit zigzag varint of
+//! up to ten bytes. The frame header marks the body as `f64`, so neither
+//! width decodes as the other.
+
+use cantor_core::{CantorError, CompressionMethod, Result};
+
+use crate::{frame, rans, zstd_frame, DeltaEncoder};
+
+impl DeltaEncoder {
+    /// Encode a delta of `f64`s, for `Lz4`, `Zstd`, `Varint` and
+    /// `VarintRans` encoders.
+    ///
+    /// As with [`Self::encode`], NaN and infinite elements fail with
+    /// `CantorError::NonFiniteDelta`. `Lz4` and `Zstd` are bit-exact;
+    /// the varint methods quantize at the encoder's scale, with the range
+    /// widened to `|value| < 2^63 / scale`. Other methods, normalization
+    /// and legacy mode fail with `CantorError::CompressionFailed`. Decode
+    /// with [`Self::decode_f64`]; [`Self::decode`] rejects the payload.
+    pub fn encode_f64(&self, delta: &[f64]) -> Result<Vec<u8>> {
+        if let Some(index) = delta.iter().position(|v| !v.is_finite()) {
+            return Err(CantorError::NonFiniteDelta { index });
+        }
+        if !frame::has_wide_form(self.method) {
+            return Err(CantorError::CompressionFailed(format!(
+                "{:?} has no f64 encoding",
+                self.method
+            )));
+        }
+        if self.normalizes() || self.legacy {
+            return Err(CantorError::CompressionFailed(
+                "f64 encoding supports neither normalization nor the legacy format".to_string(),
+            ));
+        }
+
+        let mut out = Vec::new();
+        frame::write_wide_header(&mut out, self);
+        let mut scratch = Vec::with_capacity(delta.len() * 8);
+        match self.method {
+            CompressionMethod::Lz4 | CompressionMethod::Zstd => {
+                scratch.extend(delta.iter().flat_map(|v| v.to_le_bytes()));
+                if self.method == CompressionMethod::Lz4 {
+                    self.compress_lz4(&scratch, &mut out)?;
+                } else {
+                    self.compress_zstd(&scratch, &mut out)?;
+                }
+            }
+            CompressionMethod::Varint => self.encode_varint64(delta, &mut out)?,
+            CompressionMethod::VarintRans => {
+                self.encode_varint64(delta, &mut scratch)?;
+                rans::encode(&scratch, &mut out)?;
+            }
+            _ => unreachable!("checked by has_wide_form"),
+        }
+        Ok(out)
+    }
+
+    /// Decode a payload produced by [`Self::encode_f64`] with the same
+    /// method, or any method for an `Auto` decoder. The header decides the
+    /// settings, as for [`Self::decode`]; `f32` payloads are rejected.
+    pub fn decode_f64(&self, data: &[u8]) -> Result<Vec<f64>> {
+        let frame = frame::parse_any(data)?.ok_or_else(Self::missing_header)?;
+        if !frame.wide {
+            return Err(CantorError::DecompressionFailed(
+                "payload holds f32 elements; decode it with decode".to_string(),
+            ));
+        }
+        if frame.method != self.method && self.method != CompressionMethod::Auto {
+            return Err(CantorError::DecompressionFailed(format!(
+                "payload is {:?}, decoder expects {:?}",
+                frame.method, self.method
+            )));
+        }
+        let decoder = frame.decoder(self.dictionary.as_ref())?;
+
+        let mut out = Vec::new();
+        match decoder.method {
+            CompressionMethod::Lz4 => {
+                Self::extend_from_le_bytes64(&decoder.decompress_lz4(frame.body)?, &mut out)?
+            }
+            CompressionMethod::Zstd => {
+                Self::extend_from_le_bytes64(&zstd_frame::decompress(frame.body)?, &mut out)?
+            }
+            CompressionMethod::Varint => decoder.decode_varint64(frame.body, &mut out)?,
+            CompressionMethod::VarintRans => {
+                decoder.decode_varint64(&rans::decode(frame.body)?, &mut out)?
+            }
+            _ => unreachable!("checked by frame::parse_any"),
+        }
+        Ok(out)
+    }
+
+    /// Append the little-endian `f64`s of `bytes` to `out`.
+    fn extend_from_le_bytes64(bytes: &[u8], out: &mut Vec<f64>) -> Result<()> {
+        if !bytes.len().is_multiple_of(8) {
+            return Err(CantorError::InvalidDeltaEncoding);
+        }
+        out.reserve(bytes.len() / 8);
+        out.extend(
+            bytes
+                .chunks_exact(8)
+                .map(|chunk| f64::from_le_bytes(chunk.try_into().unwrap())),
+        );
+        Ok(())
+    }
+
+    fn encode_varint64(&self, delta: &[f64], out: &mut Vec<u8>) -> Result<()> {
+        out.reserve(delta.len() * 2);
+        let scale = self.scale as f64;
+        let limit = 2f64.powi(63);
+        for (i, &val) in delta.iter().enumerate() {
+            let scaled = (val * scale).round();
+            // `as` would saturate; reject instead.
+            if !(-limit..limit).contains(&scaled) {
+                return Err(CantorError::CompressionFailed(format!(
+                    "element {} ({}) at scale {} does not fit a 64-bit varint",
+                    i, val, self.scale
+                )));
+            }
+            write_varint64(out, zigzag_encode64(scaled as i64));
+        }
+        Ok(())
+    }
+
+    fn decode_varint64(&self, data: &[u8], out: &mut Vec<f64>) -> Result<()> {
+        let scale = self.scale as f64;
+        let mut pos = 0;
+        while pos < data.len() {
+            let (value, consumed) =
+                read_varint64(&data[pos..]).ok_or(CantorError::InvalidVarint { position: pos })?;
+            out.push(zigzag_decode64(value) as f64 / scale);
+            pos += consumed;
+        }
+        Ok(())
+    }
+}
+
+fn zigzag_encode64(n: i64) -> u64 {
+    ((n << 1) ^ (n >> 63)) as u64
+}
+
+fn zigzag_decode64(n: u64) -> i64 {
+    ((n >> 1) as i64) ^ -((n & 1) as i64)
+}
+
+fn write_varint64(buf: &mut Vec<u8>, mut n: u64) {
+    while n >= 0x80 {
+        buf.push((n as u8) | 0x80);
+        n >>= 7;
+    }
+    buf.push(n as u8);
+}
+
+/// A varint at the start of `data` and the bytes it took, or `None` if it
+/// is cut short or does not fit 64 bits.
+fn read_varint64(data: &[u8]) -> Option<(u64, usize)> {
+    let mut result = 0u64;
+    for (i, &byte) in data.iter().enumerate().take(10) {
+        // The tenth byte has room for one more bit, not seven.
+        if i == 9 && byte & 0x7e != 0 {
+            return None;
+        }
+        result |= ((byte & 0x7f) as u64) << (7 * i);
+        if byte & 0x80 == 0 {
+            return Some((result, i + 1));
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bits(values: &[f64]) -> Vec<u64> {
+        values.iter().map(|v| v.to_bits()).collect()
+    }
+
+    #[test]
+    fn test_f64_lossless_roundtrip() {
+        // 1 + 1e-12 and 1e-300 have no f32 form.
+        let delta = vec![0.1, -0.0, 1.0 + 1e-12, 0.0, 1e-300, -7.25, f64::MAX];
+        for encoder in [
+            DeltaEncoder::new(CompressionMethod::Lz4),
+            DeltaEncoder::with_lz4_mode(crate::Lz4Mode::HighCompression(9)),
+            #[cfg(feature = "zstd")]
+            DeltaEncoder::with_zstd_level(3),
+        ] {
+            let encoded = encoder.encode_f64(&delta).unwrap();
+            assert_eq!(bits(&encoder.decode_f64(&encoded).unwrap()), bits(&delta));
+            let any = DeltaEncoder::new(CompressionMethod::Auto);
+            assert_eq!(bits(&any.decode_f64(&encoded).unwrap()), bits(&delta));
+            assert_eq!(DeltaEncoder::sniff_method(&encoded), Some(encoder.method));
+        }
+        assert!(DeltaEncoder::new(CompressionMethod::Lz4)
+            .decode_f64(
+                &DeltaEncoder::new(CompressionMethod::Lz4)
+                    .encode_f64(&[])
+                    .unwrap()
+            )
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_f64_varint_roundtrip_and_range() {
+        let delta = vec![0.001, -0.002, 0.0, 0.5, 2.2e6, -4.0e12];
+        for method in [CompressionMethod::Varint, CompressionMethod::VarintRans] {
+            let encoder = DeltaEncoder::new(method);
+            let decoded = encoder
+                .decode_f64(&encoder.encode_f64(&delta).unwrap())
+                .unwrap();
+            assert_eq!(decoded.len(), delta.len());
+            for (a, b) in delta.iter().zip(&decoded) {
+                assert!((a - b).abs() < 0.001, "{:?}: {} -> {}", method, a, b);
+            }
+        }
+
+        // Past the 32-bit range of the f32 varint, short of the 64-bit one.
+        let fine = DeltaEncoder::with_varint_scale(1e6);
+        let decoded = fine
+            .decode_f64(&fine.encode_f64(&[5000.123456]).unwrap())
+            .unwrap();
+        assert!((decoded[0] - 5000.123456).abs() < 1e-6);
+        let err = fine.encode_f64(&[1.0, 1e13]).unwrap_err();
+        let CantorError::CompressionFailed(message) = err else {
+            panic!("{}", err);
+        };
+        assert!(message.contains("element 1"), "{}", message);
+
+        let mut truncated = fine.encode_f64(&[1e12]).unwrap();
+        truncated.pop();
+        assert!(matches!(
+            fine.decode_f64(&truncated),
+            Err(CantorError::InvalidVarint { .. })
+        ));
+    }
+
+    #[test]
+    fn test_zigzag64() {
+        for n in [0, 1, -1, 63, -64, i64::MAX, i64::MIN] {
+            let encoded = zigzag_encode64(n);
+            assert_eq!(zigzag_decode64(encoded), n);
+            let mut buf = Vec::new();
+            write_varint64(&mut buf, encoded);
+            assert!(buf.len() <= 10);
+            assert_eq!(read_varint64(&buf), Some((encoded, buf.len())));
+        }
+        assert_eq!(zigzag_encode64(-1), 1);
+        assert_eq!(
+            read_varint64(&[0xff; 9].iter().chain(&[0x02]).copied().collect::<Vec<_>>()),
+            None
+        );
+    }
+
+    #[test]
+    fn test_f64_and_f32_payloads_kept_apart() {
+        let encoder = DeltaEncoder::new(CompressionMethod::Lz4);
+        let wide = encoder.encode_f64(&[1.0, 2.0]).unwrap();
+        let narrow = encoder.encode(&[1.0, 2.0]).unwrap();
+        assert!(encoder.decode(&wide).is_err());
+        assert!(DeltaEncoder::decode_any(&wide).is_err());
+        assert!(encoder.decode_f64(&narrow).is_err());
+        assert!(DeltaEncoder::new(CompressionMethod::Zstd)
+            .decode_f64(&wide)
+            .is_err());
+
+        for method in [CompressionMethod::RunLength, CompressionMethod::Float16] {
+            assert!(matches!(
+                DeltaEncoder::new(method).encode_f64(&[1.0]),
+                Err(CantorError::CompressionFailed(_))
+            ));
+        }
+        assert!(matches!(
+            encoder.encode_f64(&[0.0, f64::NAN]),
+            Err(CantorError::NonFiniteDelta { index: 1 })
+        ));
+    }
+}
@@ -0,0 +1,155 @@
+//! Zstd frame backend.
+//!
+//! Payloads are single Zstd frames that declare their content size. The C
+//! library is behind the default `zstd` feature; without it `Zstd`
+//! payloads neither encode nor decode, failing with
+//! `CantorError::CompressionFailed` or `CantorError::DecompressionFailed`,
+//! and `Auto` leaves the method out of its candidates. With it, bodies
+//! over 16 MiB fail the same way in both directions, and `Auto` passes
+//! over Zstd for them.
+
+use cantor_core::{CantorError, Result};
+
+/// Compression level used unless one is configured: the library's
+/// default.
+pub(crate) const DEFAULT_LEVEL: i32 = 3;
+
+/// Upper bound on the decompressed size a Zstd frame may declare.
+#[cfg(feature = "zstd")]
+const MAX_CONTENT: u64 = 16 * 1024 * 1024;
+
+/// Worst-case frame length for `len` input bytes, the library's
+/// `ZSTD_COMPRESSBOUND`.
+pub(crate) fn compressed_len_bound(len: usize) -> usize {
+    const BLOCK: usize = 128 << 10;
+    len + (len >> 8) + BLOCK.saturating_sub(len) / 2048
+}
+
+/// Compress `src` at `level`, appending the frame to `out`. Inputs over
+/// [`MAX_CONTENT`] bytes, which [`decompress`] would refuse, fail with
+/// `CantorError::CompressionFailed`.
+#[cfg(feature = "zstd")]
+pub(crate) fn compress_into(src: &[u8], level: i32, out: &mut Vec<u8>) -> Result<()> {
+    if src.len() as u64 > MAX_CONTENT {
+        return Err(CantorError::CompressionFailed(format!(
+            "{} bytes exceed the Zstd limit of {}",
+            src.len(),
+            MAX_CONTENT
+        )));
+    }
+    let start = out.len();
+    out.resize(start + zstd::zstd_safe::compress_bound(src.len()), 0);
+    let written = zstd::bulk::compress_to_buffer(src, &mut out[start..], level)
+        .map_err(|e| CantorError::CompressionFailed(e.to_string()))?;
+    out.truncate(start + written);
+    Ok(())
+}
+
+/// Decompress a frame, which must declare its content size so the output
+/// is allocated once and bounded by [`MAX_CONTENT`].
+#[cfg(feature = "zstd")]
+pub(crate) fn decompress(data: &[u8]) -> Result<Vec<u8>> {
+    let size = match zstd::zstd_safe::get_frame_content_size(data) {
+        Ok(Some(size)) if size <= MAX_CONTENT => size as usize,
+        Ok(Some(size)) => {
+            return Err(CantorError::DecompressionFailed(format!(
+                "zstd frame declares {} bytes",
+                size
+            )))
+        }
+        _ => {
+            return Err(CantorError::DecompressionFailed(
+                "zstd frame has no content size".to_string(),
+            ))
+        }
+    };
+    let decompressed = zstd::bulk::decompress(data, size)
+        .map_err(|e| CantorError::DecompressionFailed(e.to_string()))?;
+
+    if decompressed.len() != size {
+        return Err(CantorError::InvalidDeltaEncoding);
+    }
+    Ok(decompressed)
+}
+
+#[cfg(not(feature = "zstd"))]
+const DISABLED: &str = "Zstd support needs the `zstd` feature";
+
+#[cfg(not(feature = "zstd"))]
+pub(crate) fn compress_into(_src: &[u8], _level: i32, _out: &mut Vec<u8>) -> Result<()> {
+    Err(CantorError::CompressionFailed(DISABLED.to_string()))
+}
+
+#[cfg(not(feature = "zstd"))]
+pub(crate) fn decompress(_data: &[u8]) -> Result<Vec<u8>> {
+    Err(CantorError::DecompressionFailed(DISABLED.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn test_bound_matches_library() {
+        for len in [0, 1, 255, 4096, 128 << 10, (128 << 10) + 1, 1 << 20] {
+            assert_eq!(
+                compressed_len_bound(len),
+                zstd::zstd_safe::compress_bound(len),
+                "{} bytes",
+                len
+            );
+        }
+        assert_eq!(DEFAULT_LEVEL, zstd::DEFAULT_COMPRESSION_LEVEL);
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn test_compresses_only_what_decompresses() {
+        let mut frame = Vec::new();
+        let at_limit = vec![0u8; MAX_CONTENT as usize];
+        compress_into(&at_limit, DEFAULT_LEVEL, &mut frame).unwrap();
+        assert_eq!(decompress(&frame).unwrap(), at_limit);
+        assert!(matches!(
+            compress_into(&[0; MAX_CONTENT as usize + 1], DEFAULT_LEVEL, &mut Vec::new()),
+            Err(CantorError::CompressionFailed(_))
+        ));
+
+        // Auto leaves Zstd out past the limit rather than failing.
+        let long = vec![0.0f32; MAX_CONTENT as usize / 4 + 1];
+        let zstd = crate::DeltaEncoder::new(cantor_core::CompressionMethod::Zstd);
+        assert!(zstd.encode(&long).is_err());
+        let auto = crate::DeltaEncoder::new(cantor_core::CompressionMethod::Auto);
+        let encoded = auto.encode(&long).unwrap();
+        assert_ne!(
+            crate::DeltaEncoder::sniff_method(&encoded),
+            Some(cantor_core::CompressionMethod::Zstd)
+        );
+        assert_eq!(auto.decode(&encoded).unwrap(), long);
+    }
+
+    #[cfg(not(feature = "zstd"))]
+    #[test]
+    fn test_disabled_backend_errors() {
+        assert!(matches!(
+            compress_into(b"delta", DEFAULT_LEVEL, &mut Vec::new()),
+            Err(CantorError::CompressionFailed(_))
+        ));
+        assert!(matches!(
+            decompress(&[0x28, 0xb5, 0x2f, 0xfd]),
+            Err(CantorError::DecompressionFailed(_))
+        ));
+
+        let encoder = crate::DeltaEncoder::new(cantor_core::CompressionMethod::Zstd);
+        assert!(matches!(
+            encoder.encode(&[1.0]),
+            Err(CantorError::CompressionFailed(_))
+        ));
+        // Auto still encodes, choosing among the remaining candidates.
+        let auto = crate::DeltaEncoder::new(cantor_core::CompressionMethod::Auto);
+        assert_eq!(
+            auto.decode(&auto.encode(&[1.0, 0.0]).unwrap()).unwrap(),
+            [1.0, 0.0]
+        );
+    }
+}
@@ -0,0 +1,56 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use cantor_core::{Hash32, Hash32Map, StateVector};
+use std::collections::HashMap;
+
+fn keys() -> Vec<Hash32> {
+    (0..10000u32)
+        .map(|i| StateVector::new(vec![i as f32]).compute_hash())
+        .collect()
+}
+
+fn bench_insert(c: &mut Criterion) {
+    let keys = keys();
+    let mut group = c.benchmark_group("hash32_map_insert");
+    
+    group.bench_function("sip", |b| {
+        b.iter(|| {
+            let mut map = HashMap::with_capacity(keys.len());
+            for (i, key) in keys.iter().enumerate() {
+                map.insert(*key, i);
+            }
+            black_box(map)
+        });
+    });
+    
+    group.bench_function("hash32", |b| {
+        b.iter(|| {
+            let mut map = Hash32Map::with_capacity_and_hasher(keys.len(), Default::default());
+            for (i, key) in keys.iter().enumerate() {
+                map.insert(*key, i);
+            }
+            black_box(map)
+        });
+    });
+    
+    group.finish();
+}
+
+fn bench_lookup(c: &mut Criterion) {
+    let keys = keys();
+    let sip: HashMap<Hash32, usize> = keys.iter().copied().zip(0..).collect();
+    let fast: Hash32Map<usize> = keys.iter().copied().zip(0..).collect();
+    let mut group = c.benchmark_group("hash32_map_lookup");
+    
+    group.bench_function("sip", |b| {
+        b.iter(|| keys.iter().filter(|k| sip.contains_key(black_box(*k))).count());
+    });
+    
+    group.bench_function("hash32", |b| {
+        b.iter(|| keys.iter().filter(|k| fast.contains_key(black_box(*k))).count());
+    });
+    
+    group.finish();
+}
+
+criterion_group!(benches, bench_insert, bench_lookup);
+criterion_main!(benches);
@@ -0,0 +1,15 @@
+//! Streaming commitments to a block's deltas.
+
+use crate::types::Hash32;
+
+/// Root over delta payloads fed one at a time, so a block can be
+/// committed to while it is encoded by code that does not know the tree
+/// behind the root; `cantor-merkle`'s `MerkleAccumulator` is the one
+/// matching `MerkleDeltaTree::build`.
+pub trait RootAccumulator {
+    /// Append the next delta payload.
+    fn push_delta(&mut self, delta: &[u8]);
+
+    /// Root over the payloads pushed so far.
+    fn root(&self) -> Hash32;
+}
@@ -0,0 +1,101 @@
+//! Canonical state reconstruction.
+//!
+//! `actual = predicted + delta` must produce bit-identical results on every
+//! producer and verifier, or the committed `actual_root` will not match.
+//! The canonical form is:
+//!
+//! - one IEEE-754 binary32 addition per element, round-to-nearest-even,
+//!   performed in index order with no reassociation or fused operations
+//!   (Rust never contracts `a + b` into an FMA);
+//! - every NaN result replaced by the canonical quiet NaN `0x7fc0_0000`,
+//!   since NaN payload propagation differs between architectures.
+//!
+//! Both the producer's root computation and the verifier go through
+//! [`reconstruct_canonical`].
+
+use crate::error::{CantorError, Result};
+use crate::types::{Hash32, StateVector};
+
+/// Bit pattern every NaN is normalized to.
+pub const CANONICAL_NAN_BITS: u32 = 0x7fc0_0000;
+
+/// Reconstruct `predicted + delta` in canonical form.
+pub fn reconstruct_canonical(predicted: &[f32], delta: &[f32]) -> Result<Vec<f32>> {
+    let mut out = Vec::new();
+    reconstruct_canonical_into(predicted, delta, &mut out)?;
+    Ok(out)
+}
+
+/// [`reconstruct_canonical`] into `out`, replacing its contents but
+/// keeping its capacity. `out` is left empty on error.
+pub fn reconstruct_canonical_into(predicted: &[f32], delta: &[f32], out: &mut Vec<f32>) -> Result<()> {
+    out.clear();
+    if predicted.len() != delta.len() {
+        return Err(CantorError::StateReconstructionFailed(format!(
+            "delta has {} dimensions, predicted state has {}",
+            delta.len(),
+            predicted.len()
+        )));
+    }
+
+    out.reserve(predicted.len());
+    for i in 0..predicted.len() {
+        let sum = predicted[i] + delta[i];
+        out.push(if sum.is_nan() {
+            f32::from_bits(CANONICAL_NAN_BITS)
+        } else {
+            sum
+        });
+    }
+    Ok(())
+}
+
+/// Hash of the canonical reconstruction, i.e. the expected `actual_root`.
+pub fn canonical_actual_root(predicted: &[f32], delta: &[f32]) -> Result<Hash32> {
+    let actual = reconstruct_canonical(predicted, delta)?;
+    Ok(StateVector::new(actual).compute_hash())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture() -> (Vec<f32>, Vec<f32>) {
+        let predicted = (0..1024).map(|i| (i as f32 * 0.37).sin() * 1e3).collect();
+        let delta = (0..1024).map(|i| (i as f32 * 1.3).cos() * 1e-4).collect();
+        (predicted, delta)
+    }
+
+    #[test]
+    fn test_matches_exact_reference() {
+        // The f64 sum is not always exact: the fixture's exponents can
+        // differ by more than 29 bits. But f64 carries at least 2 * 24 + 2
+        // bits, so rounding the sum to f64 and then to f32 still gives the
+        // correctly rounded f32 sum, a platform-independent reference.
+        let (predicted, delta) = fixture();
+        let canonical = reconstruct_canonical(&predicted, &delta).unwrap();
+        for ((c, p), d) in canonical.iter().zip(&predicted).zip(&delta) {
+            let reference = (*p as f64 + *d as f64) as f32;
+            assert_eq!(c.to_bits(), reference.to_bits());
+        }
+    }
+
+    #[test]
+    fn test_nan_is_canonicalized() {
+        let quiet_payload = f32::from_bits(0x7fc0_1234);
+        let negative_nan = f32::from_bits(0xffc0_0000);
+        let out = reconstruct_canonical(
+            &[quiet_payload, 1.0, f32::INFINITY],
+            &[0.0, negative_nan, f32::NEG_INFINITY],
+        )
+        .unwrap();
+        for value in out {
+            assert_eq!(value.to_bits(), CANONICAL_NAN_BITS);
+        }
+    }
+
+    #[test]
+    fn test_dimension_mismatch() {
+        assert!(reconstruct_canonical(&[1.0, 2.0], &[1.0]).is_err());
+    }
+}
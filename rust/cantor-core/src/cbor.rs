@@ -0,0 +1,190 @@
+//! CBOR encoding of verification proofs (feature `cbor`).
+//!
+//! A proof is one flat CBOR map with text keys, so consumers in other
+//! languages can read it without knowing the bincode layout:
+//!
+//! | key               | CBOR type                      |
+//! |-------------------|--------------------------------|
+//! | `tx_hash`         | byte string, 32 bytes          |
+//! | `predicted_state` | byte string, 32 bytes          |
+//! | `predicted_root`  | byte string, 32 bytes          |
+//! | `actual_root`     | byte string, 32 bytes          |
+//! | `delta_bytes`     | byte string, encoded delta     |
+//! | `delta_hash`      | byte string, 32 bytes          |
+//! | `confidence`      | float                          |
+//! | `method`          | text, e.g. `"Lz4"`             |
+//! | `leaf_hash`       | byte string, 32 bytes          |
+//! | `path`            | array of 32-byte byte strings  |
+//! | `indices`         | byte string, one byte per step |
+//! | `model_version`   | text                           |
+//! | `tree_hasher`     | map: `algorithm`, `version`    |
+//!
+//! No CBOR tags are used: every [`Hash32`] is an untagged 32-byte byte
+//! string, and `delta_bytes` is the untagged payload exactly as produced
+//! by its `method`. A proof carries exactly one of `delta_bytes` and
+//! `delta_hash`, the latter for a delta referenced by hash
+//! ([`DeltaRef::ByHash`]). Unknown keys are ignored when decoding, and a
+//! missing `tree_hasher` reads as the default SHA-256 `V1`.
+
+use ciborium::Value;
+
+use crate::error::{CantorError, Result};
+use crate::hasher::TreeHasher;
+use crate::serialization::MAX_SERIALIZED_LEN;
+use crate::types::{DeltaRef, Hash32, MerkleProof, StateDelta, VerificationProof};
+
+fn err(detail: impl std::fmt::Display) -> CantorError {
+    CantorError::Serialization(format!("cbor: {}", detail))
+}
+
+fn hash(h: &Hash32) -> Value {
+    Value::Bytes(h.0.to_vec())
+}
+
+fn field<'a>(map: &'a [(Value, Value)], key: &str) -> Result<&'a Value> {
+    map.iter()
+        .find(|(k, _)| k.as_text() == Some(key))
+        .map(|(_, v)| v)
+        .ok_or_else(|| err(format!("missing `{}`", key)))
+}
+
+fn bytes_field(map: &[(Value, Value)], key: &str) -> Result<Vec<u8>> {
+    field(map, key)?
+        .as_bytes()
+        .cloned()
+        .ok_or_else(|| err(format!("`{}` is not a byte string", key)))
+}
+
+fn as_hash(value: &Value, key: &str) -> Result<Hash32> {
+    value
+        .as_bytes()
+        .and_then(|b| Hash32::from_slice(b))
+        .ok_or_else(|| err(format!("`{}` is not a 32-byte byte string", key)))
+}
+
+fn hash_field(map: &[(Value, Value)], key: &str) -> Result<Hash32> {
+    as_hash(field(map, key)?, key)
+}
+
+impl VerificationProof {
+    /// Encode as a flat CBOR map; see [`crate::cbor`] for the layout.
+    pub fn to_cbor(&self) -> Result<Vec<u8>> {
+        let method = Value::serialized(&self.delta.method).map_err(err)?;
+        let tree_hasher = Value::serialized(&self.tree_hasher).map_err(err)?;
+        let map = Value::Map(vec![
+            ("tx_hash".into(), hash(&self.tx_hash)),
+            ("predicted_state".into(), hash(&self.predicted_state)),
+            ("predicted_root".into(), hash(&self.delta.predicted_root)),
+            ("actual_root".into(), hash(&self.delta.actual_root)),
+            match &self.delta.delta_bytes {
+                DeltaRef::Inline(bytes) => ("delta_bytes".into(), Value::Bytes(bytes.clone())),
+                DeltaRef::ByHash(h) => ("delta_hash".into(), hash(h)),
+            },
+            (
+                "confidence".into(),
+                Value::Float(self.delta.confidence as f64),
+            ),
+            ("method".into(), method),
+            ("leaf_hash".into(), hash(&self.merkle_proof.leaf_hash)),
+            (
+                "path".into(),
+                Value::Array(self.merkle_proof.path.iter().map(hash).collect()),
+            ),
+            (
+                "indices".into(),
+                Value::Bytes(self.merkle_proof.indices.clone()),
+            ),
+            (
+                "model_version".into(),
+                Value::Text(self.model_version.clone()),
+            ),
+            ("tree_hasher".into(), tree_hasher),
+        ]);
+
+        let mut out = Vec::new();
+        ciborium::into_writer(&map, &mut out).map_err(err)?;
+        Ok(out)
+    }
+
+    /// Decode a proof produced by [`Self::to_cbor`].
+    ///
+    /// Input longer than [`MAX_SERIALIZED_LEN`] is rejected up front.
+    pub fn from_cbor(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() as u64 > MAX_SERIALIZED_LEN {
+            return Err(err(format!("{} bytes exceeds the size limit", bytes.len())));
+        }
+        let value: Value = ciborium::from_reader(bytes).map_err(err)?;
+        let map = value.as_map().ok_or_else(|| err("proof is not a map"))?;
+
+        let tx_hash = hash_field(map, "tx_hash")?;
+        let path = field(map, "path")?
+            .as_array()
+            .ok_or_else(|| err("`path` is not an array"))?
+            .iter()
+            .map(|v| as_hash(v, "path"))
+            .collect::<Result<Vec<_>>>()?;
+        let confidence = field(map, "confidence")?
+            .as_float()
+            .ok_or_else(|| err("`confidence` is not a float"))?;
+        let model_version = field(map, "model_version")?
+            .as_text()
+            .ok_or_else(|| err("`model_version` is not text"))?
+            .to_string();
+
+        Ok(VerificationProof {
+            tx_hash,
+            predicted_state: hash_field(map, "predicted_state")?,
+            delta: StateDelta {
+                tx_hash,
+                predicted_root: hash_field(map, "predicted_root")?,
+                actual_root: hash_field(map, "actual_root")?,
+                delta_bytes: match field(map, "delta_hash") {
+                    Ok(h) => DeltaRef::ByHash(as_hash(h, "delta_hash")?),
+                    Err(_) => DeltaRef::Inline(bytes_field(map, "delta_bytes")?),
+                },
+                confidence: confidence as f32,
+                method: field(map, "method")?.deserialized().map_err(err)?,
+            },
+            merkle_proof: MerkleProof {
+                leaf_hash: hash_field(map, "leaf_hash")?,
+                path,
+                indices: bytes_field(map, "indices")?,
+            },
+            model_version,
+            tree_hasher: match field(map, "tree_hasher") {
+                Ok(value) => value.deserialized().map_err(err)?,
+                Err(_) => TreeHasher::default(),
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::sample_proof;
+
+    #[test]
+    fn test_cbor_roundtrip_and_hash_encoding() {
+        let proof = sample_proof();
+        let bytes = proof.to_cbor().unwrap();
+        let decoded = VerificationProof::from_cbor(&bytes).unwrap();
+        assert_eq!(decoded.to_bytes().unwrap(), proof.to_bytes().unwrap());
+
+        let value: Value = ciborium::from_reader(bytes.as_slice()).unwrap();
+        let map = value.as_map().unwrap();
+        assert_eq!(field(map, "tx_hash").unwrap(), &Value::Bytes(vec![1u8; 32]));
+        // Major type 2 (byte string) with a one-byte length of 32.
+        let needle = [0x58, 0x20, 1, 1, 1];
+        assert!(bytes.windows(needle.len()).any(|w| w == needle));
+
+        let mut by_hash = proof.clone();
+        by_hash.delta.delta_bytes = proof.delta.delta_bytes.to_by_hash();
+        let decoded = VerificationProof::from_cbor(&by_hash.to_cbor().unwrap()).unwrap();
+        assert_eq!(decoded.delta.delta_bytes, by_hash.delta.delta_bytes);
+
+        let mut truncated = bytes.clone();
+        truncated.truncate(bytes.len() - 4);
+        assert!(VerificationProof::from_cbor(&truncated).is_err());
+    }
+}
@@ -0,0 +1,123 @@
+//! Block-level quantization of delta confidences.
+//!
+//! Confidences live in `[0, 1]` and tolerate coarse precision, so a block
+//! stores them as one packed stream of `bits`-wide codes instead of four
+//! bytes each. A value decodes to within `1 / (2 * (2^bits - 1))` of the
+//! original (about `0.002` at the default 8 bits); values outside `[0, 1]`
+//! are clamped and NaN is stored as 0.
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{CantorError, Result};
+
+/// Default code width: one byte per confidence.
+pub const DEFAULT_CONFIDENCE_BITS: u8 = 8;
+
+/// A block's confidences packed as fixed-width codes, LSB first.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuantizedConfidences {
+    pub bits: u8,
+    pub count: u64,
+    pub data: Vec<u8>,
+}
+
+impl QuantizedConfidences {
+    /// Quantize `values` to `bits`-wide codes, with `bits` in `1..=16`.
+    pub fn encode(values: &[f32], bits: u8) -> Result<Self> {
+        if !(1..=16).contains(&bits) {
+            return Err(CantorError::CompressionFailed(format!(
+                "confidence width must be 1..=16 bits, got {}",
+                bits
+            )));
+        }
+
+        let max_code = ((1u32 << bits) - 1) as f32;
+        let mut data = vec![0u8; (values.len() * bits as usize).div_ceil(8)];
+        for (i, &value) in values.iter().enumerate() {
+            let clamped = if value.is_nan() {
+                0.0
+            } else {
+                value.clamp(0.0, 1.0)
+            };
+            let code = (clamped * max_code).round() as u32;
+            for bit in 0..bits as usize {
+                if code >> bit & 1 == 1 {
+                    let pos = i * bits as usize + bit;
+                    data[pos / 8] |= 1 << (pos % 8);
+                }
+            }
+        }
+
+        Ok(Self {
+            bits,
+            count: values.len() as u64,
+            data,
+        })
+    }
+
+    /// Dequantize back to `f32` confidences.
+    pub fn decode(&self) -> Result<Vec<f32>> {
+        let bits = self.bits as usize;
+        let needed = (self.count as usize)
+            .checked_mul(bits)
+            .map(|n| n.div_ceil(8))
+            .ok_or(CantorError::InvalidDeltaEncoding)?;
+        if !(1..=16).contains(&bits) || self.data.len() != needed {
+            return Err(CantorError::InvalidDeltaEncoding);
+        }
+
+        let max_code = ((1u32 << bits) - 1) as f32;
+        Ok((0..self.count as usize)
+            .map(|i| {
+                let code = (0..bits).fold(0u32, |acc, bit| {
+                    let pos = i * bits + bit;
+                    acc | (((self.data[pos / 8] >> (pos % 8)) & 1) as u32) << bit
+                });
+                code as f32 / max_code
+            })
+            .collect())
+    }
+
+    pub fn len(&self) -> usize {
+        self.count as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_confidence_roundtrip_8_bits() {
+        let values: Vec<f32> = (0..100).map(|i| (i as f32 * 0.731).fract()).collect();
+        let packed = QuantizedConfidences::encode(&values, DEFAULT_CONFIDENCE_BITS).unwrap();
+        assert_eq!(packed.data.len(), 100);
+
+        let decoded = packed.decode().unwrap();
+        for (a, b) in values.iter().zip(&decoded) {
+            assert!((a - b).abs() <= 1.0 / 255.0);
+        }
+    }
+
+    #[test]
+    fn test_confidence_narrow_width_and_clamping() {
+        let values = [0.0, 1.0, 0.25, 2.0, -1.0, f32::NAN];
+        let packed = QuantizedConfidences::encode(&values, 3).unwrap();
+        assert_eq!(packed.data.len(), 3);
+
+        let decoded = packed.decode().unwrap();
+        assert_eq!(decoded[0], 0.0);
+        assert_eq!(decoded[1], 1.0);
+        assert!((decoded[2] - 0.25).abs() <= 1.0 / 14.0);
+        assert_eq!(decoded[3], 1.0);
+        assert_eq!(decoded[4], 0.0);
+        assert_eq!(decoded[5], 0.0);
+
+        assert!(QuantizedConfidences::encode(&values, 0).is_err());
+        assert!(QuantizedConfidences::encode(&values, 17).is_err());
+    }
+}
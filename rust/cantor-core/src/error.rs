@@ -2,6 +2,8 @@
 
 use thiserror::Error;
 
+use crate::types::Hash32;
+
 /// Core error type for CANTOR operations.
 #[derive(Error, Debug)]
 pub enum CantorError {
@@ -26,12 +28,39 @@ pub enum CantorError {
     #[error("Invalid delta encoding")]
     InvalidDeltaEncoding,
 
+    #[error("Invalid delta encoding: truncated or overlong varint at byte {position}")]
+    InvalidVarint { position: usize },
+
+    #[error("Non-finite delta element at index {index}")]
+    NonFiniteDelta { index: usize },
+
     #[error("Block not found: {0}")]
     BlockNotFound(u64),
 
+    #[error("Block {0} is not the genesis block")]
+    NotGenesisBlock(u64),
+
+    #[error("Delta not found: {0}")]
+    DeltaNotFound(Hash32),
+
+    #[error("Delta does not match its hash: {0}")]
+    DeltaHashMismatch(Hash32),
+
     #[error("Transaction not found: {0}")]
     TransactionNotFound(String),
 
+    #[error("Proof and delta pairing mismatch at index {0}")]
+    PairingMismatch(usize),
+
+    #[error("Batch length mismatch: {proofs} proofs but {predicted} predicted states")]
+    BatchLengthMismatch { proofs: usize, predicted: usize },
+
+    #[error("Anchor mismatch: {0}")]
+    AnchorMismatch(String),
+
+    #[error("Duplicate leaves at indices {0:?}")]
+    DuplicateLeaves(Vec<Vec<usize>>),
+
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
 
@@ -0,0 +1,27 @@
+//! Fixtures shared by the crate's test modules.
+
+use crate::hasher::{HashAlgorithm, TreeHasher, TreeVersion};
+use crate::types::{CompressionMethod, Hash32, MerkleProof, StateDelta, VerificationProof};
+
+/// A proof with every field set to a distinct, non-default value.
+pub(crate) fn sample_proof() -> VerificationProof {
+    VerificationProof {
+        tx_hash: Hash32([1u8; 32]),
+        predicted_state: Hash32([2u8; 32]),
+        delta: StateDelta {
+            tx_hash: Hash32([1u8; 32]),
+            predicted_root: Hash32([3u8; 32]),
+            actual_root: Hash32([4u8; 32]),
+            delta_bytes: vec![9, 8, 7, 0, 1].into(),
+            confidence: 0.75,
+            method: CompressionMethod::SegmentedSparse,
+        },
+        merkle_proof: MerkleProof {
+            leaf_hash: Hash32([5u8; 32]),
+            path: vec![Hash32([6u8; 32]), Hash32([7u8; 32])],
+            indices: vec![0, 1],
+        },
+        model_version: "v1.0.0".to_string(),
+        tree_hasher: TreeHasher::new(HashAlgorithm::Blake3, TreeVersion::V2),
+    }
+}
@@ -0,0 +1,161 @@
+//! Hash functions a Merkle tree can be built over, and how leaves and
+//! nodes are laid out for them.
+
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use sha3::{Digest, Keccak256};
+
+use crate::types::Hash32;
+
+/// Hash function for Merkle leaves and nodes.
+///
+/// A root is only meaningful together with the algorithm it was built
+/// with. A [`VerificationProof`](crate::VerificationProof) records its
+/// tree's [`TreeHasher`]; a bare [`MerkleProof`](crate::MerkleProof) does
+/// not, so verify one with
+/// [`MerkleProof::verify_with`](crate::MerkleProof::verify_with) and the
+/// tree's hasher. `Sha256` is the default, and what
+/// [`MerkleProof::verify`](crate::MerkleProof::verify) uses.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum HashAlgorithm {
+    #[default]
+    Sha256,
+    Blake3,
+    /// The original Keccak-256 padding, as Ethereum uses, not SHA3-256.
+    Keccak256,
+}
+
+impl HashAlgorithm {
+    /// Hash of `data`.
+    pub fn hash(self, data: &[u8]) -> Hash32 {
+        self.hash_parts([data])
+    }
+
+    /// Hash of the concatenation of `parts`, without building it.
+    pub fn hash_parts<'a>(self, parts: impl IntoIterator<Item = &'a [u8]>) -> Hash32 {
+        match self {
+            HashAlgorithm::Sha256 => {
+                let mut hasher = Sha256::new();
+                parts.into_iter().for_each(|part| hasher.update(part));
+                Hash32(hasher.finalize().into())
+            }
+            HashAlgorithm::Blake3 => {
+                let mut hasher = blake3::Hasher::new();
+                parts.into_iter().for_each(|part| {
+                    hasher.update(part);
+                });
+                Hash32(hasher.finalize().into())
+            }
+            HashAlgorithm::Keccak256 => {
+                let mut hasher = Keccak256::new();
+                parts.into_iter().for_each(|part| hasher.update(part));
+                Hash32(hasher.finalize().into())
+            }
+        }
+    }
+}
+
+/// Layout of the preimages a tree hashes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum TreeVersion {
+    /// Leaves hash the bare data, nodes the bare concatenation of their
+    /// children. An internal node's preimage then also passes as a leaf,
+    /// so a proof can stop at an internal node and prove its children's
+    /// concatenation as a delta. Kept as the default for existing roots.
+    #[default]
+    V1,
+    /// Leaves hash `0x00 || data` and nodes `0x01 || children`, so no
+    /// preimage serves as both.
+    V2,
+}
+
+/// Hash function and preimage layout of a Merkle tree: everything a
+/// verifier needs besides the proof to recompute its root.
+///
+/// The default is SHA-256 under [`TreeVersion::V1`], the original
+/// scheme. A bare [`HashAlgorithm`] converts to itself under `V1`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct TreeHasher {
+    pub algorithm: HashAlgorithm,
+    pub version: TreeVersion,
+}
+
+impl TreeHasher {
+    /// Leaf tag under [`TreeVersion::V2`].
+    pub const LEAF_TAG: u8 = 0x00;
+    /// Internal node tag under [`TreeVersion::V2`].
+    pub const NODE_TAG: u8 = 0x01;
+
+    pub fn new(algorithm: HashAlgorithm, version: TreeVersion) -> Self {
+        Self { algorithm, version }
+    }
+
+    /// Leaf hash of `data`.
+    pub fn hash_leaf(self, data: &[u8]) -> Hash32 {
+        match self.version {
+            TreeVersion::V1 => self.algorithm.hash(data),
+            TreeVersion::V2 => self.algorithm.hash_parts([&[Self::LEAF_TAG][..], data]),
+        }
+    }
+
+    /// Internal node over `children`, in order.
+    pub fn hash_node<'a>(self, children: impl IntoIterator<Item = &'a Hash32>) -> Hash32 {
+        let tag: &[u8] = match self.version {
+            TreeVersion::V1 => &[],
+            TreeVersion::V2 => &[Self::NODE_TAG],
+        };
+        self.algorithm.hash_parts(
+            std::iter::once(tag).chain(children.into_iter().map(|child| child.as_ref())),
+        )
+    }
+}
+
+impl From<HashAlgorithm> for TreeHasher {
+    fn from(algorithm: HashAlgorithm) -> Self {
+        Self::new(algorithm, TreeVersion::V1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_known_digests() {
+        let hex = |algorithm: HashAlgorithm, data: &[u8]| hex::encode(algorithm.hash(data).0);
+        assert_eq!(
+            hex(HashAlgorithm::Sha256, b""),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_eq!(
+            hex(HashAlgorithm::Blake3, b""),
+            "af1349b9f5f9a1a6a0404dea36dcc9499bcb25c9adc112b7cc9a93cae41f3262"
+        );
+        assert_eq!(
+            hex(HashAlgorithm::Keccak256, b""),
+            "c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470"
+        );
+        for algorithm in [
+            HashAlgorithm::Sha256,
+            HashAlgorithm::Blake3,
+            HashAlgorithm::Keccak256,
+        ] {
+            assert_eq!(
+                algorithm.hash_parts([&b"can"[..], b"", b"tor"]),
+                algorithm.hash(b"cantor")
+            );
+
+            let v1 = TreeHasher::from(algorithm);
+            let v2 = TreeHasher::new(algorithm, TreeVersion::V2);
+            let (a, b) = (algorithm.hash(b"a"), algorithm.hash(b"b"));
+            let pair = [a.0, b.0].concat();
+            assert_eq!(v1.hash_leaf(&pair), v1.hash_node([&a, &b]));
+            assert_ne!(v2.hash_leaf(&pair), v2.hash_node([&a, &b]));
+            assert_eq!(v2.hash_leaf(b"x"), algorithm.hash(b"\x00x"));
+            assert_eq!(
+                v2.hash_node([&a, &b]),
+                algorithm.hash(&[&[1][..], &pair].concat())
+            );
+        }
+    }
+}
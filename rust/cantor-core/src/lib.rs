@@ -1,8 +1,27 @@
 //! Core types and traits for CANTOR state compression system.
 
 pub mod types;
+pub mod accumulator;
+pub mod canonical;
+#[cfg(feature = "cbor")]
+pub mod cbor;
+pub mod confidence;
 pub mod error;
+#[cfg(test)]
+mod fixtures;
+pub mod hasher;
+pub mod map;
+pub mod serialization;
+pub mod store;
+pub mod truncated;
 
 pub use types::*;
+pub use accumulator::*;
 pub use error::*;
+pub use canonical::*;
+pub use confidence::*;
+pub use hasher::*;
+pub use map::*;
+pub use store::*;
+pub use truncated::*;
 
@@ -0,0 +1,75 @@
+//! Hash maps keyed by `Hash32`.
+//!
+//! A `Hash32` is already the output of a cryptographic hash, so its bytes
+//! are uniformly distributed and re-hashing them with SipHash is wasted
+//! work. [`Hash32Hasher`] uses the first eight bytes of the key directly as
+//! the bucket hash. It must only be used for keys that are themselves
+//! hashes: arbitrary data would collide trivially and loses SipHash's
+//! protection against adversarially chosen keys.
+
+use std::collections::{HashMap, HashSet};
+use std::hash::{BuildHasherDefault, Hasher};
+
+use crate::types::Hash32;
+
+/// Identity-style hasher for already-hashed keys.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Hash32Hasher(u64);
+
+impl Hasher for Hash32Hasher {
+    fn write(&mut self, bytes: &[u8]) {
+        let mut prefix = [0u8; 8];
+        let n = bytes.len().min(8);
+        prefix[..n].copy_from_slice(&bytes[..n]);
+        self.0 ^= u64::from_le_bytes(prefix);
+    }
+
+    // `Hash32` hashes as a fixed-size byte slice; its length prefix carries
+    // no information.
+    fn write_usize(&mut self, _: usize) {}
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+pub type BuildHash32Hasher = BuildHasherDefault<Hash32Hasher>;
+
+/// `HashMap` keyed by `Hash32` using [`Hash32Hasher`].
+pub type Hash32Map<V> = HashMap<Hash32, V, BuildHash32Hasher>;
+
+/// `HashSet` of `Hash32` using [`Hash32Hasher`].
+pub type Hash32Set = HashSet<Hash32, BuildHash32Hasher>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::hash::BuildHasher;
+
+    #[test]
+    fn test_hasher_uses_key_prefix() {
+        let key = Hash32([0xab; 32]);
+        let hash = BuildHash32Hasher::default().hash_one(key);
+        assert_eq!(hash, u64::from_le_bytes([0xab; 8]));
+    }
+
+    #[test]
+    fn test_hash32_map() {
+        let keys: Vec<Hash32> = (0..1000u32)
+            .map(|i| crate::StateVector::new(vec![i as f32]).compute_hash())
+            .collect();
+
+        let mut map = Hash32Map::default();
+        for (i, key) in keys.iter().enumerate() {
+            assert!(map.insert(*key, i).is_none());
+        }
+        assert_eq!(map.len(), keys.len());
+        for (i, key) in keys.iter().enumerate() {
+            assert_eq!(map.get(key), Some(&i));
+        }
+        assert!(!map.contains_key(&Hash32::ZERO));
+
+        assert_eq!(map.remove(&keys[10]), Some(10));
+        assert!(!map.contains_key(&keys[10]));
+    }
+}
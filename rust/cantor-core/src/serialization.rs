@@ -0,0 +1,394 @@
+//! Binary wire format for CANTOR types.
+//!
+//! Values are encoded with bincode using fixed-width integers. Every
+//! decode is bounded by [`MAX_SERIALIZED_LEN`], so a payload that claims
+//! an absurd vector length fails instead of driving a huge allocation.
+//! Values whose size grows with their contents, such as whole trees, set
+//! their own bound through [`to_bytes_with_limit`] and
+//! [`from_bytes_with_limit`].
+
+use std::borrow::Cow;
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use bincode::Options;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::confidence::{QuantizedConfidences, DEFAULT_CONFIDENCE_BITS};
+use crate::error::{CantorError, Result};
+use crate::hasher::TreeHasher;
+use crate::types::{
+    CompressionMethod, CompressionResult, DeltaRef, Hash32, MerkleProof, ProofBatch,
+    QuantizationParams, StateDelta, VerificationProof,
+};
+
+/// Upper bound on the size of any encoded value.
+pub const MAX_SERIALIZED_LEN: u64 = 16 * 1024 * 1024;
+
+fn options(limit: u64) -> impl Options {
+    bincode::DefaultOptions::new()
+        .with_fixint_encoding()
+        .with_limit(limit)
+}
+
+/// Serialize a value into the CANTOR wire format.
+pub fn to_bytes<T: Serialize>(value: &T) -> Result<Vec<u8>> {
+    to_bytes_with_limit(value, MAX_SERIALIZED_LEN)
+}
+
+/// Deserialize a value from the CANTOR wire format.
+pub fn from_bytes<T: DeserializeOwned>(bytes: &[u8]) -> Result<T> {
+    from_bytes_with_limit(bytes, MAX_SERIALIZED_LEN)
+}
+
+/// [`to_bytes`] failing past `limit` bytes instead of
+/// [`MAX_SERIALIZED_LEN`].
+pub fn to_bytes_with_limit<T: Serialize>(value: &T, limit: u64) -> Result<Vec<u8>> {
+    options(limit)
+        .serialize(value)
+        .map_err(|e| CantorError::Serialization(e.to_string()))
+}
+
+/// [`from_bytes`] bounded by `limit` bytes instead of
+/// [`MAX_SERIALIZED_LEN`].
+pub fn from_bytes_with_limit<T: DeserializeOwned>(bytes: &[u8], limit: u64) -> Result<T> {
+    options(limit)
+        .deserialize(bytes)
+        .map_err(|e| CantorError::Serialization(e.to_string()))
+}
+
+impl VerificationProof {
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        to_bytes(self)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        from_bytes(bytes)
+    }
+
+    /// The wire format as standard padded base64 (RFC 4648 alphabet,
+    /// `A-Z a-z 0-9 + /`).
+    pub fn to_base64(&self) -> Result<String> {
+        Ok(BASE64.encode(self.to_bytes()?))
+    }
+
+    /// Parse a proof produced by [`Self::to_base64`].
+    ///
+    /// Input that would decode to more than [`MAX_SERIALIZED_LEN`] bytes is
+    /// rejected before decoding, and the decoded bytes go through the same
+    /// bounded deserializer as [`Self::from_bytes`].
+    pub fn from_base64(s: &str) -> Result<Self> {
+        if (s.len() as u64 / 4) * 3 > MAX_SERIALIZED_LEN {
+            return Err(CantorError::Serialization(format!(
+                "base64 input of {} chars exceeds the size limit",
+                s.len()
+            )));
+        }
+        let bytes = BASE64
+            .decode(s)
+            .map_err(|e| CantorError::Serialization(e.to_string()))?;
+        Self::from_bytes(&bytes)
+    }
+}
+
+impl ProofBatch {
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        to_bytes(self)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        from_bytes(bytes)
+    }
+}
+
+/// A [`StateDelta`] on the wire, with its confidence moved to the block.
+/// Borrowed when serializing, owned when deserialized.
+#[derive(Serialize, Deserialize)]
+struct DeltaWire<'a> {
+    tx_hash: Hash32,
+    predicted_root: Hash32,
+    actual_root: Hash32,
+    delta_bytes: Cow<'a, DeltaRef>,
+    method: CompressionMethod,
+}
+
+/// A [`VerificationProof`] on the wire, without the delta it shares with
+/// the block's delta list.
+#[derive(Serialize, Deserialize)]
+struct ProofWire<'a> {
+    tx_hash: Hash32,
+    predicted_state: Hash32,
+    merkle_proof: Cow<'a, MerkleProof>,
+    model_version: Cow<'a, str>,
+    tree_hasher: TreeHasher,
+}
+
+#[derive(Serialize, Deserialize)]
+struct BlockWire<'a> {
+    block_number: u64,
+    original_size: usize,
+    compressed_size: usize,
+    delta_tree_root: Hash32,
+    deltas: Vec<DeltaWire<'a>>,
+    confidences: QuantizedConfidences,
+    proofs: Vec<ProofWire<'a>>,
+    quantization: QuantizationParams,
+}
+
+impl CompressionResult {
+    /// Serialize with confidences quantized to one byte each.
+    ///
+    /// Decoded confidences are within `1/510` of the originals; see
+    /// [`crate::confidence`]. Proofs must either be absent or pair with
+    /// the deltas, since each proof's delta is stored only once.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        self.to_bytes_with_confidence_bits(DEFAULT_CONFIDENCE_BITS)
+    }
+
+    /// Serialize with confidences quantized to `bits`-wide codes.
+    pub fn to_bytes_with_confidence_bits(&self, bits: u8) -> Result<Vec<u8>> {
+        if !self.proofs.is_empty() {
+            self.validate_pairing()?;
+        }
+        to_bytes(&self.wire(bits)?)
+    }
+
+    /// Exact length of [`Self::to_bytes`]'s output, counted without
+    /// producing it, e.g. to fit blocks into size-limited files.
+    ///
+    /// The count does not check what serializing would reject, so it is
+    /// also returned for a block whose proofs do not pair with its deltas
+    /// or which exceeds [`MAX_SERIALIZED_LEN`].
+    pub fn serialized_len(&self) -> usize {
+        let wire = self
+            .wire(DEFAULT_CONFIDENCE_BITS)
+            .expect("the default confidence width is valid");
+        bincode::DefaultOptions::new()
+            .with_fixint_encoding()
+            .serialized_size(&wire)
+            .expect("counting the wire format cannot fail") as usize
+    }
+
+    /// The block's wire form, borrowing the deltas and proofs.
+    fn wire(&self, bits: u8) -> Result<BlockWire<'_>> {
+        let confidences: Vec<f32> = self.deltas.iter().map(|d| d.confidence).collect();
+        Ok(BlockWire {
+            block_number: self.block_number,
+            original_size: self.original_size,
+            compressed_size: self.compressed_size,
+            delta_tree_root: self.delta_tree_root,
+            deltas: self
+                .deltas
+                .iter()
+                .map(|d| DeltaWire {
+                    tx_hash: d.tx_hash,
+                    predicted_root: d.predicted_root,
+                    actual_root: d.actual_root,
+                    delta_bytes: Cow::Borrowed(&d.delta_bytes),
+                    method: d.method,
+                })
+                .collect(),
+            confidences: QuantizedConfidences::encode(&confidences, bits)?,
+            proofs: self
+                .proofs
+                .iter()
+                .map(|p| ProofWire {
+                    tx_hash: p.tx_hash,
+                    predicted_state: p.predicted_state,
+                    merkle_proof: Cow::Borrowed(&p.merkle_proof),
+                    model_version: Cow::Borrowed(&p.model_version),
+                    tree_hasher: p.tree_hasher,
+                })
+                .collect(),
+            quantization: self.quantization,
+        })
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let wire: BlockWire = from_bytes(bytes)?;
+        let confidences = wire.confidences.decode()?;
+        if confidences.len() != wire.deltas.len() {
+            return Err(CantorError::Serialization(format!(
+                "{} confidences for {} deltas",
+                confidences.len(),
+                wire.deltas.len()
+            )));
+        }
+        if !wire.proofs.is_empty() && wire.proofs.len() != wire.deltas.len() {
+            return Err(CantorError::PairingMismatch(
+                wire.proofs.len().min(wire.deltas.len()),
+            ));
+        }
+
+        let deltas: Vec<StateDelta> = wire
+            .deltas
+            .into_iter()
+            .zip(confidences)
+            .map(|(d, confidence)| StateDelta {
+                tx_hash: d.tx_hash,
+                predicted_root: d.predicted_root,
+                actual_root: d.actual_root,
+                delta_bytes: d.delta_bytes.into_owned(),
+                confidence,
+                method: d.method,
+            })
+            .collect();
+        let proofs = wire
+            .proofs
+            .into_iter()
+            .zip(&deltas)
+            .map(|(p, delta)| VerificationProof {
+                tx_hash: p.tx_hash,
+                predicted_state: p.predicted_state,
+                delta: delta.clone(),
+                merkle_proof: p.merkle_proof.into_owned(),
+                model_version: p.model_version.into_owned(),
+                tree_hasher: p.tree_hasher,
+            })
+            .collect();
+
+        Ok(Self {
+            block_number: wire.block_number,
+            original_size: wire.original_size,
+            compressed_size: wire.compressed_size,
+            delta_tree_root: wire.delta_tree_root,
+            deltas,
+            proofs,
+            quantization: wire.quantization,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::sample_proof;
+
+    #[test]
+    fn test_proof_roundtrip() {
+        let proof = sample_proof();
+        let bytes = proof.to_bytes().unwrap();
+        let decoded = VerificationProof::from_bytes(&bytes).unwrap();
+        assert_eq!(decoded.tx_hash, proof.tx_hash);
+        assert_eq!(decoded.delta.delta_bytes, proof.delta.delta_bytes);
+        assert_eq!(decoded.merkle_proof.path, proof.merkle_proof.path);
+        assert_eq!(decoded.model_version, proof.model_version);
+    }
+
+    #[test]
+    fn test_rejects_absurd_length() {
+        // A u64 length prefix claiming far more elements than the limit allows.
+        let mut bytes = vec![0u8; 160];
+        bytes.extend_from_slice(&u64::MAX.to_le_bytes());
+        assert!(matches!(
+            VerificationProof::from_bytes(&bytes),
+            Err(CantorError::Serialization(_))
+        ));
+    }
+
+    #[test]
+    fn test_proof_base64_roundtrip() {
+        let proof = sample_proof();
+        let encoded = proof.to_base64().unwrap();
+        let decoded = VerificationProof::from_base64(&encoded).unwrap();
+        assert_eq!(decoded.to_bytes().unwrap(), proof.to_bytes().unwrap());
+
+        assert!(VerificationProof::from_base64("not base64!").is_err());
+
+        let mut absurd = vec![0u8; 160];
+        absurd.extend_from_slice(&u64::MAX.to_le_bytes());
+        assert!(matches!(
+            VerificationProof::from_base64(&BASE64.encode(absurd)),
+            Err(CantorError::Serialization(_))
+        ));
+    }
+
+    fn confidence_block(count: usize, with_proofs: bool) -> CompressionResult {
+        let deltas: Vec<StateDelta> = (0..count)
+            .map(|i| StateDelta {
+                tx_hash: Hash32([i as u8; 32]),
+                predicted_root: Hash32::ZERO,
+                actual_root: Hash32::ZERO,
+                delta_bytes: vec![].into(),
+                confidence: (i as f32 * 0.0137).fract(),
+                method: CompressionMethod::Lz4,
+            })
+            .collect();
+        let proofs = if with_proofs {
+            deltas
+                .iter()
+                .map(|delta| VerificationProof {
+                    delta: delta.clone(),
+                    tx_hash: delta.tx_hash,
+                    ..sample_proof()
+                })
+                .collect()
+        } else {
+            vec![]
+        };
+
+        CompressionResult {
+            block_number: 7,
+            original_size: 0,
+            compressed_size: 0,
+            delta_tree_root: Hash32::ZERO,
+            deltas,
+            proofs,
+            quantization: QuantizationParams::default(),
+        }
+    }
+
+    #[test]
+    fn test_block_confidences_roundtrip_quantized() {
+        let block = confidence_block(100, true);
+        let decoded = CompressionResult::from_bytes(&block.to_bytes().unwrap()).unwrap();
+
+        assert_eq!(decoded.deltas.len(), 100);
+        for (a, b) in block.deltas.iter().zip(&decoded.deltas) {
+            assert!((a.confidence - b.confidence).abs() <= 1.0 / 255.0);
+        }
+        decoded.validate_pairing().unwrap();
+
+        // Confidences take one byte each, plus the codec's fixed header.
+        let bare = confidence_block(100, false);
+        let mut zeroed = bare.clone();
+        zeroed.deltas.truncate(0);
+        let per_delta = bare.to_bytes().unwrap().len() - zeroed.to_bytes().unwrap().len();
+        // Three hashes, the `DeltaRef` tag, the empty payload's length and
+        // the method.
+        let wire_delta = 32 * 3 + 4 + 8 + 4;
+        assert_eq!(per_delta, 100 * (wire_delta + 1));
+    }
+
+    #[test]
+    fn test_block_rejects_unpaired_proofs() {
+        let mut block = confidence_block(3, true);
+        block.proofs.pop();
+        assert!(matches!(
+            block.to_bytes(),
+            Err(CantorError::PairingMismatch(2))
+        ));
+    }
+
+    #[test]
+    fn test_serialized_len_matches_to_bytes() {
+        let mut large = confidence_block(2000, true);
+        for (i, delta) in large.deltas.iter_mut().enumerate() {
+            delta.delta_bytes = vec![i as u8; i % 700].into();
+        }
+        for (proof, delta) in large.proofs.iter_mut().zip(&large.deltas) {
+            proof.delta = delta.clone();
+            proof.merkle_proof.path = vec![Hash32([6u8; 32]); 11];
+            proof.merkle_proof.indices = vec![0; 11];
+        }
+        let blocks = [
+            confidence_block(0, false),
+            confidence_block(1, true),
+            confidence_block(100, false),
+            large,
+        ];
+        for block in &blocks {
+            assert_eq!(block.serialized_len(), block.to_bytes().unwrap().len());
+        }
+    }
+}
@@ -0,0 +1,74 @@
+//! Out-of-line storage for deltas referenced by hash.
+
+use std::borrow::Cow;
+
+use sha2::{Digest, Sha256};
+
+use crate::error::{CantorError, Result};
+use crate::map::Hash32Map;
+use crate::types::{DeltaRef, Hash32};
+
+/// Source of encoded delta bytes for [`DeltaRef::ByHash`] references,
+/// keyed by the SHA-256 of the bytes.
+pub trait DeltaStore {
+    /// The bytes stored under `hash`, if any.
+    fn get(&self, hash: &Hash32) -> Option<Cow<'_, [u8]>>;
+}
+
+impl DeltaStore for Hash32Map<Vec<u8>> {
+    fn get(&self, hash: &Hash32) -> Option<Cow<'_, [u8]>> {
+        Hash32Map::get(self, hash).map(|bytes| Cow::Borrowed(bytes.as_slice()))
+    }
+}
+
+impl DeltaRef {
+    /// The referenced bytes: inline ones directly, others from `store`.
+    ///
+    /// Bytes from the store are hashed and checked against the reference,
+    /// so a store cannot substitute a different delta. A missing delta
+    /// fails with `CantorError::DeltaNotFound`, a substituted one with
+    /// `CantorError::DeltaHashMismatch`.
+    pub fn resolve<'a>(&'a self, store: &'a dyn DeltaStore) -> Result<Cow<'a, [u8]>> {
+        match self {
+            DeltaRef::Inline(bytes) => Ok(Cow::Borrowed(bytes)),
+            DeltaRef::ByHash(hash) => {
+                let bytes = store.get(hash).ok_or(CantorError::DeltaNotFound(*hash))?;
+                if Sha256::digest(&bytes).as_slice() != hash.as_bytes() {
+                    return Err(CantorError::DeltaHashMismatch(*hash));
+                }
+                Ok(bytes)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_by_hash() {
+        let inline = DeltaRef::from(vec![1, 2, 3]);
+        let by_hash = inline.to_by_hash();
+        assert_eq!(by_hash.content_hash(), inline.content_hash());
+        assert!(matches!(
+            by_hash.inline_bytes(),
+            Err(CantorError::DeltaNotFound(_))
+        ));
+
+        let mut store = Hash32Map::default();
+        assert!(matches!(
+            by_hash.resolve(&store),
+            Err(CantorError::DeltaNotFound(_))
+        ));
+        store.insert(inline.content_hash(), vec![1, 2, 3]);
+        assert_eq!(by_hash.resolve(&store).unwrap().as_ref(), [1, 2, 3]);
+        assert_eq!(inline.resolve(&store).unwrap().as_ref(), [1, 2, 3]);
+
+        store.insert(inline.content_hash(), vec![1, 2, 4]);
+        assert!(matches!(
+            by_hash.resolve(&store),
+            Err(CantorError::DeltaHashMismatch(_))
+        ));
+    }
+}
@@ -0,0 +1,165 @@
+//! Truncated hashes for space-constrained commitments.
+//!
+//! `HashN<N>` keeps the first `N` bytes of a SHA-256 digest. Collision
+//! resistance drops to about `4 * N` bits (64 bits for [`Hash16`]), so these
+//! types are only suitable for low-security internal trees. Anything that
+//! is consensus-critical or exposed to adversarial producers should keep
+//! using [`Hash32`](crate::Hash32).
+
+use sha2::{Digest, Sha256};
+use std::fmt;
+
+use crate::Hash32;
+
+/// Hash truncated to `N` bytes, with `N <= 32`.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct HashN<const N: usize>(pub [u8; N]);
+
+/// 128-bit truncated hash.
+pub type Hash16 = HashN<16>;
+
+impl<const N: usize> HashN<N> {
+    pub const ZERO: Self = {
+        #[allow(clippy::let_unit_value)]
+        let () = Self::VALID_LENGTH;
+        Self([0u8; N])
+    };
+
+    /// Checked by every constructor but the tuple literal, so an invalid
+    /// `N` fails to compile wherever a `HashN<N>` is made, not only where
+    /// one is hashed.
+    const VALID_LENGTH: () = assert!(N > 0 && N <= 32, "HashN length must be 1..=32");
+
+    pub fn from_slice(slice: &[u8]) -> Option<Self> {
+        #[allow(clippy::let_unit_value)]
+        let () = Self::VALID_LENGTH;
+        if slice.len() != N {
+            return None;
+        }
+        let mut arr = [0u8; N];
+        arr.copy_from_slice(slice);
+        Some(Self(arr))
+    }
+
+    pub fn as_bytes(&self) -> &[u8; N] {
+        &self.0
+    }
+
+    /// SHA-256 of `data`, truncated to `N` bytes.
+    pub fn digest(data: &[u8]) -> Self {
+        let full = Sha256::digest(data);
+        Self::from_slice(&full[..N]).unwrap()
+    }
+
+    /// Hash of the concatenation `left || right`.
+    pub fn hash_pair(left: &Self, right: &Self) -> Self {
+        let combined = [left.as_ref(), right.as_ref()].concat();
+        Self::digest(&combined)
+    }
+
+    /// The first `N` bytes of a full hash.
+    pub fn prefix_of(hash: &Hash32) -> Self {
+        Self::from_slice(&hash.0[..N]).unwrap()
+    }
+}
+
+impl<const N: usize> fmt::Debug for HashN<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Hash{}({})", N, hex::encode(&self.0[..N.min(8)]))
+    }
+}
+
+impl<const N: usize> fmt::Display for HashN<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "0x{}", hex::encode(self.0))
+    }
+}
+
+impl<const N: usize> AsRef<[u8]> for HashN<N> {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+/// Merkle proof over truncated hashes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MerkleProofN<const N: usize> {
+    pub leaf_hash: HashN<N>,
+    pub path: Vec<HashN<N>>,
+    pub indices: Vec<u8>,
+}
+
+impl<const N: usize> MerkleProofN<N> {
+    pub fn verify(&self, root: &HashN<N>) -> bool {
+        let mut current = self.leaf_hash;
+
+        for (sibling, &index) in self.path.iter().zip(self.indices.iter()) {
+            current = if index == 0 {
+                HashN::hash_pair(&current, sibling)
+            } else {
+                HashN::hash_pair(sibling, &current)
+            };
+        }
+
+        current == *root
+    }
+}
+
+/// Merkle proof whose siblings are stored as 16-byte prefixes, for
+/// trees whose internal nodes hash their children's prefixes; see
+/// `TruncatedPathTree` in `cantor-merkle`.
+///
+/// The leaf and every recomputed node are full SHA-256 hashes, and the
+/// root is compared in full, but at each level only the first 16 bytes of
+/// the current node and its sibling enter the parent. Half-size proofs
+/// come at a cost: any sibling sharing the stored 16-byte prefix is
+/// accepted, whatever its remaining bytes, so a producer able to find a
+/// 128-bit prefix collision (about 2^64 work) could substitute a subtree.
+/// Keep this to commitments where that margin is acceptable.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TruncatedProof {
+    pub leaf_hash: Hash32,
+    pub path: Vec<Hash16>,
+    /// 0 if the path node is the left child at that level, 1 if right.
+    pub indices: Vec<u8>,
+}
+
+impl TruncatedProof {
+    /// Parent of two children given by their prefixes.
+    pub fn hash_node(left: &Hash16, right: &Hash16) -> Hash32 {
+        let combined = [left.as_ref(), right.as_ref()].concat();
+        Hash32(Sha256::digest(&combined).into())
+    }
+
+    /// The root this proof leads to.
+    pub fn compute_root(&self) -> Hash32 {
+        let mut current = self.leaf_hash;
+        for (sibling, &index) in self.path.iter().zip(&self.indices) {
+            let prefix = Hash16::prefix_of(&current);
+            current = if index == 0 {
+                Self::hash_node(&prefix, sibling)
+            } else {
+                Self::hash_node(sibling, &prefix)
+            };
+        }
+        current
+    }
+
+    pub fn verify(&self, root: &Hash32) -> bool {
+        self.path.len() == self.indices.len() && self.compute_root() == *root
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hash16_is_sha256_prefix() {
+        let full = Sha256::digest(b"cantor");
+        let short = Hash16::digest(b"cantor");
+        assert_eq!(short.as_bytes(), &full[..16]);
+        assert_eq!(short.to_string().len(), 2 + 32);
+        assert!(Hash16::from_slice(&[0u8; 32]).is_none());
+    }
+}
@@ -3,8 +3,11 @@
 use serde::{Deserialize, Serialize};
 use std::fmt;
 
+use crate::error::{CantorError, Result};
+use crate::hasher::{HashAlgorithm, TreeHasher};
+
 /// 32-byte hash type used throughout the system.
-#[derive(Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub struct Hash32(pub [u8; 32]);
 
 impl Hash32 {
@@ -22,6 +25,27 @@ impl Hash32 {
     pub fn as_bytes(&self) -> &[u8; 32] {
         &self.0
     }
+
+    /// Write the `0x`-prefixed lowercase hex form into `buf` without
+    /// allocating. The returned string borrows from `buf` and matches the
+    /// `Display` output.
+    pub fn encode_hex_into<'a>(&self, buf: &'a mut [u8; 66]) -> &'a str {
+        const HEX: &[u8; 16] = b"0123456789abcdef";
+        buf[0] = b'0';
+        buf[1] = b'x';
+        for (i, byte) in self.0.iter().enumerate() {
+            buf[2 + 2 * i] = HEX[(byte >> 4) as usize];
+            buf[3 + 2 * i] = HEX[(byte & 0x0f) as usize];
+        }
+        std::str::from_utf8(buf).expect("hex digits are ASCII")
+    }
+
+    /// The `0x`-prefixed hex form as a stack array.
+    pub fn to_hex_array(&self) -> [u8; 66] {
+        let mut buf = [0u8; 66];
+        self.encode_hex_into(&mut buf);
+        buf
+    }
 }
 
 impl fmt::Debug for Hash32 {
@@ -32,7 +56,7 @@ impl fmt::Debug for Hash32 {
 
 impl fmt::Display for Hash32 {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "0x{}", hex::encode(&self.0))
+        write!(f, "0x{}", hex::encode(self.0))
     }
 }
 
@@ -63,26 +87,261 @@ impl StateVector {
     }
 
     pub fn compute_hash(&self) -> Hash32 {
+        hash_state_iter(self.data.iter().copied())
+    }
+
+    /// Hash under a domain tag: `H(len(domain)_le64 || domain || data)`.
+    ///
+    /// Subsystems that each use their own tag can never produce the same
+    /// hash for a state. An empty domain is the untagged
+    /// [`Self::compute_hash`], which carries no such separation.
+    pub fn compute_hash_domain(&self, domain: &[u8]) -> Hash32 {
         use sha2::{Sha256, Digest};
-        let bytes: Vec<u8> = self.data.iter()
-            .flat_map(|f| f.to_le_bytes())
-            .collect();
-        let result = Sha256::digest(&bytes);
-        Hash32::from_slice(&result).unwrap()
+        if domain.is_empty() {
+            return self.compute_hash();
+        }
+
+        let mut hasher = Sha256::new();
+        hasher.update((domain.len() as u64).to_le_bytes());
+        hasher.update(domain);
+        for value in &self.data {
+            hasher.update(value.to_le_bytes());
+        }
+        Hash32::from_slice(&hasher.finalize()).unwrap()
+    }
+
+    /// Exact sparse patch turning `self` into `other`.
+    ///
+    /// Elements are compared bit for bit, so `0.0` vs `-0.0` and NaN
+    /// payloads count as changes and survive the round trip.
+    pub fn binary_diff(&self, other: &StateVector) -> Result<StatePatch> {
+        if self.data.len() != other.data.len() {
+            return Err(CantorError::StateReconstructionFailed(format!(
+                "cannot diff a {}-dimensional state against a {}-dimensional one",
+                self.data.len(),
+                other.data.len()
+            )));
+        }
+
+        let mut patch = StatePatch {
+            dimension: self.data.len(),
+            indices: Vec::new(),
+            values: Vec::new(),
+        };
+        for (i, (a, b)) in self.data.iter().zip(&other.data).enumerate() {
+            if a.to_bits() != b.to_bits() {
+                patch.indices.push(i as u32);
+                patch.values.push(*b);
+            }
+        }
+        Ok(patch)
+    }
+
+    /// Apply a patch produced by [`Self::binary_diff`].
+    pub fn apply_patch(&self, patch: &StatePatch) -> Result<StateVector> {
+        if self.data.len() != patch.dimension || patch.indices.len() != patch.values.len() {
+            return Err(CantorError::StateReconstructionFailed(format!(
+                "patch for dimension {} does not apply to a {}-dimensional state",
+                patch.dimension,
+                self.data.len()
+            )));
+        }
+
+        let mut data = self.data.clone();
+        for (&index, &value) in patch.indices.iter().zip(&patch.values) {
+            let slot = data.get_mut(index as usize).ok_or_else(|| {
+                CantorError::StateReconstructionFailed(format!(
+                    "patch index {} is out of bounds",
+                    index
+                ))
+            })?;
+            *slot = value;
+        }
+        Ok(StateVector::new(data))
+    }
+}
+
+/// Changed indices and their new values between two equal-length states.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct StatePatch {
+    pub dimension: usize,
+    pub indices: Vec<u32>,
+    pub values: Vec<f32>,
+}
+
+/// Hash a state supplied as a stream of floats.
+///
+/// Each value's little-endian bytes are fed to SHA-256 as they arrive, so
+/// the result equals `StateVector::compute_hash` over the collected values
+/// without materializing them.
+pub fn hash_state_iter<I: Iterator<Item = f32>>(iter: I) -> Hash32 {
+    use sha2::{Sha256, Digest};
+    let mut hasher = Sha256::new();
+    for value in iter {
+        hasher.update(value.to_le_bytes());
+    }
+    Hash32::from_slice(&hasher.finalize()).unwrap()
+}
+
+/// Compression method used to encode a delta.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum CompressionMethod {
+    #[default]
+    Lz4,
+    Varint,
+    RunLength,
+    /// Contiguous non-zero segments with their start index; gaps are zero.
+    SegmentedSparse,
+    /// `Varint` quantization followed by a static rANS entropy coder.
+    VarintRans,
+    /// Raw little-endian `f32` bytes in a Zstandard frame.
+    Zstd,
+    /// Each element rounded to a 16-bit float, then LZ4-packed. The
+    /// frame header records which 16-bit format.
+    Float16,
+    /// `Varint` quantization of the first element, then of each element's
+    /// difference from the previous one, for smoothly varying deltas.
+    DeltaOfDelta,
+    /// `Varint` quantization with every value packed at the bit width of
+    /// the widest, for deltas confined to a small integer range.
+    BitPack,
+    /// Whichever lossless method gives the smallest payload for each
+    /// delta; the frame header records the one chosen.
+    Auto,
+}
+
+/// Encoder settings a producer used for a block.
+///
+/// Carried in [`CompressionResult`] so that the verifier decodes with the
+/// producer's exact settings instead of its own defaults.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct QuantizationParams {
+    /// Fixed-point scale for `Varint`, `VarintRans`, `DeltaOfDelta` and
+    /// `BitPack` (a value `v` is stored as `round(v * scale)`).
+    pub scale: f32,
+    /// Magnitude below which `RunLength` and `SegmentedSparse` treat a
+    /// value as zero.
+    pub zero_threshold: f32,
+    pub method: CompressionMethod,
+}
+
+impl QuantizationParams {
+    pub const DEFAULT_SCALE: f32 = 1000.0;
+    pub const DEFAULT_ZERO_THRESHOLD: f32 = 1e-6;
+
+    /// Default settings for `method`.
+    pub fn for_method(method: CompressionMethod) -> Self {
+        Self {
+            method,
+            ..Self::default()
+        }
+    }
+}
+
+impl Default for QuantizationParams {
+    fn default() -> Self {
+        Self {
+            scale: Self::DEFAULT_SCALE,
+            zero_threshold: Self::DEFAULT_ZERO_THRESHOLD,
+            method: CompressionMethod::default(),
+        }
+    }
+}
+
+/// Encoded delta bytes, carried inline or referenced by their hash.
+///
+/// A proof holding `ByHash` is smaller by the whole payload, for clients
+/// that already have the deltas; the bytes are looked up in a
+/// [`DeltaStore`](crate::DeltaStore) at verification time.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DeltaRef {
+    Inline(Vec<u8>),
+    /// SHA-256 of the encoded bytes.
+    ByHash(Hash32),
+}
+
+impl DeltaRef {
+    /// SHA-256 of the referenced bytes, computed for `Inline`.
+    pub fn content_hash(&self) -> Hash32 {
+        use sha2::{Sha256, Digest};
+        match self {
+            DeltaRef::Inline(bytes) => Hash32::from_slice(&Sha256::digest(bytes)).unwrap(),
+            DeltaRef::ByHash(hash) => *hash,
+        }
+    }
+
+    /// The same delta referenced by hash.
+    pub fn to_by_hash(&self) -> Self {
+        DeltaRef::ByHash(self.content_hash())
+    }
+
+    /// The inline bytes, or `CantorError::DeltaNotFound` for a reference
+    /// that needs a store to resolve.
+    pub fn inline_bytes(&self) -> Result<&[u8]> {
+        match self {
+            DeltaRef::Inline(bytes) => Ok(bytes),
+            DeltaRef::ByHash(hash) => Err(CantorError::DeltaNotFound(*hash)),
+        }
+    }
+}
+
+impl From<Vec<u8>> for DeltaRef {
+    fn from(bytes: Vec<u8>) -> Self {
+        DeltaRef::Inline(bytes)
     }
 }
 
 /// Delta between predicted and actual state.
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct StateDelta {
     pub tx_hash: Hash32,
     pub predicted_root: Hash32,
     pub actual_root: Hash32,
-    pub delta_bytes: Vec<u8>,
+    pub delta_bytes: DeltaRef,
     pub confidence: f32,
+    /// Method `delta_bytes` was encoded with.
+    #[serde(default)]
+    pub method: CompressionMethod,
+}
+
+impl StateDelta {
+    /// Equality on everything but `confidence`, which may legitimately
+    /// differ between runs that produced the same delta.
+    pub fn content_eq(&self, other: &Self) -> bool {
+        self.tx_hash == other.tx_hash
+            && self.predicted_root == other.predicted_root
+            && self.actual_root == other.actual_root
+            && self.delta_bytes == other.delta_bytes
+            && self.method == other.method
+    }
+}
+
+/// Transaction metadata committed into a tree leaf alongside the delta.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LeafMetadata {
+    pub tx_hash: Hash32,
+    pub block_number: u64,
+}
+
+impl LeafMetadata {
+    /// Leaf hash binding a delta to its transaction:
+    /// `H(tx_hash || block_number_le || delta_bytes)`.
+    pub fn leaf_hash(&self, delta_bytes: &[u8]) -> Hash32 {
+        use sha2::{Sha256, Digest};
+        let mut hasher = Sha256::new();
+        hasher.update(self.tx_hash.as_ref());
+        hasher.update(self.block_number.to_le_bytes());
+        hasher.update(delta_bytes);
+        Hash32::from_slice(&hasher.finalize()).unwrap()
+    }
 }
 
 /// Merkle proof for a delta.
+///
+/// Each level contributes the proven node's siblings to `path`, in order,
+/// and its position among them to `indices`. A binary tree has one
+/// sibling and a side bit per level; a k-ary tree has `k - 1` siblings and
+/// a position below `k`, so the arity is implied by the lengths.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct MerkleProof {
     pub leaf_hash: Hash32,
@@ -91,25 +350,192 @@ pub struct MerkleProof {
 }
 
 impl MerkleProof {
+    /// Fold the path into a root and compare it with `root`.
+    ///
+    /// Padding siblings appear in `path` like any other node, so the
+    /// producer's padding convention needs no separate configuration here.
     pub fn verify(&self, root: &Hash32) -> bool {
-        use sha2::{Sha256, Digest};
-        
+        self.compute_root() == *root
+    }
+
+    /// [`Self::verify`] for a tree built with `hasher`, or a bare
+    /// [`HashAlgorithm`] under the original layout.
+    pub fn verify_with(&self, root: &Hash32, hasher: impl Into<TreeHasher>) -> bool {
+        self.compute_root_with(hasher) == *root
+    }
+
+    /// Branching factor of the tree the proof comes from: one more than
+    /// the siblings per level, and at least 2.
+    pub fn arity(&self) -> usize {
+        match self.indices.len() {
+            0 => 2,
+            levels => (self.path.len() / levels + 1).max(2),
+        }
+    }
+
+    /// Position of the proven leaf, read from the path's positions, or
+    /// `None` if the positions describe a leaf past `usize::MAX`.
+    pub fn leaf_index(&self) -> Option<usize> {
+        let arity = self.arity();
+        self.indices
+            .iter()
+            .rev()
+            .try_fold(0usize, |acc, &position| {
+                acc.checked_mul(arity)?
+                    .checked_add((position as usize).min(arity - 1))
+            })
+    }
+
+    /// Root implied by folding the leaf hash up the path.
+    ///
+    /// At each level the node is hashed with its siblings, inserted at its
+    /// position; positions past the last sibling count as the last.
+    pub fn compute_root(&self) -> Hash32 {
+        self.compute_root_with(HashAlgorithm::Sha256)
+    }
+
+    /// [`Self::compute_root`], hashing each level with `hasher`.
+    pub fn compute_root_with(&self, hasher: impl Into<TreeHasher>) -> Hash32 {
+        let hasher = hasher.into();
+        let arity = self.arity();
         let mut current = self.leaf_hash;
         
-        for (sibling, &index) in self.path.iter().zip(self.indices.iter()) {
-            let combined = if index == 0 {
-                [current.as_ref(), sibling.as_ref()].concat()
-            } else {
-                [sibling.as_ref(), current.as_ref()].concat()
-            };
-            let result = Sha256::digest(&combined);
-            current = Hash32::from_slice(&result).unwrap();
+        for (siblings, &position) in self.path.chunks(arity - 1).zip(&self.indices) {
+            let (before, after) = siblings.split_at((position as usize).min(siblings.len()));
+            current = hasher.hash_node(before.iter().chain([&current]).chain(after));
         }
         
-        current == *root
+        current
+    }
+
+    /// Verify a proof from a metadata-committing tree.
+    ///
+    /// The leaf is recomputed from the caller-supplied metadata and delta,
+    /// so a proof cannot be re-attributed to a different transaction.
+    pub fn verify_with_metadata(
+        &self,
+        root: &Hash32,
+        metadata: &LeafMetadata,
+        delta_bytes: &[u8],
+    ) -> bool {
+        metadata.leaf_hash(delta_bytes) == self.leaf_hash && self.verify(root)
+    }
+
+    /// Verify against a [`model_commitment`] for `model_version`.
+    pub fn verify_model_bound(&self, commitment: &Hash32, model_version: &str) -> bool {
+        model_commitment(&self.compute_root(), model_version) == *commitment
+    }
+
+    /// Lay the proof's hashes out as field-element-sized limbs for a
+    /// circuit.
+    ///
+    /// The leaf hash comes first, then each path node from the leaf up.
+    /// Every hash is split in byte order into `field_bytes`-sized limbs,
+    /// and always starts a new limb; when `field_bytes` does not divide 32,
+    /// the hash's last limb is right-padded with zero bytes. Each hash
+    /// therefore takes `ceil(32 / field_bytes)` limbs. The side bits in
+    /// `indices` are not included, since circuits take them as bits.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `field_bytes` is zero.
+    pub fn to_field_limbs(&self, field_bytes: usize) -> Vec<Vec<u8>> {
+        assert!(field_bytes > 0, "field_bytes must be non-zero");
+        std::iter::once(&self.leaf_hash)
+            .chain(&self.path)
+            .flat_map(|hash| hash.0.chunks(field_bytes))
+            .map(|chunk| {
+                let mut limb = chunk.to_vec();
+                limb.resize(field_bytes, 0);
+                limb
+            })
+            .collect()
     }
 }
 
+/// Proof for several leaves of one tree, sharing the nodes their paths
+/// have in common.
+///
+/// `leaves` holds the proven leaves by index, strictly increasing. They
+/// are folded up `depth` levels of an `arity`-ary tree, each parent hashed
+/// from its children in order. A child the fold cannot compute from the
+/// leaves below is taken from `nodes`, which lists them in the order
+/// needed: level by level from the leaves, left to right within a level.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MultiProof {
+    pub leaves: Vec<(usize, Hash32)>,
+    pub nodes: Vec<Hash32>,
+    pub arity: usize,
+    pub depth: usize,
+}
+
+impl MultiProof {
+    /// Fold the leaves into a root and compare it with `root`.
+    pub fn verify(&self, root: &Hash32) -> bool {
+        self.verify_with(root, HashAlgorithm::Sha256)
+    }
+
+    /// [`Self::verify`] for a tree built with `hasher`.
+    pub fn verify_with(&self, root: &Hash32, hasher: impl Into<TreeHasher>) -> bool {
+        self.compute_root_with(hasher) == Some(*root)
+    }
+
+    /// Root implied by the proof, or `None` if it is malformed: an arity
+    /// outside the 2 to 256 that trees are built with, no leaves, indices
+    /// out of order or past the tree's width, or `nodes` not exactly the
+    /// ones the fold needs.
+    pub fn compute_root_with(&self, hasher: impl Into<TreeHasher>) -> Option<Hash32> {
+        let hasher = hasher.into();
+        if !(2..=256).contains(&self.arity) {
+            return None;
+        }
+        let width = self.arity.checked_pow(self.depth.try_into().ok()?)?;
+        if self.leaves.last()?.0 >= width
+            || !self.leaves.windows(2).all(|pair| pair[0].0 < pair[1].0)
+        {
+            return None;
+        }
+
+        let mut nodes = self.nodes.iter();
+        let mut level = self.leaves.clone();
+        for _ in 0..self.depth {
+            let mut next = Vec::new();
+            let mut known = level.iter().peekable();
+            while let Some(&&(index, _)) = known.peek() {
+                let parent = index / self.arity;
+                let mut children = Vec::with_capacity(self.arity);
+                for child in parent * self.arity..(parent + 1) * self.arity {
+                    match known.next_if(|(i, _)| *i == child) {
+                        Some((_, hash)) => children.push(*hash),
+                        None => children.push(*nodes.next()?),
+                    }
+                }
+                next.push((parent, hasher.hash_node(&children)));
+            }
+            level = next;
+        }
+
+        if nodes.next().is_some() {
+            return None;
+        }
+        Some(level[0].1)
+    }
+}
+
+/// Commitment binding a tree root to the model version that produced it:
+/// `H(root || H(model_version))`.
+///
+/// Publishing this instead of the bare root ties every proof under it to
+/// one model version cryptographically, rather than by string comparison.
+pub fn model_commitment(root: &Hash32, model_version: &str) -> Hash32 {
+    use sha2::{Sha256, Digest};
+    let model_hash = Sha256::digest(model_version.as_bytes());
+    let mut hasher = Sha256::new();
+    hasher.update(root.as_ref());
+    hasher.update(model_hash);
+    Hash32::from_slice(&hasher.finalize()).unwrap()
+}
+
 /// Verification proof for a transaction.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct VerificationProof {
@@ -118,6 +544,47 @@ pub struct VerificationProof {
     pub delta: StateDelta,
     pub merkle_proof: MerkleProof,
     pub model_version: String,
+    /// Hash function and preimage layout of the tree `merkle_proof` was
+    /// generated from, which its path must be folded with.
+    #[serde(default)]
+    pub tree_hasher: TreeHasher,
+}
+
+impl VerificationProof {
+    /// Root `merkle_proof` folds to under [`Self::tree_hasher`].
+    pub fn merkle_root(&self) -> Hash32 {
+        self.merkle_proof.compute_root_with(self.tree_hasher)
+    }
+
+    /// Whether `merkle_proof` folds to `root` under [`Self::tree_hasher`].
+    pub fn verify_merkle(&self, root: &Hash32) -> bool {
+        self.merkle_root() == *root
+    }
+}
+
+/// Sequence of sub-step proofs within one transaction.
+///
+/// Each step's `delta.predicted_root` must equal the previous step's
+/// `delta.actual_root`, so the steps form a verifiable chain of state
+/// transitions.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct DeltaChain {
+    pub steps: Vec<VerificationProof>,
+}
+
+impl DeltaChain {
+    pub fn new(steps: Vec<VerificationProof>) -> Self {
+        Self { steps }
+    }
+
+    /// Index of the first step whose predicted root does not match the
+    /// previous step's actual root.
+    pub fn first_broken_link(&self) -> Option<usize> {
+        self.steps
+            .windows(2)
+            .position(|pair| pair[1].delta.predicted_root != pair[0].delta.actual_root)
+            .map(|i| i + 1)
+    }
 }
 
 /// Compression result for a block.
@@ -129,12 +596,85 @@ pub struct CompressionResult {
     pub delta_tree_root: Hash32,
     pub deltas: Vec<StateDelta>,
     pub proofs: Vec<VerificationProof>,
+    /// Encoder settings the deltas were produced with.
+    #[serde(default)]
+    pub quantization: QuantizationParams,
 }
 
 impl CompressionResult {
     pub fn compression_ratio(&self) -> f64 {
         self.original_size as f64 / self.compressed_size.max(1) as f64
     }
+
+    /// Check that `proofs[i]` belongs to `deltas[i]` for every index.
+    ///
+    /// Fails with the first index whose proof has a different tx_hash or
+    /// embeds a different delta, or where one list runs out early.
+    /// Confidences are compared by their bits, so a NaN confidence pairs
+    /// with the same NaN rather than with nothing.
+    pub fn validate_pairing(&self) -> Result<()> {
+        for (i, (proof, delta)) in self.proofs.iter().zip(&self.deltas).enumerate() {
+            if proof.tx_hash != delta.tx_hash
+                || !proof.delta.content_eq(delta)
+                || proof.delta.confidence.to_bits() != delta.confidence.to_bits()
+            {
+                return Err(CantorError::PairingMismatch(i));
+            }
+        }
+
+        if self.proofs.len() != self.deltas.len() {
+            return Err(CantorError::PairingMismatch(
+                self.proofs.len().min(self.deltas.len()),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Succinct digest for comparing blocks across nodes.
+    ///
+    /// SHA-256 over a domain label, `block_number` (`u64` LE),
+    /// `delta_tree_root`, the delta count (`u64` LE) and each delta's
+    /// `tx_hash` and `actual_root` in order. The delta bytes are covered
+    /// through the tree root; sizes, confidences and quantization are not.
+    pub fn digest(&self) -> Hash32 {
+        let header = [
+            &b"cantor-block-digest"[..],
+            &self.block_number.to_le_bytes(),
+            self.delta_tree_root.as_ref(),
+            &(self.deltas.len() as u64).to_le_bytes(),
+        ];
+        let deltas = self
+            .deltas
+            .iter()
+            .flat_map(|delta| [delta.tx_hash.as_ref(), delta.actual_root.as_ref()]);
+        HashAlgorithm::Sha256.hash_parts(header.into_iter().chain(deltas))
+    }
+
+    /// The block's proofs packaged with the root they verify against.
+    pub fn proof_batch(&self) -> ProofBatch {
+        ProofBatch {
+            root: self.delta_tree_root,
+            block_number: self.block_number,
+            proofs: self.proofs.clone(),
+            quantization: self.quantization,
+        }
+    }
+}
+
+/// Proofs for one block, all verifying against a single `root`.
+///
+/// Carrying the root with the proofs means a receiver cannot check them
+/// against the wrong block's root by mistake.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ProofBatch {
+    /// The block's `delta_tree_root`.
+    pub root: Hash32,
+    pub block_number: u64,
+    pub proofs: Vec<VerificationProof>,
+    /// Encoder settings the proofs' deltas were produced with.
+    #[serde(default)]
+    pub quantization: QuantizationParams,
 }
 
 #[cfg(test)]
@@ -148,6 +688,218 @@ mod tests {
         assert_eq!(hash.0, bytes);
     }
 
+    #[test]
+    fn test_hash32_hex_into_matches_display() {
+        let hash = StateVector::new(vec![1.0, 2.0, 3.0]).compute_hash();
+        let mut buf = [0u8; 66];
+        assert_eq!(hash.encode_hex_into(&mut buf), hash.to_string());
+        assert_eq!(&hash.to_hex_array()[..], hash.to_string().as_bytes());
+        assert_eq!(
+            Hash32::ZERO.encode_hex_into(&mut buf),
+            format!("0x{}", "0".repeat(64))
+        );
+    }
+
+    #[test]
+    fn test_field_limbs_reassemble() {
+        let proof = MerkleProof {
+            leaf_hash: Hash32([7u8; 32]),
+            path: (0..3u8).map(|i| Hash32([i * 40 + 1; 32])).collect(),
+            indices: vec![0, 1, 0],
+        };
+        let hashes: Vec<Hash32> =
+            std::iter::once(proof.leaf_hash).chain(proof.path.clone()).collect();
+
+        for field_bytes in [31, 16, 8, 32] {
+            let limbs = proof.to_field_limbs(field_bytes);
+            let per_hash = 32usize.div_ceil(field_bytes);
+            assert_eq!(limbs.len(), hashes.len() * per_hash);
+            assert!(limbs.iter().all(|limb| limb.len() == field_bytes));
+
+            for (hash, chunk) in hashes.iter().zip(limbs.chunks(per_hash)) {
+                let bytes = chunk.concat();
+                assert_eq!(&bytes[..32], hash.as_ref());
+                assert!(bytes[32..].iter().all(|&b| b == 0));
+            }
+        }
+    }
+
+    #[test]
+    fn test_state_delta_content_eq_ignores_confidence() {
+        let a = StateDelta {
+            tx_hash: Hash32([1u8; 32]),
+            predicted_root: Hash32([2u8; 32]),
+            actual_root: Hash32([3u8; 32]),
+            delta_bytes: vec![4, 5, 6].into(),
+            confidence: 0.9,
+            method: CompressionMethod::Lz4,
+        };
+        let jittered = StateDelta {
+            confidence: 0.85,
+            ..a.clone()
+        };
+        assert!(a.content_eq(&jittered));
+        assert_ne!(a, jittered);
+
+        let other_bytes = StateDelta {
+            delta_bytes: vec![4, 5, 7].into(),
+            ..a.clone()
+        };
+        assert!(!a.content_eq(&other_bytes));
+    }
+
+    #[test]
+    fn test_compute_hash_domain() {
+        let state = StateVector::new(vec![1.0, -2.0, 0.5]);
+        let a = state.compute_hash_domain(b"cantor/verify");
+        let b = state.compute_hash_domain(b"cantor/monitor");
+        assert_ne!(a, b);
+        assert_ne!(a, state.compute_hash());
+        assert_eq!(a, state.compute_hash_domain(b"cantor/verify"));
+        assert_eq!(state.compute_hash_domain(b""), state.compute_hash());
+    }
+
+    #[test]
+    fn test_binary_diff_and_apply_patch() {
+        let a = StateVector::new(vec![1.0, 2.0, 0.0, f32::NAN, 5.0]);
+        let b = StateVector::new(vec![1.0, 2.5, -0.0, f32::NAN, -7.0]);
+
+        let patch = a.binary_diff(&b).unwrap();
+        assert_eq!(patch.indices, vec![1, 2, 4]);
+
+        let patched = a.apply_patch(&patch).unwrap();
+        let bits = |v: &StateVector| v.data.iter().map(|f| f.to_bits()).collect::<Vec<_>>();
+        assert_eq!(bits(&patched), bits(&b));
+        assert_eq!(patched.compute_hash(), b.compute_hash());
+
+        let short = StateVector::new(vec![1.0, 2.0]);
+        assert!(a.binary_diff(&short).is_err());
+        assert!(short.apply_patch(&patch).is_err());
+    }
+
+    fn paired_result(count: u8) -> CompressionResult {
+        let deltas: Vec<StateDelta> = (0..count)
+            .map(|i| StateDelta {
+                tx_hash: Hash32([i; 32]),
+                predicted_root: Hash32::ZERO,
+                actual_root: Hash32([i; 32]),
+                delta_bytes: vec![i].into(),
+                confidence: 1.0,
+                method: CompressionMethod::Lz4,
+            })
+            .collect();
+        let proofs = deltas
+            .iter()
+            .map(|delta| VerificationProof {
+                tx_hash: delta.tx_hash,
+                predicted_state: Hash32::ZERO,
+                delta: delta.clone(),
+                merkle_proof: MerkleProof {
+                    leaf_hash: Hash32::ZERO,
+                    path: vec![],
+                    indices: vec![],
+                },
+                model_version: "v1".to_string(),
+                tree_hasher: TreeHasher::default(),
+            })
+            .collect();
+
+        CompressionResult {
+            block_number: 1,
+            original_size: 0,
+            compressed_size: 0,
+            delta_tree_root: Hash32::ZERO,
+            deltas,
+            proofs,
+            quantization: QuantizationParams::default(),
+        }
+    }
+
+    #[test]
+    fn test_validate_pairing() {
+        let result = paired_result(4);
+        assert!(result.validate_pairing().is_ok());
+
+        let mut swapped = result.clone();
+        swapped.proofs.swap(1, 2);
+        assert!(matches!(
+            swapped.validate_pairing(),
+            Err(CantorError::PairingMismatch(1))
+        ));
+
+        let mut wrong_delta = result.clone();
+        wrong_delta.proofs[3].delta.delta_bytes = vec![0xff].into();
+        assert!(matches!(
+            wrong_delta.validate_pairing(),
+            Err(CantorError::PairingMismatch(3))
+        ));
+
+        let mut short = result.clone();
+        short.proofs.pop();
+        assert!(matches!(
+            short.validate_pairing(),
+            Err(CantorError::PairingMismatch(3))
+        ));
+
+        let mut nan = result;
+        nan.deltas[2].confidence = f32::NAN;
+        nan.proofs[2].delta.confidence = f32::NAN;
+        assert!(nan.validate_pairing().is_ok());
+        nan.proofs[2].delta.confidence = -f32::NAN;
+        assert!(matches!(
+            nan.validate_pairing(),
+            Err(CantorError::PairingMismatch(2))
+        ));
+    }
+
+    #[test]
+    fn test_block_digest() {
+        let result = paired_result(3);
+        let digest = result.digest();
+        assert_eq!(result.clone().digest(), digest);
+
+        let changes: [fn(&mut CompressionResult); 6] = [
+            |r| r.block_number += 1,
+            |r| r.delta_tree_root.0[31] ^= 1,
+            |r| r.deltas[1].tx_hash.0[0] ^= 1,
+            |r| r.deltas[2].actual_root.0[0] ^= 1,
+            |r| r.deltas.swap(0, 1),
+            |r| {
+                r.deltas.pop();
+            },
+        ];
+        for (i, change) in changes.iter().enumerate() {
+            let mut changed = result.clone();
+            change(&mut changed);
+            assert_ne!(changed.digest(), digest, "change {}", i);
+        }
+
+        // Fields outside the digest leave it alone.
+        let mut resized = result;
+        resized.compressed_size = 99;
+        resized.deltas[0].confidence = 0.5;
+        assert_eq!(resized.digest(), digest);
+    }
+
+    #[test]
+    fn test_hash_state_iter_matches_collected() {
+        use sha2::{Sha256, Digest};
+
+        let inputs: Vec<Vec<f32>> = vec![
+            vec![],
+            vec![1.0],
+            vec![1.0, -2.5, 0.0, f32::MAX],
+            (0..4096).map(|i| (i as f32 * 0.01).sin()).collect(),
+        ];
+        for input in inputs {
+            let streamed = hash_state_iter(input.iter().map(|v| v * 1.0));
+            assert_eq!(streamed, StateVector::new(input.clone()).compute_hash());
+
+            let bytes: Vec<u8> = input.iter().flat_map(|f| f.to_le_bytes()).collect();
+            assert_eq!(streamed.as_bytes()[..], Sha256::digest(&bytes)[..]);
+        }
+    }
+
     #[test]
     fn test_state_vector_hash() {
         let sv = StateVector::new(vec![1.0, 2.0, 3.0]);
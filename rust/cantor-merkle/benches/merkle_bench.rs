@@ -17,6 +17,12 @@ fn bench_tree_build(c: &mut Criterion) {
                 b.iter(|| MerkleDeltaTree::build(black_box(deltas)));
             },
         );
+        #[cfg(feature = "parallel")]
+        if *size == 10000 {
+            group.bench_with_input(BenchmarkId::new("parallel", size), &delta_refs, |b, deltas| {
+                b.iter(|| MerkleDeltaTree::build_parallel(black_box(deltas)));
+            });
+        }
     }
     
     group.finish();
@@ -48,6 +54,49 @@ fn bench_proof_verification(c: &mut Criterion) {
     });
 }
 
-criterion_group!(benches, bench_tree_build, bench_proof_generation, bench_proof_verification);
+/// 100 of 1000 leaves: one multiproof against 100 separate proofs.
+fn bench_multiproof(c: &mut Criterion) {
+    let deltas: Vec<Vec<u8>> = (0..1000)
+        .map(|i| format!("delta_{}", i).into_bytes())
+        .collect();
+    let delta_refs: Vec<&[u8]> = deltas.iter().map(|d| d.as_slice()).collect();
+    let tree = MerkleDeltaTree::build(&delta_refs);
+    let root = tree.root();
+    let indices: Vec<usize> = (0..100).map(|i| i * 37 % 1000).collect();
+    let proofs: Vec<_> = indices.iter().map(|&i| tree.generate_proof(i).unwrap()).collect();
+    let multi = tree.generate_multiproof(&indices).unwrap();
+
+    let mut group = c.benchmark_group("multiproof");
+    group.bench_function("generate_separate", |b| {
+        b.iter(|| {
+            black_box(&indices)
+                .iter()
+                .map(|&i| tree.generate_proof(i).unwrap())
+                .collect::<Vec<_>>()
+        });
+    });
+    group.bench_function("generate_multi", |b| {
+        b.iter(|| tree.generate_multiproof(black_box(&indices)).unwrap());
+    });
+    group.bench_function("verify_separate", |b| {
+        b.iter(|| {
+            black_box(&proofs)
+                .iter()
+                .all(|proof| MerkleDeltaTree::verify_proof(proof, &root))
+        });
+    });
+    group.bench_function("verify_multi", |b| {
+        b.iter(|| MerkleDeltaTree::verify_multiproof(black_box(&multi), black_box(&root)));
+    });
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_tree_build,
+    bench_proof_generation,
+    bench_proof_verification,
+    bench_multiproof
+);
 criterion_main!(benches);
 
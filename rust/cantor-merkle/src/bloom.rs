@@ -0,0 +1,143 @@
+//! Bloom filter over a tree's leaf hashes, for cheap membership checks
+//! before any proof work.
+
+use cantor_core::{Hash32, TreeHasher};
+
+use crate::MerkleDeltaTree;
+
+/// Approximate set of leaf hashes, built by
+/// [`MerkleDeltaTree::bloom_filter`].
+///
+/// A negative answer is definite: the delta is not a leaf of the tree. A
+/// positive answer is only probable, wrong for about the `fp_rate` the
+/// filter was sized for when the delta is absent, so a proof is still
+/// needed before trusting it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BloomFilter {
+    bits: Vec<u64>,
+    num_bits: u64,
+    num_hashes: u32,
+    hasher: TreeHasher,
+}
+
+impl BloomFilter {
+    /// Empty filter sized for `items` entries at a false-positive rate of
+    /// `fp_rate`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `fp_rate` is not strictly between 0 and 1.
+    pub fn with_capacity(items: usize, fp_rate: f64) -> Self {
+        assert!(
+            fp_rate > 0.0 && fp_rate < 1.0,
+            "false-positive rate must be between 0 and 1"
+        );
+        let ln2 = std::f64::consts::LN_2;
+        let items = items.max(1) as f64;
+        let num_bits = (-items * fp_rate.ln() / (ln2 * ln2)).ceil().max(64.0) as u64;
+        let num_hashes = (num_bits as f64 / items * ln2).round().max(1.0) as u32;
+        Self {
+            bits: vec![0; num_bits.div_ceil(64) as usize],
+            num_bits,
+            num_hashes,
+            hasher: TreeHasher::default(),
+        }
+    }
+
+    /// Add a leaf hash.
+    pub fn insert(&mut self, leaf: &Hash32) {
+        for bit in self.bit_indices(leaf) {
+            self.bits[(bit / 64) as usize] |= 1 << (bit % 64);
+        }
+    }
+
+    /// Whether `leaf` may have been inserted. `false` is definite.
+    pub fn contains_leaf(&self, leaf: &Hash32) -> bool {
+        self.bit_indices(leaf)
+            .all(|bit| self.bits[(bit / 64) as usize] & (1 << (bit % 64)) != 0)
+    }
+
+    /// Whether `delta` may be a leaf of the tree the filter was built
+    /// from. `false` is definite.
+    ///
+    /// Hashes `delta` as a leaf with the tree's [`TreeHasher`], so a tree
+    /// built with [`MerkleDeltaTree::build_with_metadata`] must be queried
+    /// with [`Self::contains_leaf`] and the metadata leaf hash instead.
+    pub fn probably_contains(&self, delta: &[u8]) -> bool {
+        self.contains_leaf(&self.hasher.hash_leaf(delta))
+    }
+
+    /// Size of the bit array.
+    pub fn num_bits(&self) -> u64 {
+        self.num_bits
+    }
+
+    /// Bits set per entry.
+    pub fn num_hashes(&self) -> u32 {
+        self.num_hashes
+    }
+
+    /// Bit positions for `leaf` by double hashing. The leaf is already a
+    /// SHA-256 digest, so its first two words serve as the base hashes.
+    fn bit_indices(&self, leaf: &Hash32) -> impl Iterator<Item = u64> {
+        let word = |i: usize| u64::from_le_bytes(leaf.0[i * 8..i * 8 + 8].try_into().unwrap());
+        let (h1, h2) = (word(0), word(1) | 1);
+        let num_bits = self.num_bits;
+        (0..self.num_hashes as u64).map(move |i| h1.wrapping_add(i.wrapping_mul(h2)) % num_bits)
+    }
+}
+
+impl MerkleDeltaTree {
+    /// Bloom filter over the real leaves, sized for a false-positive rate
+    /// of `fp_rate`, so a client can rule out an absent delta in O(1)
+    /// before generating or requesting an O(log n) proof.
+    ///
+    /// Padding leaves are left out.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `fp_rate` is not strictly between 0 and 1.
+    pub fn bloom_filter(&self, fp_rate: f64) -> BloomFilter {
+        let mut filter = BloomFilter::with_capacity(self.leaves.len(), fp_rate);
+        filter.hasher = self.hasher;
+        for leaf in &self.leaves {
+            filter.insert(leaf);
+        }
+        filter
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bloom_filter_membership() {
+        let deltas: Vec<Vec<u8>> = (0..1000u32).map(|i| i.to_le_bytes().to_vec()).collect();
+        let refs: Vec<&[u8]> = deltas.iter().map(|d| d.as_slice()).collect();
+        let tree = MerkleDeltaTree::build(&refs);
+        let filter = tree.bloom_filter(0.01);
+
+        assert!(refs.iter().all(|delta| filter.probably_contains(delta)));
+
+        let absent = (1000..11_000u32)
+            .filter(|i| filter.probably_contains(&i.to_le_bytes()))
+            .count();
+        // 1% of 10000 expected; allow for variance.
+        assert!(absent < 200, "{} false positives", absent);
+        assert!(!filter.probably_contains(b"padding"));
+
+        let empty = MerkleDeltaTree::build(&[]).bloom_filter(0.01);
+        assert!(!empty.probably_contains(b"anything"));
+
+        let keccak = MerkleDeltaTree::build_with_hasher(
+            &refs,
+            TreeHasher::new(
+                cantor_core::HashAlgorithm::Keccak256,
+                cantor_core::TreeVersion::V2,
+            ),
+        );
+        let filter = keccak.bloom_filter(0.01);
+        assert!(refs.iter().all(|delta| filter.probably_contains(delta)));
+    }
+}
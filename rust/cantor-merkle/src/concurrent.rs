@@ -0,0 +1,118 @@
+//! Shared [`IncrementalMerkleTree`] for one writer and many readers.
+
+use std::sync::{RwLock, RwLockReadGuard};
+
+use cantor_core::{Hash32, MerkleProof, Result};
+
+use crate::IncrementalMerkleTree;
+
+/// [`IncrementalMerkleTree`] behind an `RwLock`.
+///
+/// Inserts take the write lock for the whole update, so readers see the
+/// tree either before or after an insert, never part-way through one.
+/// Separate calls may straddle an insert; use
+/// [`Self::generate_proof_with_root`] when a proof must match a root.
+pub struct ConcurrentIncrementalTree {
+    inner: RwLock<IncrementalMerkleTree>,
+}
+
+impl ConcurrentIncrementalTree {
+    pub fn new(depth: usize) -> Self {
+        Self {
+            inner: RwLock::new(IncrementalMerkleTree::new(depth)),
+        }
+    }
+
+    /// Insert the next leaf and return the new root.
+    pub fn insert(&self, leaf: Hash32) -> Hash32 {
+        self.inner
+            .write()
+            .expect("merkle tree lock poisoned")
+            .insert(leaf)
+    }
+
+    fn read(&self) -> RwLockReadGuard<'_, IncrementalMerkleTree> {
+        self.inner.read().expect("merkle tree lock poisoned")
+    }
+
+    /// Number of leaves inserted.
+    pub fn len(&self) -> usize {
+        self.read().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.read().is_empty()
+    }
+
+    pub fn root(&self) -> Hash32 {
+        self.read().root()
+    }
+
+    pub fn generate_proof(&self, index: usize) -> Result<MerkleProof> {
+        self.read().generate_proof(index)
+    }
+
+    /// A proof for `index` and the root it verifies against, read from
+    /// the same snapshot.
+    pub fn generate_proof_with_root(&self, index: usize) -> Result<(MerkleProof, Hash32)> {
+        let tree = self.read();
+        Ok((tree.generate_proof(index)?, tree.root()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MerkleDeltaTree;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    #[test]
+    fn test_readers_see_consistent_prefixes() {
+        let leaves: Vec<Hash32> = (0..200u32)
+            .map(|i| MerkleDeltaTree::hash(&i.to_le_bytes()))
+            .collect();
+        let mut sequential = IncrementalMerkleTree::new(8);
+        let mut prefix_roots = vec![sequential.root()];
+        for leaf in &leaves {
+            prefix_roots.push(sequential.insert(*leaf));
+        }
+
+        let tree = ConcurrentIncrementalTree::new(8);
+        let done = AtomicBool::new(false);
+        std::thread::scope(|scope| {
+            let readers: Vec<_> = (0..4)
+                .map(|_| {
+                    scope.spawn(|| {
+                        let mut last_prefix = 0;
+                        while !done.load(Ordering::Acquire) {
+                            let root = tree.root();
+                            let prefix = prefix_roots
+                                .iter()
+                                .position(|r| *r == root)
+                                .expect("root of no prefix");
+                            assert!(prefix >= last_prefix, "root went backwards");
+                            last_prefix = prefix;
+
+                            if prefix > 0 {
+                                let (proof, root) =
+                                    tree.generate_proof_with_root(prefix - 1).unwrap();
+                                assert!(proof.verify(&root));
+                            }
+                        }
+                    })
+                })
+                .collect();
+
+            for (i, leaf) in leaves.iter().enumerate() {
+                assert_eq!(tree.insert(*leaf), prefix_roots[i + 1]);
+            }
+            done.store(true, Ordering::Release);
+            for reader in readers {
+                reader.join().unwrap();
+            }
+        });
+
+        assert_eq!(tree.len(), leaves.len());
+        assert_eq!(tree.root(), *prefix_roots.last().unwrap());
+    }
+}
@@ -0,0 +1,135 @@
+//! Graphviz export of a [`MerkleDeltaTree`], for debugging failed proofs.
+
+use std::collections::HashSet;
+use std::fmt::Write;
+
+use cantor_core::{Hash32, Result};
+
+use crate::MerkleDeltaTree;
+
+/// Hex bytes shown per node label.
+const LABEL_BYTES: usize = 4;
+
+fn label(hash: &Hash32) -> String {
+    hash.0[..LABEL_BYTES]
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+impl MerkleDeltaTree {
+    /// The tree as a Graphviz `digraph`, one node per hash labeled with
+    /// its first bytes in hex and edges from parents to children. Node
+    /// ids are `n<level>_<index>`, leaves being level 0; padding leaves
+    /// are drawn gray. Render with `dot -Tsvg`.
+    pub fn to_dot(&self) -> String {
+        self.render_dot(&HashSet::new(), &HashSet::new())
+    }
+
+    /// [`Self::to_dot`] with the proof path of leaf `index` highlighted:
+    /// the nodes from the leaf up to the root are filled, and the siblings
+    /// the proof carries are dashed.
+    pub fn to_dot_with_path(&self, index: usize) -> Result<String> {
+        let labels = self.proof_path_labels(index)?;
+        let mut path: HashSet<(usize, usize)> = labels
+            .iter()
+            .map(|node| (node.level, node.node_index))
+            .collect();
+        path.insert((self.tree.len() - 1, 0));
+        let siblings = labels
+            .iter()
+            .flat_map(|node| {
+                let first = node.node_index - node.position;
+                (first..first + self.arity)
+                    .filter(move |&i| i != node.node_index)
+                    .map(move |i| (node.level, i))
+            })
+            .collect();
+        Ok(self.render_dot(&path, &siblings))
+    }
+
+    fn render_dot(
+        &self,
+        path: &HashSet<(usize, usize)>,
+        siblings: &HashSet<(usize, usize)>,
+    ) -> String {
+        let mut dot = String::from("digraph merkle {\n");
+        dot.push_str("    node [shape=box, fontname=\"monospace\"];\n");
+
+        for (level, nodes) in self.tree.iter().enumerate().rev() {
+            for (index, hash) in nodes.iter().enumerate() {
+                let mut attrs = format!("label=\"{}\"", label(hash));
+                if path.contains(&(level, index)) {
+                    attrs.push_str(", style=filled, fillcolor=\"#ffd27f\"");
+                } else if siblings.contains(&(level, index)) {
+                    attrs.push_str(", style=dashed");
+                }
+                if level == 0 && index >= self.leaves.len() {
+                    attrs.push_str(", color=gray, fontcolor=gray");
+                }
+                writeln!(dot, "    n{}_{} [{}];", level, index, attrs).unwrap();
+            }
+        }
+
+        for (level, nodes) in self.tree.iter().enumerate().skip(1) {
+            for index in 0..nodes.len() {
+                for child in self.arity * index..self.arity * (index + 1) {
+                    writeln!(dot, "    n{}_{} -> n{}_{};", level, index, level - 1, child).unwrap();
+                }
+            }
+        }
+
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node_line<'a>(dot: &'a str, id: &str) -> &'a str {
+        let prefix = format!("    {} [", id);
+        dot.lines().find(|l| l.starts_with(&prefix)).unwrap()
+    }
+
+    #[test]
+    fn test_to_dot_four_leaves() {
+        let tree = MerkleDeltaTree::build(&[b"a", b"b", b"c", b"d"]);
+        let dot = tree.to_dot();
+        assert!(dot.starts_with("digraph merkle {\n"));
+        assert!(dot.ends_with("}\n"));
+        assert_eq!(dot.lines().filter(|l| l.contains("[label=")).count(), 7);
+        assert_eq!(dot.lines().filter(|l| l.contains(" -> ")).count(), 6);
+        assert!(dot.contains(&format!("n2_0 [label=\"{}\"]", label(&tree.root()))));
+        assert!(dot.contains("    n1_1 -> n0_3;"));
+        assert!(!dot.contains("filled") && !dot.contains("dashed"));
+
+        let dot = tree.to_dot_with_path(2).unwrap();
+        for id in ["n0_2", "n1_1", "n2_0"] {
+            assert!(node_line(&dot, id).contains("filled"), "{}", id);
+        }
+        for id in ["n0_3", "n1_0"] {
+            assert!(node_line(&dot, id).contains("dashed"), "{}", id);
+        }
+        for id in ["n0_0", "n0_1"] {
+            let line = node_line(&dot, id);
+            assert!(
+                !line.contains("filled") && !line.contains("dashed"),
+                "{}",
+                id
+            );
+        }
+        assert!(tree.to_dot_with_path(4).is_err());
+
+        let padded = MerkleDeltaTree::build(&[b"a", b"b", b"c"]).to_dot();
+        assert!(node_line(&padded, "n0_3").contains("color=gray"));
+
+        let kary = MerkleDeltaTree::build_kary(&[b"a", b"b", b"c"], 4);
+        let dot = kary.to_dot_with_path(1).unwrap();
+        assert_eq!(dot.lines().filter(|l| l.contains(" -> ")).count(), 4);
+        for id in ["n0_0", "n0_2", "n0_3"] {
+            assert!(node_line(&dot, id).contains("dashed"), "{}", id);
+        }
+    }
+}
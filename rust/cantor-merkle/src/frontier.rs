@@ -0,0 +1,169 @@
+//! The minimal append-only state of an [`IncrementalMerkleTree`], for
+//! resuming it after a restart without replaying every leaf.
+//!
+//! The next leaf's path to the root passes right of one complete subtree
+//! at every level where the bit of `next_index` is set, and hashes those
+//! subtrees' roots as left siblings; every other sibling it meets is a
+//! zero hash or a node it writes itself. Those roots are the whole
+//! frontier: at most `depth` hashes, the same state
+//! [`MerkleAccumulator`](crate::MerkleAccumulator) keeps.
+
+use cantor_core::{CantorError, Hash32, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::IncrementalMerkleTree;
+
+/// Resumable state of an [`IncrementalMerkleTree`], from
+/// [`IncrementalMerkleTree::frontier`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Frontier {
+    /// Leaves inserted so far.
+    pub next_index: usize,
+    /// Per level from the leaves up, the rightmost complete node, present
+    /// exactly where bit `level` of `next_index` is set: the levels where
+    /// the next leaf's path passes right of it.
+    pub nodes: Vec<Option<Hash32>>,
+}
+
+impl IncrementalMerkleTree {
+    /// The state [`Self::from_frontier`] needs to continue this tree.
+    pub fn frontier(&self) -> Frontier {
+        let nodes = (0..self.depth)
+            .map(|i| {
+                let index = self.next_index >> i;
+                (index % 2 == 1).then(|| self.filled[i][index - 1 - self.first[i]])
+            })
+            .collect();
+        Frontier {
+            next_index: self.next_index,
+            nodes,
+        }
+    }
+
+    /// Resume a tree of `depth` levels from its [`Frontier`].
+    ///
+    /// The resumed tree takes further inserts and reports roots exactly as
+    /// the original would, and proves leaves inserted after resuming. It
+    /// keeps nothing left of the frontier, so proofs of earlier leaves fail
+    /// with `CantorError::TransactionNotFound`. Fails with
+    /// `CantorError::Serialization` if the frontier does not have one
+    /// entry per level, present exactly at the set bits of `next_index`,
+    /// or if the tree it describes is full.
+    pub fn from_frontier(depth: usize, frontier: Frontier) -> Result<Self> {
+        let invalid = |detail: String| CantorError::Serialization(format!("frontier: {}", detail));
+        let capacity = u32::try_from(depth)
+            .ok()
+            .and_then(|depth| 1usize.checked_shl(depth))
+            .unwrap_or(usize::MAX);
+        let next_index = frontier.next_index;
+        if depth == 0 || next_index >= capacity {
+            return Err(invalid(format!(
+                "{} leaves do not leave room in a tree of depth {}",
+                next_index, depth
+            )));
+        }
+        if frontier.nodes.len() != depth {
+            return Err(invalid(format!(
+                "{} levels for a tree of depth {}",
+                frontier.nodes.len(),
+                depth
+            )));
+        }
+
+        // Rebuild the right edge: at each level, the frontier node and
+        // the partial node over the leaves right of it, if any.
+        let zeros = Self::compute_zeros(depth);
+        let mut filled = Vec::with_capacity(depth);
+        let mut first = Vec::with_capacity(depth);
+        let mut partial: Option<Hash32> = None;
+        for (i, (node, zero)) in frontier.nodes.iter().zip(&zeros).enumerate() {
+            let index = next_index >> i;
+            if node.is_some() != (index % 2 == 1) {
+                return Err(invalid(format!(
+                    "level {} disagrees with next index {}",
+                    i, next_index
+                )));
+            }
+            filled.push(node.iter().chain(&partial).copied().collect::<Vec<_>>());
+            first.push(index - node.is_some() as usize);
+            partial = match (node, partial) {
+                (Some(left), right) => Some(Self::hash_pair(left, &right.unwrap_or(*zero))),
+                (None, Some(left)) => Some(Self::hash_pair(&left, zero)),
+                (None, None) => None,
+            };
+        }
+
+        Ok(Self {
+            depth,
+            zeros,
+            filled,
+            first,
+            next_index,
+            prune: false,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MerkleDeltaTree;
+    use cantor_core::serialization::{from_bytes, to_bytes};
+
+    #[test]
+    fn test_resume_from_frontier() {
+        let leaves: Vec<Hash32> = (0..100u8).map(|i| MerkleDeltaTree::hash(&[i])).collect();
+        for saved_at in [0, 1, 2, 37, 64] {
+            let mut control = IncrementalMerkleTree::new(7);
+            let mut original = IncrementalMerkleTree::new(7);
+            control.insert_batch(&leaves[..saved_at]);
+            original.insert_batch(&leaves[..saved_at]);
+
+            let bytes = to_bytes(&original.frontier()).unwrap();
+            let mut resumed =
+                IncrementalMerkleTree::from_frontier(7, from_bytes(&bytes).unwrap()).unwrap();
+            assert_eq!(resumed.root(), control.root(), "saved at {}", saved_at);
+            assert_eq!(resumed.len(), saved_at);
+            assert_eq!(resumed.frontier(), control.frontier());
+
+            for leaf in &leaves[saved_at..saved_at + 5] {
+                assert_eq!(resumed.insert(*leaf), control.insert(*leaf));
+            }
+            let rest = &leaves[saved_at + 5..];
+            assert_eq!(resumed.insert_batch(rest), control.insert_batch(rest));
+            assert_eq!(resumed.root(), control.root());
+            assert_eq!(resumed.frontier(), control.frontier());
+
+            for index in saved_at..leaves.len() {
+                let proof = resumed.generate_proof(index).unwrap();
+                assert!(proof.verify(&control.root()), "leaf {}", index);
+            }
+            // A one-leaf frontier is leaf 0 itself, so that one stays provable.
+            if saved_at > 1 {
+                assert!(matches!(
+                    resumed.generate_proof(0),
+                    Err(CantorError::TransactionNotFound(_))
+                ));
+            }
+        }
+    }
+
+    #[test]
+    fn test_invalid_frontier_rejected() {
+        let mut tree = IncrementalMerkleTree::new(3);
+        tree.insert_batch(&[Hash32::ZERO; 5]);
+        let frontier = tree.frontier();
+        assert!(IncrementalMerkleTree::from_frontier(3, frontier.clone()).is_ok());
+        assert!(IncrementalMerkleTree::from_frontier(4, frontier.clone()).is_err());
+
+        let mut skewed = frontier.clone();
+        skewed.next_index = 6;
+        assert!(IncrementalMerkleTree::from_frontier(3, skewed).is_err());
+
+        tree.insert_batch(&[Hash32::ZERO; 3]);
+        assert!(matches!(
+            IncrementalMerkleTree::from_frontier(3, tree.frontier()),
+            Err(CantorError::Serialization(_))
+        ));
+    }
+}
@@ -1,53 +1,275 @@
 //! High-performance Merkle tree for CANTOR delta commitments.
 
-use cantor_core::{Hash32, MerkleProof, CantorError, Result};
+pub mod bloom;
+pub mod concurrent;
+mod dot;
+pub mod frontier;
+pub mod mmr;
+#[cfg(feature = "parallel")]
+mod parallel;
+mod serialization;
+pub mod sorted;
+pub mod sparse;
+pub mod truncated;
+
+pub use bloom::*;
+pub use concurrent::*;
+pub use frontier::*;
+pub use mmr::*;
+pub use sorted::*;
+pub use sparse::*;
+pub use truncated::*;
+
+use cantor_core::{
+    Hash32, Hash32Map, HashAlgorithm, LeafMetadata, MerkleProof, MultiProof, CantorError,
+    Result, RootAccumulator, TreeHasher,
+};
+use serde::{Deserialize, Serialize};
 use sha2::{Sha256, Digest};
 
+/// How a tree fills the leaf level up to a power of its arity.
+///
+/// Producers in the ecosystem differ here, which changes the root. Proofs
+/// carry every sibling (padding nodes included), so a proof verifies
+/// against its own root regardless of the convention its producer used.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PaddingConvention {
+    /// Pad slot `i` of the leaf level with `hash(b"padding" || i)`, `i` as
+    /// a little-endian `u64`, so no two padding leaves are alike.
+    #[default]
+    Indexed,
+    /// Pad with `hash(b"padding")`, the default before [`Self::Indexed`].
+    /// Every padding leaf is the same, as is every padding subtree of a
+    /// given height.
+    Labeled,
+    /// Pad by repeating the last real leaf.
+    DuplicateLast,
+    /// Pad with the all-zero hash.
+    ZeroHash,
+}
+
+/// A node on a leaf's path to the root, with its position in the tree.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PathNode {
+    /// Height above the leaves (leaves are level 0).
+    pub level: usize,
+    /// Position of the node within its level.
+    pub node_index: usize,
+    /// Whether the node is the left (first) child of its parent.
+    pub is_left: bool,
+    /// Position among its parent's children, below the tree's arity.
+    pub position: usize,
+    pub hash: Hash32,
+    /// The node's sibling, i.e. the proof entry for this level. In a k-ary
+    /// tree, the first of its `k - 1` siblings.
+    pub sibling: Hash32,
+}
+
 /// Merkle tree for delta commitments.
+///
+/// Binary by default; [`Self::build_kary`] builds a tree whose internal
+/// nodes hash `k` children, for shorter proofs with wider levels. Hashed
+/// with SHA-256 under [`TreeVersion::V1`](cantor_core::TreeVersion::V1)
+/// by default; see [`Self::build_with_hasher`].
 pub struct MerkleDeltaTree {
     leaves: Vec<Hash32>,
     tree: Vec<Vec<Hash32>>,
     root: Hash32,
+    arity: usize,
+    hasher: TreeHasher,
+    padding: PaddingConvention,
 }
 
 impl MerkleDeltaTree {
     /// Build a new Merkle tree from delta data.
     pub fn build(deltas: &[&[u8]]) -> Self {
-        if deltas.is_empty() {
+        Self::build_with_padding(deltas, PaddingConvention::default())
+    }
+
+    /// Build a tree using a specific padding convention.
+    pub fn build_with_padding(deltas: &[&[u8]], padding: PaddingConvention) -> Self {
+        let leaves: Vec<Hash32> = deltas.iter().map(|d| Self::hash(d)).collect();
+        Self::from_leaf_hashes(leaves, padding, 2, TreeHasher::default())
+    }
+
+    /// Build a tree whose leaves, nodes and padding are all hashed with
+    /// `hasher`: a [`HashAlgorithm`] such as Keccak-256, to share roots
+    /// with an EVM proof system, or a [`TreeHasher`] that also picks the
+    /// [`TreeVersion`](cantor_core::TreeVersion). `TreeVersion::V2`
+    /// domain-separates leaves from internal nodes and changes the root.
+    ///
+    /// Proofs carry neither, so check them with
+    /// [`MerkleProof::verify_with`] and [`Self::hasher`];
+    /// [`MerkleProof::verify`] only accepts SHA-256 `V1` trees.
+    pub fn build_with_hasher(deltas: &[&[u8]], hasher: impl Into<TreeHasher>) -> Self {
+        let hasher = hasher.into();
+        let leaves = deltas.iter().map(|d| hasher.hash_leaf(d)).collect();
+        Self::from_leaf_hashes(leaves, PaddingConvention::default(), 2, hasher)
+    }
+
+    /// Build a tree in which each internal node hashes its `arity`
+    /// children, concatenated in order.
+    ///
+    /// Leaves are padded to a power of `arity` with the default padding,
+    /// so proofs have `log_arity(n)` levels of `arity - 1` siblings each.
+    /// `build_kary(deltas, 2)` is [`Self::build`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `arity` is not between 2 and 256, the positions a proof
+    /// can record.
+    pub fn build_kary(deltas: &[&[u8]], arity: usize) -> Self {
+        assert!(
+            (2..=256).contains(&arity),
+            "arity must be between 2 and 256"
+        );
+        let leaves: Vec<Hash32> = deltas.iter().map(|d| Self::hash(d)).collect();
+        Self::from_leaf_hashes(
+            leaves,
+            PaddingConvention::default(),
+            arity,
+            TreeHasher::default(),
+        )
+    }
+
+    /// Build a tree, failing with `CantorError::DuplicateLeaves` if any two
+    /// deltas hash to the same leaf.
+    pub fn build_unique(deltas: &[&[u8]]) -> Result<Self> {
+        let tree = Self::build(deltas);
+        let duplicates = tree.duplicate_leaves();
+        if !duplicates.is_empty() {
+            return Err(CantorError::DuplicateLeaves(duplicates));
+        }
+        Ok(tree)
+    }
+
+    /// Groups of leaf indices that share a hash, in order of first
+    /// occurrence. Empty when every leaf is distinct.
+    pub fn duplicate_leaves(&self) -> Vec<Vec<usize>> {
+        let mut seen: Hash32Map<usize> = Hash32Map::default();
+        let mut groups: Vec<Vec<usize>> = Vec::new();
+        for (index, leaf) in self.leaves.iter().enumerate() {
+            match seen.get(leaf) {
+                Some(&group) => groups[group].push(index),
+                None => {
+                    seen.insert(*leaf, groups.len());
+                    groups.push(vec![index]);
+                }
+            }
+        }
+        groups.retain(|group| group.len() > 1);
+        groups
+    }
+
+    /// Build a tree whose leaves commit to transaction metadata as well as
+    /// the delta bytes. Proofs must be checked with
+    /// [`MerkleDeltaTree::verify_proof_with_metadata`].
+    pub fn build_with_metadata(entries: &[(LeafMetadata, &[u8])]) -> Self {
+        let leaves = entries
+            .iter()
+            .map(|(metadata, delta)| metadata.leaf_hash(delta))
+            .collect();
+        Self::from_leaf_hashes(leaves, PaddingConvention::default(), 2, TreeHasher::default())
+    }
+
+    fn from_leaf_hashes(
+        leaves: Vec<Hash32>,
+        padding: PaddingConvention,
+        arity: usize,
+        hasher: TreeHasher,
+    ) -> Self {
+        Self::assemble(leaves, padding, arity, hasher, false)
+    }
+
+    /// [`Self::from_leaf_hashes`], hashing the padding and each level
+    /// across threads if `parallel` is set; see [`map_range`].
+    fn assemble(
+        leaves: Vec<Hash32>,
+        padding: PaddingConvention,
+        arity: usize,
+        hasher: TreeHasher,
+        parallel: bool,
+    ) -> Self {
+        if leaves.is_empty() {
             return Self {
                 leaves: vec![],
                 tree: vec![],
-                root: Self::hash(b"empty"),
+                root: Self::empty_root(hasher),
+                arity,
+                hasher,
+                padding,
             };
         }
 
-        let leaves: Vec<Hash32> = deltas.iter().map(|d| Self::hash(d)).collect();
-        
-        // Pad to power of 2
+        // Pad to a power of the arity
         let mut padded = leaves.clone();
-        let target_size = padded.len().next_power_of_two();
-        while padded.len() < target_size {
-            padded.push(Self::hash(b"padding"));
+        let mut target_size = 1;
+        while target_size < padded.len() {
+            target_size *= arity;
+        }
+        match padding {
+            PaddingConvention::Indexed => padded.extend(map_range(
+                target_size - leaves.len(),
+                parallel,
+                |i| Self::padding_leaf(hasher, leaves.len() + i),
+            )),
+            PaddingConvention::Labeled => padded.resize(target_size, hasher.hash_leaf(b"padding")),
+            PaddingConvention::DuplicateLast => {
+                padded.resize(target_size, leaves[leaves.len() - 1])
+            }
+            PaddingConvention::ZeroHash => padded.resize(target_size, Hash32::ZERO),
         }
 
-        let mut tree = vec![padded.clone()];
-        let mut current = padded;
+        let tree = build_levels(padded, arity, parallel, |children| hasher.hash_node(children));
+        let root = tree
+            .last()
+            .map(|l| l[0])
+            .unwrap_or(Self::empty_root(hasher));
 
-        while current.len() > 1 {
-            let mut next = Vec::with_capacity(current.len() / 2);
-            for chunk in current.chunks(2) {
-                let left = &chunk[0];
-                let right = chunk.get(1).unwrap_or(left);
-                let combined = [left.as_ref(), right.as_ref()].concat();
-                next.push(Self::hash(&combined));
-            }
-            tree.push(next.clone());
-            current = next;
+        Self {
+            leaves,
+            tree,
+            root,
+            arity,
+            hasher,
+            padding,
         }
+    }
+
+    /// Root of a tree with no leaves.
+    fn empty_root(hasher: TreeHasher) -> Hash32 {
+        hasher.hash_leaf(b"empty")
+    }
+
+    /// Padding leaf for slot `index` under [`PaddingConvention::Indexed`].
+    fn padding_leaf(hasher: TreeHasher, index: usize) -> Hash32 {
+        hasher.hash_leaf(&padding_label(index))
+    }
 
-        let root = tree.last().map(|l| l[0]).unwrap_or(Self::hash(b"empty"));
+    /// What the tree's padding convention puts in leaf-level slot
+    /// `index`, past the real leaves.
+    fn expected_padding(&self, index: usize) -> Hash32 {
+        match self.padding {
+            PaddingConvention::Indexed => Self::padding_leaf(self.hasher, index),
+            PaddingConvention::Labeled => self.hasher.hash_leaf(b"padding"),
+            PaddingConvention::DuplicateLast => self.leaves[self.leaves.len() - 1],
+            PaddingConvention::ZeroHash => Hash32::ZERO,
+        }
+    }
 
-        Self { leaves, tree, root }
+    /// The error for a leaf `index` outside the real leaves, telling a
+    /// padding slot apart from a position past the tree.
+    fn leaf_not_found(&self, index: usize) -> CantorError {
+        let width = self.tree.first().map_or(0, Vec::len);
+        CantorError::TransactionNotFound(if index < width {
+            format!(
+                "leaf {} is padding; the tree has {} leaves",
+                index,
+                self.leaves.len()
+            )
+        } else {
+            format!("leaf {}; the tree has {} leaves", index, self.leaves.len())
+        })
     }
 
     /// Get the root hash.
@@ -55,10 +277,70 @@ impl MerkleDeltaTree {
         self.root
     }
 
+    /// Children hashed into each internal node.
+    pub fn arity(&self) -> usize {
+        self.arity
+    }
+
+    /// Hash function the tree was built with.
+    pub fn hash_algorithm(&self) -> HashAlgorithm {
+        self.hasher.algorithm
+    }
+
+    /// Hash function and preimage layout the tree was built with.
+    pub fn hasher(&self) -> TreeHasher {
+        self.hasher
+    }
+
+    /// How the tree filled its leaf level.
+    pub fn padding(&self) -> PaddingConvention {
+        self.padding
+    }
+
+    /// Replace the delta at `index` with `new_data` and return the new
+    /// root, rehashing only the nodes on the leaf's path.
+    ///
+    /// The delta is hashed as a plain leaf with [`Self::hasher`], so this
+    /// does not suit trees from [`Self::build_with_metadata`]. Proofs
+    /// generated before the update verify only against the old root; a
+    /// fresh proof of any other leaf verifies against the new one. Under
+    /// [`PaddingConvention::DuplicateLast`], updating the last leaf also
+    /// updates the padding copies of it. Fails with
+    /// `CantorError::TransactionNotFound` if `index` is out of range.
+    pub fn update_leaf(&mut self, index: usize, new_data: &[u8]) -> Result<Hash32> {
+        if index >= self.leaves.len() {
+            return Err(self.leaf_not_found(index));
+        }
+
+        let leaf = self.hasher.hash_leaf(new_data);
+        self.leaves[index] = leaf;
+        let mut dirty = vec![index];
+        if index + 1 == self.leaves.len() && self.padding == PaddingConvention::DuplicateLast {
+            dirty.extend(index + 1..self.tree[0].len());
+        }
+        for &i in &dirty {
+            self.tree[0][i] = leaf;
+        }
+
+        for height in 1..self.tree.len() {
+            dirty.dedup_by_key(|i| *i / self.arity);
+            for i in &mut dirty {
+                *i /= self.arity;
+                let first = *i * self.arity;
+                let node = self
+                    .hasher
+                    .hash_node(&self.tree[height - 1][first..first + self.arity]);
+                self.tree[height][*i] = node;
+            }
+        }
+        self.root = self.tree[self.tree.len() - 1][0];
+        Ok(self.root)
+    }
+
     /// Generate a proof for a specific leaf index.
     pub fn generate_proof(&self, index: usize) -> Result<MerkleProof> {
         if index >= self.leaves.len() {
-            return Err(CantorError::TransactionNotFound(index.to_string()));
+            return Err(self.leaf_not_found(index));
         }
 
         let mut path = Vec::new();
@@ -66,14 +348,24 @@ impl MerkleDeltaTree {
         let mut current_index = index;
 
         for level in &self.tree[..self.tree.len().saturating_sub(1)] {
-            let sibling_index = current_index ^ 1;
-            if sibling_index < level.len() {
-                path.push(level[sibling_index]);
-                indices.push((sibling_index % 2) as u8);
+            let position = current_index % self.arity;
+            let first = current_index - position;
+            for (i, sibling) in level[first..first + self.arity].iter().enumerate() {
+                if i != position {
+                    path.push(*sibling);
+                }
             }
-            current_index /= 2;
+            indices.push(position as u8);
+            current_index /= self.arity;
         }
 
+        // Real leaves come first in the padded level, so this only fails
+        // if padding or indexing is broken.
+        debug_assert_eq!(
+            self.tree[0][index], self.leaves[index],
+            "padded leaf {} diverges from the leaf it was built from",
+            index
+        );
         Ok(MerkleProof {
             leaf_hash: self.tree[0][index],
             path,
@@ -81,23 +373,322 @@ impl MerkleDeltaTree {
         })
     }
 
-    /// Verify a proof against the root.
+    /// Generate one proof for the leaves at `indices`, carrying each node
+    /// their paths share once. Duplicate indices are proven once.
+    ///
+    /// Proving many leaves this way needs fewer nodes than separate
+    /// proofs, and verifying hashes each shared node once. Verify with
+    /// [`Self::verify_multiproof`], or [`MultiProof::verify_with`] and
+    /// [`Self::hasher`] for a tree not built with the default hasher.
+    pub fn generate_multiproof(&self, indices: &[usize]) -> Result<MultiProof> {
+        let mut known = indices.to_vec();
+        known.sort_unstable();
+        known.dedup();
+        if let Some(&index) = known.iter().find(|&&index| index >= self.leaves.len()) {
+            return Err(self.leaf_not_found(index));
+        }
+
+        let leaves = known
+            .iter()
+            .map(|&index| (index, self.tree[0][index]))
+            .collect();
+        let depth = self.tree.len().saturating_sub(1);
+        let mut nodes = Vec::new();
+        for level in &self.tree[..depth] {
+            let mut parents = Vec::new();
+            let mut below = known.iter().peekable();
+            while let Some(&&index) = below.peek() {
+                let parent = index / self.arity;
+                let first = parent * self.arity;
+                for (child, hash) in level[first..first + self.arity].iter().enumerate() {
+                    if below.next_if(|&&index| index == first + child).is_none() {
+                        nodes.push(*hash);
+                    }
+                }
+                parents.push(parent);
+            }
+            known = parents;
+        }
+
+        Ok(MultiProof {
+            leaves,
+            nodes,
+            arity: self.arity,
+            depth,
+        })
+    }
+
+    /// Check that every real leaf's proof would carry that leaf's own
+    /// hash and verifies against the root, and that every padding slot
+    /// holds what [`Self::padding`] puts there, failing with
+    /// [`CantorError::PairingMismatch`] at the first slot that does not.
+    ///
+    /// Costs one proof per leaf; meant for tests and debugging.
+    pub fn verify_self_consistency(&self) -> Result<()> {
+        for (index, leaf) in self.leaves.iter().enumerate() {
+            // Compared before generating the proof, whose debug assertion
+            // would otherwise panic instead of reporting the index.
+            if self.tree[0][index] != *leaf
+                || !self.generate_proof(index)?.verify_with(&self.root, self.hasher)
+            {
+                return Err(CantorError::PairingMismatch(index));
+            }
+        }
+        let bottom = self.tree.first().map_or(&[][..], Vec::as_slice);
+        for (index, pad) in bottom.iter().enumerate().skip(self.leaves.len()) {
+            if *pad != self.expected_padding(index) {
+                return Err(CantorError::PairingMismatch(index));
+            }
+        }
+        Ok(())
+    }
+
+    /// Positional labels for every node on a leaf's path, from the leaf up
+    /// to (but excluding) the root. Intended for rendering proofs.
+    pub fn proof_path_labels(&self, index: usize) -> Result<Vec<PathNode>> {
+        if index >= self.leaves.len() {
+            return Err(self.leaf_not_found(index));
+        }
+
+        let mut labels = Vec::with_capacity(self.tree.len().saturating_sub(1));
+        let mut node_index = index;
+
+        for (level, nodes) in self.tree[..self.tree.len() - 1].iter().enumerate() {
+            let position = node_index % self.arity;
+            let first = node_index - position;
+            labels.push(PathNode {
+                level,
+                node_index,
+                is_left: position == 0,
+                position,
+                hash: nodes[node_index],
+                sibling: nodes[if position == 0 { first + 1 } else { first }],
+            });
+            node_index /= self.arity;
+        }
+
+        Ok(labels)
+    }
+
+    /// Verify a proof against the root of a SHA-256 `V1` tree. Proofs of
+    /// trees built with another hasher need [`MerkleProof::verify_with`]
+    /// and the tree's [`Self::hasher`], which a
+    /// [`VerificationProof`](cantor_core::VerificationProof) records as
+    /// its `tree_hasher`.
     pub fn verify_proof(proof: &MerkleProof, root: &Hash32) -> bool {
         proof.verify(root)
     }
 
+    /// Verify a multiproof against the root of a SHA-256 tree.
+    pub fn verify_multiproof(proof: &MultiProof, root: &Hash32) -> bool {
+        proof.verify(root)
+    }
+
+    /// Verify a proof from a metadata-committing tree.
+    pub fn verify_proof_with_metadata(
+        proof: &MerkleProof,
+        root: &Hash32,
+        metadata: &LeafMetadata,
+        delta: &[u8],
+    ) -> bool {
+        proof.verify_with_metadata(root, metadata, delta)
+    }
+
     fn hash(data: &[u8]) -> Hash32 {
         let result = Sha256::digest(data);
         Hash32::from_slice(&result).unwrap()
     }
 }
 
+/// Preimage of the [`PaddingConvention::Indexed`] leaf for slot `index`:
+/// `b"padding"` followed by `index` as a little-endian `u64`.
+fn padding_label(index: usize) -> [u8; 15] {
+    let mut label = [0u8; 15];
+    label[..7].copy_from_slice(b"padding");
+    label[7..].copy_from_slice(&(index as u64).to_le_bytes());
+    label
+}
+
+/// `f` over `0..n`, in order. With the `parallel` feature and `parallel`
+/// set, the calls are spread across rayon's thread pool.
+fn map_range<T: Send>(n: usize, parallel: bool, f: impl Fn(usize) -> T + Send + Sync) -> Vec<T> {
+    #[cfg(feature = "parallel")]
+    if parallel {
+        use rayon::prelude::*;
+        return (0..n).into_par_iter().map(f).collect();
+    }
+    #[cfg(not(feature = "parallel"))]
+    let _ = parallel;
+    (0..n).map(f).collect()
+}
+
+/// Every level of a tree over `bottom`, already padded to a power of
+/// `arity`, up to the single root, each node being `node` over its
+/// children. Levels are hashed across threads if `parallel` is set; see
+/// [`map_range`].
+fn build_levels<T: Send + Sync>(
+    bottom: Vec<T>,
+    arity: usize,
+    parallel: bool,
+    node: impl Fn(&[T]) -> T + Send + Sync,
+) -> Vec<Vec<T>> {
+    let mut tree = vec![bottom];
+    while let Some(below) = tree.last().filter(|level| level.len() > 1) {
+        let next = map_range(below.len() / arity, parallel, |i| {
+            node(&below[i * arity..(i + 1) * arity])
+        });
+        tree.push(next);
+    }
+    tree
+}
+
+/// Streaming root computation matching [`MerkleDeltaTree::build`].
+///
+/// Keeps one pending subtree root per level, like a binary counter, so
+/// pushing a leaf costs amortized O(1) hashes and O(log n) memory. The
+/// root pads to a power of two with the default indexed padding, so it
+/// equals the root of `build` over the same deltas in the same order.
+/// Padding leaves differ by position, so [`Self::root`] hashes the
+/// padding subtrees afresh, as many hashes as there are padding slots,
+/// without allocating. It is the [`RootAccumulator`] to hand a
+/// streaming block builder for `build`'s root.
+#[derive(Clone, Debug, Default)]
+pub struct MerkleAccumulator {
+    frontier: Vec<Option<Hash32>>,
+    len: usize,
+    hasher: TreeHasher,
+}
+
+impl MerkleAccumulator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Accumulator whose root equals that of
+    /// [`MerkleDeltaTree::build_with_hasher`] with `hasher`.
+    pub fn with_hasher(hasher: impl Into<TreeHasher>) -> Self {
+        Self {
+            hasher: hasher.into(),
+            ..Self::default()
+        }
+    }
+
+    /// Hash function and preimage layout of the tree being accumulated.
+    pub fn hasher(&self) -> TreeHasher {
+        self.hasher
+    }
+
+    /// Append a delta, hashed into a leaf as `build` does.
+    pub fn push_delta(&mut self, delta: &[u8]) {
+        self.push_leaf(self.hasher.hash_leaf(delta));
+    }
+
+    /// Append an already-hashed leaf.
+    pub fn push_leaf(&mut self, leaf: Hash32) {
+        let mut carry = leaf;
+        let mut level = 0;
+        while let Some(left) = self.frontier.get_mut(level).and_then(Option::take) {
+            carry = self.hasher.hash_node([&left, &carry]);
+            level += 1;
+        }
+        if level == self.frontier.len() {
+            self.frontier.push(None);
+        }
+        self.frontier[level] = Some(carry);
+        self.len += 1;
+    }
+
+    /// Number of leaves pushed so far.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Root over the leaves pushed so far.
+    pub fn root(&self) -> Hash32 {
+        if self.len == 0 {
+            return MerkleDeltaTree::empty_root(self.hasher);
+        }
+
+        // Fold pending subtrees from the bottom up. `acc` is the partial
+        // right edge; missing right siblings are all-padding subtrees,
+        // each the node just right of the last real leaf's ancestor.
+        let mut acc: Option<Hash32> = None;
+        for (level, node) in self.frontier.iter().enumerate() {
+            let is_top = level + 1 == self.frontier.len();
+            let pad = || self.padding_subtree(level, (((self.len - 1) >> level) + 1) << level);
+            let pair = |left: &Hash32, right: &Hash32| self.hasher.hash_node([left, right]);
+            acc = match (node, acc) {
+                (Some(left), None) if is_top => Some(*left),
+                (Some(left), None) => Some(pair(left, &pad())),
+                (Some(left), Some(right)) => Some(pair(left, &right)),
+                (None, Some(left)) => Some(pair(&left, &pad())),
+                (None, None) => None,
+            };
+        }
+        acc.expect("a non-empty accumulator has a top-level node")
+    }
+
+    /// Root of the padding subtree of height `level` over the slots from
+    /// `start`, folded leaf by leaf on a stack of one node per level.
+    fn padding_subtree(&self, level: usize, start: usize) -> Hash32 {
+        let mut stack = [Hash32::ZERO; usize::BITS as usize];
+        for (pushed, slot) in (start..start + (1 << level)).enumerate() {
+            // `pushed` leaves leave one complete subtree per set bit, and
+            // this leaf completes one more per trailing one.
+            let mut depth = pushed.count_ones() as usize;
+            let mut node = MerkleDeltaTree::padding_leaf(self.hasher, slot);
+            for _ in 0..pushed.trailing_ones() {
+                depth -= 1;
+                node = self.hasher.hash_node([&stack[depth], &node]);
+            }
+            stack[depth] = node;
+        }
+        stack[0]
+    }
+}
+
+impl RootAccumulator for MerkleAccumulator {
+    fn push_delta(&mut self, delta: &[u8]) {
+        MerkleAccumulator::push_delta(self, delta);
+    }
+
+    fn root(&self) -> Hash32 {
+        MerkleAccumulator::root(self)
+    }
+}
+
+fn hash_pair(left: &Hash32, right: &Hash32) -> Hash32 {
+    let mut hasher = Sha256::new();
+    hasher.update(left.as_ref());
+    hasher.update(right.as_ref());
+    Hash32::from_slice(&hasher.finalize()).unwrap()
+}
+
 /// Incremental Merkle tree for streaming updates.
+///
+/// A fixed-depth tree of `2^depth` leaves, filled left to right; slots not
+/// yet inserted hash as `hash(b"zero")` and its per-level combinations.
+///
+/// A tree from [`Self::new`] keeps every node it has hashed, about two
+/// per leaf, so it can prove any leaf. One from [`Self::new_pruned`] keeps
+/// only the nodes later inserts and the root read, at most two per level,
+/// and cannot prove earlier leaves.
 pub struct IncrementalMerkleTree {
     depth: usize,
     zeros: Vec<Hash32>,
+    /// Current node hashes per level, by position from `first`; nodes
+    /// right of the newest leaf's path are implicit zeros.
     filled: Vec<Vec<Hash32>>,
+    /// Position of each level's first stored node: 0, unless the tree was
+    /// restored from a [`Frontier`] or prunes, and holds no nodes left of it.
+    first: Vec<usize>,
     next_index: usize,
+    /// Drop nodes left of the frontier after every insert.
+    prune: bool,
 }
 
 impl IncrementalMerkleTree {
@@ -107,40 +698,150 @@ impl IncrementalMerkleTree {
             depth,
             zeros,
             filled: vec![vec![]; depth],
+            first: vec![0; depth],
             next_index: 0,
+            prune: false,
+        }
+    }
+
+    /// Tree that stores `O(depth)` nodes however many leaves it takes, for
+    /// callers that only need roots. It returns the same roots as
+    /// [`Self::new`]'s, but [`Self::generate_proof`] fails with
+    /// `CantorError::TransactionNotFound` for any leaf whose path it has
+    /// dropped, which is every leaf but possibly the newest.
+    pub fn new_pruned(depth: usize) -> Self {
+        Self {
+            prune: true,
+            ..Self::new(depth)
         }
     }
 
+    /// Insert the next leaf and return the new root.
     pub fn insert(&mut self, leaf: Hash32) -> Hash32 {
         let mut current = leaf;
         let mut index = self.next_index;
 
         for i in 0..self.depth {
-            if index % 2 == 0 {
+            let pos = index - self.first[i];
+            if pos == self.filled[i].len() {
                 self.filled[i].push(current);
+            } else {
+                self.filled[i][pos] = current;
+            }
+            if index.is_multiple_of(2) {
                 current = Self::hash_pair(&current, &self.zeros[i]);
             } else {
-                let sibling = self.filled[i].last().copied().unwrap_or(self.zeros[i]);
-                current = Self::hash_pair(&sibling, &current);
+                current = Self::hash_pair(&self.filled[i][pos - 1], &current);
             }
             index /= 2;
         }
 
         self.next_index += 1;
+        self.prune_left_of_frontier();
         current
     }
 
-    pub fn root(&self) -> Hash32 {
-        if self.next_index == 0 {
-            return self.zeros[self.depth - 1];
+    /// Insert `leaves` in order and return the root after the last,
+    /// leaving the tree as a sequence of [`Self::insert`] calls would.
+    ///
+    /// Each level is rehashed once over the span the batch touches, so a
+    /// parent shared by several new leaves is hashed once instead of once
+    /// per leaf.
+    pub fn insert_batch(&mut self, leaves: &[Hash32]) -> Hash32 {
+        if leaves.is_empty() {
+            return self.root();
         }
 
-        let mut current = self.filled[0].last().copied().unwrap_or(self.zeros[0]);
+        let (mut first, mut last) = (self.next_index, self.next_index + leaves.len() - 1);
+        if let Some(level) = self.filled.first_mut() {
+            level.extend_from_slice(leaves);
+        }
         for i in 1..self.depth {
-            let sibling = self.filled[i].last().copied().unwrap_or(self.zeros[i]);
-            current = Self::hash_pair(&sibling, &current);
+            let (below, level) = self.filled.split_at_mut(i);
+            let (below, offset) = (&below[i - 1], self.first[i - 1]);
+            for index in first / 2..=last / 2 {
+                let left = &below[2 * index - offset];
+                let right = below.get(2 * index + 1 - offset);
+                let node = Self::hash_pair(left, right.unwrap_or(&self.zeros[i - 1]));
+                let pos = index - self.first[i];
+                if pos == level[0].len() {
+                    level[0].push(node);
+                } else {
+                    level[0][pos] = node;
+                }
+            }
+            (first, last) = (first / 2, last / 2);
         }
-        current
+
+        self.next_index += leaves.len();
+        self.prune_left_of_frontier();
+        self.root()
+    }
+
+    /// For a pruning tree, drop every node left of the one the next leaf's
+    /// path reads or writes on each level, and of its left sibling.
+    fn prune_left_of_frontier(&mut self) {
+        if !self.prune {
+            return;
+        }
+        for (i, (level, first)) in self.filled.iter_mut().zip(&mut self.first).enumerate() {
+            let keep = (self.next_index >> i) & !1;
+            let stale = keep.saturating_sub(*first).min(level.len());
+            level.drain(..stale);
+            *first += stale;
+        }
+    }
+
+    /// Number of leaves inserted.
+    pub fn len(&self) -> usize {
+        self.next_index
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.next_index == 0
+    }
+
+    /// Root over the leaves inserted so far, equal to the value the last
+    /// `insert` returned. Each level pairs the path node with its stored
+    /// left sibling or a zero right one, so an empty tree's root is that of
+    /// `2^depth` zero leaves.
+    pub fn root(&self) -> Hash32 {
+        let top = self.depth - 1;
+        // A tree restored from a frontier is never full, so it keeps the
+        // top level from position 0.
+        debug_assert_eq!(self.first[top], 0);
+        let node = |i: usize| self.filled[top].get(i).copied().unwrap_or(self.zeros[top]);
+        Self::hash_pair(&node(0), &node(1))
+    }
+
+    /// Proof for the leaf at `index` against the current root.
+    pub fn generate_proof(&self, index: usize) -> Result<MerkleProof> {
+        if index >= self.next_index {
+            return Err(CantorError::TransactionNotFound(index.to_string()));
+        }
+        let pruned = || {
+            CantorError::TransactionNotFound(format!(
+                "{} (its path was pruned, by a pruning tree or on resuming from a frontier)",
+                index
+            ))
+        };
+
+        let mut path = Vec::with_capacity(self.depth);
+        let mut indices = Vec::with_capacity(self.depth);
+        let mut current_index = index;
+        for ((level, first), zero) in self.filled.iter().zip(&self.first).zip(&self.zeros) {
+            let pos = (current_index ^ 1).checked_sub(*first).ok_or_else(pruned)?;
+            path.push(level.get(pos).copied().unwrap_or(*zero));
+            indices.push((current_index % 2) as u8);
+            current_index /= 2;
+        }
+
+        let pos = index.checked_sub(self.first[0]).ok_or_else(pruned)?;
+        Ok(MerkleProof {
+            leaf_hash: self.filled[0][pos],
+            path,
+            indices,
+        })
     }
 
     fn compute_zeros(depth: usize) -> Vec<Hash32> {
@@ -166,6 +867,7 @@ impl IncrementalMerkleTree {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use cantor_core::TreeVersion;
 
     #[test]
     fn test_merkle_tree_build() {
@@ -185,12 +887,569 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_proof_indices_record_own_side() {
+        let deltas: Vec<&[u8]> = vec![b"delta1", b"delta2", b"delta3", b"delta4", b"delta5"];
+        let tree = MerkleDeltaTree::build(&deltas);
+
+        for i in 0..deltas.len() {
+            let proof = tree.generate_proof(i).unwrap();
+            let sides: Vec<u8> = (0..proof.indices.len()).map(|k| (i >> k) as u8 & 1).collect();
+            assert_eq!(proof.indices, sides, "leaf {}", i);
+
+            // The sibling's side, as the baseline recorded, must not verify.
+            let mut flipped = proof;
+            flipped.indices.iter_mut().for_each(|side| *side ^= 1);
+            assert!(!MerkleDeltaTree::verify_proof(&flipped, &tree.root()));
+        }
+    }
+
+    #[test]
+    fn test_update_leaf_matches_rebuild() {
+        let mut deltas: Vec<&[u8]> = vec![b"delta1", b"delta2", b"delta3", b"delta4", b"delta5"];
+        let mut tree = MerkleDeltaTree::build(&deltas);
+        let old_sibling = tree.generate_proof(3).unwrap();
+
+        let root = tree.update_leaf(2, b"corrected").unwrap();
+        deltas[2] = b"corrected";
+        assert_eq!(root, MerkleDeltaTree::build(&deltas).root());
+        assert_eq!(tree.root(), root);
+        let sibling = tree.generate_proof(3).unwrap();
+        assert!(sibling.verify(&root));
+        assert!(!old_sibling.verify(&root));
+        assert!(tree.generate_proof(2).unwrap().verify(&root));
+        assert!(matches!(
+            tree.update_leaf(5, b"x"),
+            Err(CantorError::TransactionNotFound(_))
+        ));
+
+        let v2 = TreeHasher::new(HashAlgorithm::Keccak256, TreeVersion::V2);
+        type Build = fn(&[&[u8]]) -> MerkleDeltaTree;
+        let builds: [Build; 4] = [
+            |d| MerkleDeltaTree::build_kary(d, 3),
+            |d| MerkleDeltaTree::build_with_padding(d, PaddingConvention::DuplicateLast),
+            |d| MerkleDeltaTree::build_with_padding(d, PaddingConvention::ZeroHash),
+            |d| MerkleDeltaTree::build(&d[..1]),
+        ];
+        for build in builds {
+            let mut tree = build(&deltas);
+            let last = tree.leaves.len() - 1;
+            let mut expected = deltas.clone();
+            expected[last] = b"last";
+            assert_eq!(
+                tree.update_leaf(last, b"last").unwrap(),
+                build(&expected).root()
+            );
+            tree.verify_self_consistency().unwrap();
+        }
+
+        // A last delta that hashes like the padding after it is still
+        // just a leaf outside DuplicateLast.
+        let indexed_label = [&b"padding"[..], &5u64.to_le_bytes()].concat();
+        for (padding, label) in [
+            (PaddingConvention::Labeled, &b"padding"[..]),
+            (PaddingConvention::Indexed, &indexed_label[..]),
+        ] {
+            let mut lookalike = deltas.clone();
+            lookalike[4] = label;
+            let mut tree = MerkleDeltaTree::build_with_padding(&lookalike, padding);
+            assert_eq!(tree.tree[0][4], tree.tree[0][5]);
+            lookalike[4] = b"last";
+            assert_eq!(
+                tree.update_leaf(4, b"last").unwrap(),
+                MerkleDeltaTree::build_with_padding(&lookalike, padding).root(),
+                "{:?}",
+                padding
+            );
+            tree.verify_self_consistency().unwrap();
+        }
+        let mut tree = MerkleDeltaTree::build_with_hasher(&deltas, v2);
+        let mut expected = deltas.clone();
+        expected[0] = b"first";
+        assert_eq!(
+            tree.update_leaf(0, b"first").unwrap(),
+            MerkleDeltaTree::build_with_hasher(&expected, v2).root()
+        );
+    }
+
+    #[test]
+    fn test_kary_tree_proofs() {
+        let deltas: Vec<Vec<u8>> = (0..16).map(|i| format!("delta{}", i).into_bytes()).collect();
+        let refs: Vec<&[u8]> = deltas.iter().map(|d| d.as_slice()).collect();
+        let tree = MerkleDeltaTree::build_kary(&refs, 4);
+        assert_eq!(tree.arity(), 4);
+        assert_ne!(tree.root(), MerkleDeltaTree::build(&refs).root());
+        tree.verify_self_consistency().unwrap();
+
+        for i in 0..refs.len() {
+            let proof = tree.generate_proof(i).unwrap();
+            // log4(16) = 2 levels of 3 siblings each.
+            assert_eq!(proof.indices.len(), 2);
+            assert_eq!(proof.path.len(), 6);
+            assert_eq!(proof.arity(), 4);
+            assert_eq!(proof.leaf_index(), Some(i));
+            assert!(MerkleDeltaTree::verify_proof(&proof, &tree.root()));
+
+            let mut moved = proof.clone();
+            moved.indices[0] = (moved.indices[0] + 1) % 4;
+            assert!(!moved.verify(&tree.root()));
+        }
+
+        // Padding fills up to the next power of the arity.
+        let padded = MerkleDeltaTree::build_kary(&refs[..5], 3);
+        assert_eq!(padded.generate_proof(4).unwrap().path.len(), 4);
+        padded.verify_self_consistency().unwrap();
+        assert_eq!(
+            MerkleDeltaTree::build_kary(&refs[..5], 2).root(),
+            MerkleDeltaTree::build(&refs[..5]).root()
+        );
+    }
+
+    #[test]
+    fn test_v2_rejects_second_preimage() {
+        let deltas: Vec<&[u8]> = vec![b"delta1", b"delta2", b"delta3", b"delta4"];
+        for version in [TreeVersion::V1, TreeVersion::V2] {
+            let hasher = TreeHasher::new(HashAlgorithm::Sha256, version);
+            let tree = MerkleDeltaTree::build_with_hasher(&deltas, hasher);
+            tree.verify_self_consistency().unwrap();
+
+            // The classic forgery: present the preimage of the internal
+            // node over leaves 0 and 1 as a delta, proven one level up.
+            let (left, right) = (tree.tree[0][0], tree.tree[0][1]);
+            let forged_delta = [left.0, right.0].concat();
+            let forged = MerkleProof {
+                leaf_hash: hasher.hash_leaf(&forged_delta),
+                path: vec![tree.tree[1][1]],
+                indices: vec![0],
+            };
+            let accepted = forged.verify_with(&tree.root(), hasher);
+            assert_eq!(accepted, version == TreeVersion::V1, "{:?}", version);
+        }
+
+        let v1 = MerkleDeltaTree::build_with_hasher(&deltas, HashAlgorithm::Sha256);
+        let v2 = MerkleDeltaTree::build_with_hasher(
+            &deltas,
+            TreeHasher::new(HashAlgorithm::Sha256, TreeVersion::V2),
+        );
+        assert_eq!(v1.root(), MerkleDeltaTree::build(&deltas).root());
+        assert_ne!(v2.root(), v1.root());
+        assert_eq!(v2.hasher().version, TreeVersion::V2);
+        let proof = v2.generate_proof(2).unwrap();
+        assert!(!proof.verify(&v2.root()));
+        assert!(!proof.verify_with(&v2.root(), HashAlgorithm::Sha256));
+        assert!(proof.verify_with(&v2.root(), v2.hasher()));
+        let multi = v2.generate_multiproof(&[0, 3]).unwrap();
+        assert!(multi.verify_with(&v2.root(), v2.hasher()));
+        assert!(!multi.verify(&v2.root()));
+    }
+
+    #[test]
+    fn test_multiproof_shares_nodes() {
+        let deltas: Vec<Vec<u8>> = (0..1000)
+            .map(|i| format!("delta{}", i).into_bytes())
+            .collect();
+        let refs: Vec<&[u8]> = deltas.iter().map(|d| d.as_slice()).collect();
+        let tree = MerkleDeltaTree::build(&refs);
+        let root = tree.root();
+
+        let indices: Vec<usize> = (0..100).map(|i| i * 37 % 1000).collect();
+        let multi = tree.generate_multiproof(&indices).unwrap();
+        assert!(MerkleDeltaTree::verify_multiproof(&multi, &root));
+        assert_eq!(multi.leaves.len(), 100);
+        let separate: usize = indices
+            .iter()
+            .map(|&i| tree.generate_proof(i).unwrap().path.len())
+            .sum();
+        assert!(
+            multi.nodes.len() * 2 < separate,
+            "{} vs {}",
+            multi.nodes.len(),
+            separate
+        );
+        // Order and repeats in the request do not matter.
+        let mut shuffled: Vec<usize> = indices.iter().rev().copied().collect();
+        shuffled.push(indices[0]);
+        assert_eq!(
+            tree.generate_multiproof(&shuffled).unwrap().nodes,
+            multi.nodes
+        );
+
+        let mut forged = multi.clone();
+        forged.leaves[3].1 = MerkleDeltaTree::hash(b"forged");
+        assert!(!forged.verify(&root));
+        let mut forged = multi.clone();
+        forged.nodes[7].0[0] ^= 1;
+        assert!(!forged.verify(&root));
+        let mut extra = multi.clone();
+        extra.nodes.push(Hash32::ZERO);
+        assert!(!extra.verify(&root));
+        let mut short = multi.clone();
+        short.nodes.pop();
+        assert!(!short.verify(&root));
+        let mut moved = multi.clone();
+        moved.leaves.swap(0, 1);
+        assert!(!moved.verify(&root));
+        assert!(!tree.generate_multiproof(&[]).unwrap().verify(&root));
+        assert!(tree.generate_multiproof(&[3, 1000]).is_err());
+        // An arity no tree has is refused before anything is allocated.
+        for arity in [0, 1, 257, 1 << 40] {
+            let wide = MultiProof {
+                leaves: vec![(0, root)],
+                nodes: vec![],
+                arity,
+                depth: 1,
+            };
+            assert_eq!(wide.compute_root_with(HashAlgorithm::Sha256), None);
+        }
+
+        // Every leaf of a k-ary tree over another hash, and a lone leaf.
+        let kary = MerkleDeltaTree::build_kary(&refs[..20], 3);
+        let all: Vec<usize> = (0..20).collect();
+        let multi = kary.generate_multiproof(&all).unwrap();
+        assert!(multi.verify(&kary.root()));
+        let keccak = MerkleDeltaTree::build_with_hasher(&refs[..9], HashAlgorithm::Keccak256);
+        let multi = keccak.generate_multiproof(&[0, 4, 8]).unwrap();
+        assert!(multi.verify_with(&keccak.root(), HashAlgorithm::Keccak256));
+        assert!(!multi.verify(&keccak.root()));
+        let single = MerkleDeltaTree::build(&refs[..1]);
+        assert!(single
+            .generate_multiproof(&[0])
+            .unwrap()
+            .verify(&single.root()));
+    }
+
+    #[test]
+    fn test_hash_algorithms_round_trip() {
+        let deltas: Vec<&[u8]> = vec![b"delta1", b"delta2", b"delta3", b"delta4", b"delta5"];
+        let algorithms = [
+            HashAlgorithm::Sha256,
+            HashAlgorithm::Blake3,
+            HashAlgorithm::Keccak256,
+        ];
+        assert_eq!(
+            MerkleDeltaTree::build_with_hasher(&deltas, HashAlgorithm::Sha256).root(),
+            MerkleDeltaTree::build(&deltas).root()
+        );
+
+        let trees: Vec<MerkleDeltaTree> = algorithms
+            .iter()
+            .map(|&algorithm| MerkleDeltaTree::build_with_hasher(&deltas, algorithm))
+            .collect();
+        for (tree, &algorithm) in trees.iter().zip(&algorithms) {
+            assert_eq!(tree.hash_algorithm(), algorithm);
+            tree.verify_self_consistency().unwrap();
+            for (i, delta) in deltas.iter().enumerate() {
+                let proof = tree.generate_proof(i).unwrap();
+                assert_eq!(proof.leaf_hash, algorithm.hash(delta));
+                assert!(proof.verify_with(&tree.root(), algorithm));
+                // Neither the proof nor the root carries over to another
+                // algorithm.
+                for (other_tree, &other) in trees.iter().zip(&algorithms) {
+                    if other != algorithm {
+                        assert_ne!(other_tree.root(), tree.root());
+                        assert!(!proof.verify_with(&tree.root(), other));
+                        assert!(!proof.verify_with(&other_tree.root(), other));
+                    }
+                }
+            }
+        }
+        assert!(!trees[2].generate_proof(0).unwrap().verify(&trees[2].root()));
+    }
+
+    #[test]
+    fn test_metadata_leaf_binds_transaction() {
+        let tx_a = LeafMetadata { tx_hash: Hash32([0xaa; 32]), block_number: 42 };
+        let tx_b = LeafMetadata { tx_hash: Hash32([0xbb; 32]), block_number: 42 };
+        let other = LeafMetadata { tx_hash: Hash32([0xcc; 32]), block_number: 42 };
+        let delta: &[u8] = b"delta-d";
+
+        let entries = vec![(tx_a, delta), (other, b"delta-e".as_slice())];
+        let tree = MerkleDeltaTree::build_with_metadata(&entries);
+        let proof = tree.generate_proof(0).unwrap();
+
+        assert!(MerkleDeltaTree::verify_proof_with_metadata(&proof, &tree.root(), &tx_a, delta));
+        assert!(!MerkleDeltaTree::verify_proof_with_metadata(&proof, &tree.root(), &tx_b, delta));
+
+        let wrong_block = LeafMetadata { block_number: 43, ..tx_a };
+        assert!(!MerkleDeltaTree::verify_proof_with_metadata(
+            &proof,
+            &tree.root(),
+            &wrong_block,
+            delta
+        ));
+    }
+
+    #[test]
+    fn test_padding_conventions() {
+        let deltas: Vec<&[u8]> = vec![b"delta1", b"delta2", b"delta3"];
+        let indexed = MerkleDeltaTree::build(&deltas);
+        let labeled = MerkleDeltaTree::build_with_padding(&deltas, PaddingConvention::Labeled);
+        let zero = MerkleDeltaTree::build_with_padding(&deltas, PaddingConvention::ZeroHash);
+        let duplicate =
+            MerkleDeltaTree::build_with_padding(&deltas, PaddingConvention::DuplicateLast);
+
+        assert_ne!(indexed.root(), labeled.root());
+        assert_ne!(labeled.root(), zero.root());
+        assert_ne!(labeled.root(), duplicate.root());
+
+        for tree in [&indexed, &labeled, &zero, &duplicate] {
+            for i in 0..deltas.len() {
+                let proof = tree.generate_proof(i).unwrap();
+                assert!(MerkleDeltaTree::verify_proof(&proof, &tree.root()));
+            }
+        }
+
+        // Each proof is only valid against the root of its own convention.
+        let proof = zero.generate_proof(2).unwrap();
+        assert!(!MerkleDeltaTree::verify_proof(&proof, &labeled.root()));
+    }
+
+    #[test]
+    fn test_padding_slots_are_distinct_and_unprovable() {
+        let deltas: Vec<&[u8]> = vec![b"delta1", b"delta2", b"delta3", b"delta4", b"delta5"];
+        let tree = MerkleDeltaTree::build(&deltas);
+        let padding = &tree.tree[0][deltas.len()..];
+        assert_eq!(padding.len(), 3);
+        assert_ne!(padding[0], padding[1]);
+        assert_ne!(padding[1], padding[2]);
+        assert_eq!(
+            padding[0],
+            MerkleDeltaTree::hash(b"padding\x05\0\0\0\0\0\0\0")
+        );
+
+        for index in 5..8 {
+            let err = tree.generate_proof(index).unwrap_err();
+            assert!(matches!(err, CantorError::TransactionNotFound(_)));
+            assert!(err.to_string().contains("is padding"), "{}", err);
+            assert!(tree.proof_path_labels(index).is_err());
+            assert!(tree.generate_multiproof(&[0, index]).is_err());
+        }
+        let err = tree.generate_proof(8).unwrap_err();
+        assert!(!err.to_string().contains("padding"), "{}", err);
+
+        for (index, delta) in deltas.iter().enumerate() {
+            let proof = tree.generate_proof(index).unwrap();
+            assert_eq!(proof.leaf_hash, MerkleDeltaTree::hash(delta));
+            assert!(proof.verify(&tree.root()));
+        }
+    }
+
+    #[test]
+    fn test_proof_path_labels() {
+        let deltas: Vec<Vec<u8>> = (0..8).map(|i| format!("delta{}", i).into_bytes()).collect();
+        let refs: Vec<&[u8]> = deltas.iter().map(|d| d.as_slice()).collect();
+        let tree = MerkleDeltaTree::build(&refs);
+
+        let labels = tree.proof_path_labels(3).unwrap();
+        let positions: Vec<(usize, usize, bool)> = labels
+            .iter()
+            .map(|n| (n.level, n.node_index, n.is_left))
+            .collect();
+        assert_eq!(positions, vec![(0, 3, false), (1, 1, false), (2, 0, true)]);
+
+        let proof = tree.generate_proof(3).unwrap();
+        assert_eq!(labels[0].hash, proof.leaf_hash);
+        let siblings: Vec<Hash32> = labels.iter().map(|n| n.sibling).collect();
+        assert_eq!(siblings, proof.path);
+        assert!(tree.proof_path_labels(8).is_err());
+    }
+
+    #[test]
+    fn test_build_unique_reports_duplicates() {
+        let deltas: Vec<&[u8]> = vec![b"delta1", b"same", b"delta3", b"same", b"delta5"];
+
+        match MerkleDeltaTree::build_unique(&deltas) {
+            Err(CantorError::DuplicateLeaves(groups)) => assert_eq!(groups, vec![vec![1, 3]]),
+            other => panic!("expected duplicate leaves, got {:?}", other.map(|t| t.root())),
+        }
+
+        let tree = MerkleDeltaTree::build(&deltas);
+        assert_eq!(tree.duplicate_leaves(), vec![vec![1, 3]]);
+
+        let unique: Vec<&[u8]> = vec![b"delta1", b"delta2", b"delta3"];
+        assert!(MerkleDeltaTree::build_unique(&unique).is_ok());
+    }
+
+    #[test]
+    fn test_accumulator_matches_build() {
+        let deltas: Vec<Vec<u8>> = (0..17u8).map(|i| vec![i; 3 + i as usize]).collect();
+        let mut acc = MerkleAccumulator::new();
+        assert_eq!(acc.root(), MerkleDeltaTree::build(&[]).root());
+
+        for n in 1..=deltas.len() {
+            acc.push_delta(&deltas[n - 1]);
+            let refs: Vec<&[u8]> = deltas[..n].iter().map(|d| d.as_slice()).collect();
+            assert_eq!(acc.root(), MerkleDeltaTree::build(&refs).root(), "{} leaves", n);
+        }
+        assert_eq!(acc.len(), 17);
+
+        let v2 = TreeHasher::new(HashAlgorithm::Keccak256, TreeVersion::V2);
+        let mut acc = MerkleAccumulator::with_hasher(v2);
+        let refs: Vec<&[u8]> = deltas.iter().map(|d| d.as_slice()).collect();
+        assert_eq!(acc.root(), MerkleDeltaTree::build_with_hasher(&[], v2).root());
+        for delta in &refs {
+            acc.push_delta(delta);
+        }
+        assert_eq!(acc.root(), MerkleDeltaTree::build_with_hasher(&refs, v2).root());
+    }
+
+    #[test]
+    fn test_proof_leaf_hashes_match_deltas() {
+        for n in [1usize, 2, 3, 5, 8, 13, 32] {
+            let deltas: Vec<Vec<u8>> = (0..n).map(|i| vec![i as u8; i + 1]).collect();
+            let refs: Vec<&[u8]> = deltas.iter().map(|d| d.as_slice()).collect();
+            for padding in [
+                PaddingConvention::Labeled,
+                PaddingConvention::DuplicateLast,
+                PaddingConvention::ZeroHash,
+            ] {
+                let tree = MerkleDeltaTree::build_with_padding(&refs, padding);
+                tree.verify_self_consistency().unwrap();
+                for (i, delta) in deltas.iter().enumerate() {
+                    let proof = tree.generate_proof(i).unwrap();
+                    assert_eq!(proof.leaf_hash, MerkleDeltaTree::hash(delta), "{} leaves", n);
+                }
+            }
+        }
+        MerkleDeltaTree::build(&[]).verify_self_consistency().unwrap();
+
+        let mut broken = MerkleDeltaTree::build(&[b"a", b"b", b"c"]);
+        broken.leaves[2] = MerkleDeltaTree::hash(b"z");
+        assert!(matches!(
+            broken.verify_self_consistency(),
+            Err(CantorError::PairingMismatch(2))
+        ));
+    }
+
     #[test]
     fn test_incremental_tree() {
         let mut tree = IncrementalMerkleTree::new(10);
         let leaf = Hash32::from_slice(&[1u8; 32]).unwrap();
         let root = tree.insert(leaf);
         assert_ne!(root, Hash32::ZERO);
+        assert_eq!(tree.root(), root);
+
+        let leaves: Vec<Hash32> = (0..6u8).map(|i| MerkleDeltaTree::hash(&[i])).collect();
+        let mut tree = IncrementalMerkleTree::new(3);
+        let empty = tree.root();
+        for leaf in &leaves {
+            assert_eq!(tree.insert(*leaf), tree.root());
+        }
+        assert_ne!(tree.root(), empty);
+        for (i, leaf) in leaves.iter().enumerate() {
+            let proof = tree.generate_proof(i).unwrap();
+            assert_eq!(proof.leaf_hash, *leaf);
+            assert_eq!(proof.leaf_index(), Some(i));
+            assert!(proof.verify(&tree.root()));
+        }
+        assert!(tree.generate_proof(6).is_err());
+    }
+
+    #[test]
+    fn test_incremental_batch_matches_inserts() {
+        let leaves: Vec<Hash32> = (0..80u8).map(|i| MerkleDeltaTree::hash(&[i])).collect();
+        let mut single = IncrementalMerkleTree::new(8);
+        let mut batched = IncrementalMerkleTree::new(8);
+        for leaf in &leaves[..50] {
+            single.insert(*leaf);
+        }
+        assert_eq!(batched.insert_batch(&leaves[..50]), single.root());
+        assert_eq!(batched.len(), 50);
+        assert_eq!(batched.filled, single.filled);
+
+        // Batches starting mid-pair, and empty ones, line up as well.
+        for leaf in &leaves[50..53] {
+            single.insert(*leaf);
+        }
+        batched.insert(leaves[50]);
+        assert_eq!(batched.insert_batch(&leaves[51..53]), single.root());
+        assert_eq!(batched.insert_batch(&[]), single.root());
+        for leaf in &leaves[53..] {
+            single.insert(*leaf);
+        }
+        assert_eq!(batched.insert_batch(&leaves[53..]), single.root());
+        assert_eq!(batched.len(), single.len());
+        assert_eq!(batched.filled, single.filled);
+        for index in [0, 52, 79] {
+            assert!(batched
+                .generate_proof(index)
+                .unwrap()
+                .verify(&single.root()));
+        }
+    }
+
+    #[test]
+    fn test_pruned_tree_keeps_depth_nodes() {
+        let leaves: Vec<Hash32> = (0..200u8).map(|i| MerkleDeltaTree::hash(&[i])).collect();
+        let mut full = IncrementalMerkleTree::new(9);
+        let mut pruned = IncrementalMerkleTree::new_pruned(9);
+        for (i, leaf) in leaves[..150].iter().enumerate() {
+            assert_eq!(pruned.insert(*leaf), full.insert(*leaf), "leaf {}", i);
+            assert!(pruned.filled.iter().all(|level| level.len() <= 2));
+            assert_eq!(pruned.frontier(), full.frontier());
+        }
+        assert_eq!(
+            pruned.insert_batch(&leaves[150..]),
+            full.insert_batch(&leaves[150..])
+        );
+        assert!(pruned.filled.iter().all(|level| level.len() <= 2));
+        assert_eq!(pruned.root(), full.root());
+        assert_eq!(pruned.len(), full.len());
+
+        // Only the newest leaf can still be proven, when it is a left child.
+        pruned.insert(leaves[0]);
+        full.insert(leaves[0]);
+        assert!(pruned.generate_proof(200).unwrap().verify(&full.root()));
+        assert!(matches!(
+            pruned.generate_proof(3),
+            Err(CantorError::TransactionNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_incremental_empty_root_is_all_zero_tree() {
+        for depth in [1, 3, 6] {
+            let tree = IncrementalMerkleTree::new(depth);
+            let zeros = vec![tree.zeros[0]; 1 << depth];
+            let built = MerkleDeltaTree::from_leaf_hashes(
+                zeros,
+                PaddingConvention::default(),
+                2,
+                TreeHasher::default(),
+            );
+            assert_eq!(tree.root(), built.root(), "depth {}", depth);
+        }
+    }
+
+    #[test]
+    fn test_incremental_root_matches_static_build() {
+        let leaves: Vec<Hash32> = (0..16u8).map(|i| MerkleDeltaTree::hash(&[i])).collect();
+        for depth in [4, 5] {
+            let mut tree = IncrementalMerkleTree::new(depth);
+            let zero = tree.zeros[0];
+            for n in 1..=16 {
+                tree.insert(leaves[n - 1]);
+                // Padding the static tree with the zero leaf out to the
+                // full width leaves no room for a convention to differ.
+                let mut padded = leaves[..n].to_vec();
+                padded.resize(1 << depth, zero);
+                let built = MerkleDeltaTree::from_leaf_hashes(
+                    padded,
+                    PaddingConvention::default(),
+                    2,
+                    TreeHasher::default(),
+                );
+                assert_eq!(tree.root(), built.root(), "depth {} n {}", depth, n);
+                let proof = tree.generate_proof(n - 1).unwrap();
+                assert_eq!(
+                    proof.path,
+                    built.generate_proof(n - 1).unwrap().path,
+                    "depth {} n {}",
+                    depth,
+                    n
+                );
+            }
+        }
     }
 }
 
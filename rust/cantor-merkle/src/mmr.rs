@@ -0,0 +1,331 @@
+//! Merkle mountain range for append-only logs.
+//!
+//! Nodes are stored in append order at 0-based positions: every leaf is
+//! followed by the parents it completes, so after 4 leaves the layout is
+//! `l0 l1 p01 l2 l3 p23 p0123`. The range is a list of perfect trees
+//! ("peaks") of strictly decreasing height, and it commits to them with a
+//! single bagged root, folded from the right:
+//! `H(peak_0 || H(peak_1 || ... peak_n))`.
+
+use cantor_core::{CantorError, Hash32, Result};
+
+use crate::{hash_pair, MerkleDeltaTree};
+
+/// Append-only Merkle mountain range.
+#[derive(Clone, Debug, Default)]
+pub struct MmrAccumulator {
+    nodes: Vec<Hash32>,
+    leaves: usize,
+}
+
+/// Inclusion proof for one leaf of an [`MmrAccumulator`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MmrProof {
+    /// Node position of the leaf.
+    pub position: u64,
+    /// Node count of the range the proof was generated from. It fixes the
+    /// peak above `position` and the path length, but the bagged root does
+    /// not commit to it: a verifier that relies on the position must check
+    /// it against a size it trusts.
+    pub size: u64,
+    pub leaf_hash: Hash32,
+    /// Siblings from the leaf up to its peak.
+    pub path: Vec<Hash32>,
+    /// Every peak of the range the proof was generated from, left to right.
+    pub peaks: Vec<Hash32>,
+}
+
+/// Height of the node at 0-based `position` (leaves are height 0).
+fn height(position: u64) -> u32 {
+    // In 1-based positions a node is a peak of a perfect tree exactly when
+    // its index is all ones; otherwise jump to the same node of the tree to
+    // its left.
+    let mut pos = position + 1;
+    // An all-ones `pos` is a peak; `u64::MAX` wraps to zero here.
+    while pos & pos.wrapping_add(1) != 0 {
+        let bits = u64::BITS - pos.leading_zeros();
+        pos -= (1 << (bits - 1)) - 1;
+    }
+    (u64::BITS - pos.leading_zeros()) - 1
+}
+
+/// Positions of the peaks of a range holding `size` nodes, left to right.
+fn peak_positions(size: u64) -> Vec<u64> {
+    let mut peaks = Vec::new();
+    let mut offset = 0;
+    let mut remaining = size;
+    while remaining > 0 {
+        // Largest perfect tree (2^k - 1 nodes) that fits.
+        let ones = u64::MAX >> remaining.leading_zeros();
+        let tree_size = if ones == remaining { ones } else { ones >> 1 };
+        peaks.push(offset + tree_size - 1);
+        offset += tree_size;
+        remaining -= tree_size;
+    }
+    peaks
+}
+
+/// Bag peaks into a single root, folding from the right.
+fn bag(peaks: &[Hash32]) -> Hash32 {
+    let mut iter = peaks.iter().rev();
+    let Some(last) = iter.next() else {
+        return MerkleDeltaTree::hash(b"empty");
+    };
+    iter.fold(*last, |acc, peak| hash_pair(peak, &acc))
+}
+
+impl MmrAccumulator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append a leaf hash, returning its node position.
+    pub fn append(&mut self, leaf: Hash32) -> u64 {
+        let position = self.nodes.len() as u64;
+        self.nodes.push(leaf);
+
+        // Merge while the new node is a right child: the node after it in
+        // a full range would be its parent.
+        let mut h = 0;
+        while height(self.nodes.len() as u64) > h {
+            let right = self.nodes.len() - 1;
+            let left = right + 1 - (2 << h);
+            let parent = hash_pair(&self.nodes[left], &self.nodes[right]);
+            self.nodes.push(parent);
+            h += 1;
+        }
+
+        self.leaves += 1;
+        position
+    }
+
+    /// Number of leaves appended.
+    pub fn len(&self) -> usize {
+        self.leaves
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.leaves == 0
+    }
+
+    /// Number of stored nodes, leaves and parents together.
+    pub fn size(&self) -> u64 {
+        self.nodes.len() as u64
+    }
+
+    /// Peak hashes, left (tallest) to right.
+    pub fn peaks(&self) -> Vec<Hash32> {
+        peak_positions(self.size())
+            .into_iter()
+            .map(|pos| self.nodes[pos as usize])
+            .collect()
+    }
+
+    /// The bagged root over the current peaks.
+    pub fn root(&self) -> Hash32 {
+        bag(&self.peaks())
+    }
+
+    /// Inclusion proof for the leaf at node `position`.
+    pub fn generate_proof(&self, position: u64) -> Result<MmrProof> {
+        if position >= self.size() || height(position) != 0 {
+            return Err(CantorError::TransactionNotFound(position.to_string()));
+        }
+
+        let mut path = Vec::new();
+        let mut pos = position;
+        let mut h = 0;
+        while !peak_positions(self.size()).contains(&pos) {
+            let (sibling, parent) = if height(pos + 1) > h {
+                (pos + 1 - (2 << h), pos + 1)
+            } else {
+                (pos + (2 << h) - 1, pos + (2 << h))
+            };
+            path.push(self.nodes[sibling as usize]);
+            pos = parent;
+            h += 1;
+        }
+
+        Ok(MmrProof {
+            position,
+            size: self.size(),
+            leaf_hash: self.nodes[position as usize],
+            path,
+            peaks: self.peaks(),
+        })
+    }
+
+    /// Verify a proof against a bagged root.
+    pub fn verify_proof(proof: &MmrProof, root: &Hash32) -> bool {
+        proof.verify(root)
+    }
+}
+
+impl MmrProof {
+    /// Climb from the leaf to the peak its position falls under, check
+    /// that peak sits at its place among the committed peaks, and check
+    /// the peaks bag to `root`.
+    ///
+    /// The position and `size` decide which peak that is and how long the
+    /// path must be, so a proof fails if its position is not a leaf of a
+    /// range of that size or if its path does not end exactly at the peak.
+    pub fn verify(&self, root: &Hash32) -> bool {
+        let peaks = peak_positions(self.size);
+        // A range's peaks have strictly decreasing heights; other sizes
+        // never occur.
+        if peaks
+            .windows(2)
+            .any(|pair| height(pair[0]) <= height(pair[1]))
+            || self.position >= self.size
+            || height(self.position) != 0
+            || peaks.len() != self.peaks.len()
+        {
+            return false;
+        }
+        let index = peaks.partition_point(|&peak| peak < self.position);
+        if self.path.len() != height(peaks[index]) as usize {
+            return false;
+        }
+
+        // Every node climbed is at most the peak, so nothing overflows.
+        let mut current = self.leaf_hash;
+        let mut pos = self.position;
+        for (h, sibling) in self.path.iter().enumerate() {
+            if height(pos + 1) > h as u32 {
+                current = hash_pair(sibling, &current);
+                pos += 1;
+            } else {
+                current = hash_pair(&current, sibling);
+                pos += 2 << h;
+            }
+        }
+        pos == peaks[index] && self.peaks[index] == current && bag(&self.peaks) == *root
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn leaf(i: u8) -> Hash32 {
+        MerkleDeltaTree::hash(&[i])
+    }
+
+    #[test]
+    fn test_mmr_seven_leaves() {
+        let mut mmr = MmrAccumulator::new();
+        let mut positions = Vec::new();
+        for i in 0..7u8 {
+            positions.push(mmr.append(leaf(i)));
+            // One peak per set bit of the leaf count.
+            assert_eq!(mmr.peaks().len(), (i as u32 + 1).count_ones() as usize);
+            assert_eq!(mmr.root(), bag(&mmr.peaks()));
+
+            if i == 3 {
+                let left = hash_pair(&leaf(0), &leaf(1));
+                let right = hash_pair(&leaf(2), &leaf(3));
+                assert_eq!(mmr.root(), hash_pair(&left, &right));
+            }
+        }
+        assert_eq!(positions, vec![0, 1, 3, 4, 7, 8, 10]);
+        assert_eq!(mmr.size(), 11);
+
+        let p45 = hash_pair(&leaf(4), &leaf(5));
+        let p0123 = hash_pair(
+            &hash_pair(&leaf(0), &leaf(1)),
+            &hash_pair(&leaf(2), &leaf(3)),
+        );
+        assert_eq!(mmr.peaks(), vec![p0123, p45, leaf(6)]);
+        assert_eq!(mmr.root(), hash_pair(&p0123, &hash_pair(&p45, &leaf(6))));
+
+        let root = mmr.root();
+        let proof = mmr.generate_proof(positions[3]).unwrap();
+        assert_eq!(proof.leaf_hash, leaf(3));
+        assert!(MmrAccumulator::verify_proof(&proof, &root));
+        for &pos in &positions {
+            assert!(mmr.generate_proof(pos).unwrap().verify(&root));
+        }
+
+        let mut forged = proof.clone();
+        forged.leaf_hash = leaf(9);
+        assert!(!forged.verify(&root));
+        assert!(mmr.generate_proof(2).is_err());
+        assert!(mmr.generate_proof(11).is_err());
+
+        // Appending an eighth leaf merges everything into one peak and
+        // invalidates proofs against the old root.
+        mmr.append(leaf(7));
+        assert_eq!(mmr.peaks().len(), 1);
+        assert!(!proof.verify(&mmr.root()));
+        assert!(mmr
+            .generate_proof(positions[3])
+            .unwrap()
+            .verify(&mmr.root()));
+    }
+
+    #[test]
+    fn test_mmr_proof_position_is_bound() {
+        let mut mmr = MmrAccumulator::new();
+        for i in 0..7u8 {
+            mmr.append(leaf(i));
+        }
+        let root = mmr.root();
+
+        // Leaf 4 (position 7) sits under the height-1 peak; moving its
+        // proof to position 0 asks for the height-2 peak's longer path.
+        let mut moved = mmr.generate_proof(7).unwrap();
+        moved.position = 0;
+        assert!(!moved.verify(&root));
+        for position in [1, 2, 3, 4, 8, 9, 10] {
+            moved.position = position;
+            assert!(!moved.verify(&root), "position {}", position);
+        }
+
+        // A peak passed off as a leaf with an empty path.
+        let peaks = mmr.peaks();
+        for (i, &position) in peak_positions(mmr.size()).iter().enumerate() {
+            let forged = MmrProof {
+                position,
+                size: mmr.size(),
+                leaf_hash: peaks[i],
+                path: vec![],
+                peaks: peaks.clone(),
+            };
+            assert_eq!(forged.verify(&root), position == 10, "peak {}", position);
+        }
+
+        // A size no range has, with the same peak count.
+        let mut resized = mmr.generate_proof(10).unwrap();
+        resized.size = 12;
+        assert!(!resized.verify(&root));
+    }
+
+    #[test]
+    fn test_mmr_proof_extremes_do_not_panic() {
+        let mut mmr = MmrAccumulator::new();
+        for i in 0..5u8 {
+            mmr.append(leaf(i));
+        }
+        let root = mmr.root();
+        let proof = mmr.generate_proof(0).unwrap();
+
+        let mut long = proof.clone();
+        long.path = vec![Hash32::ZERO; 100];
+        assert!(!long.verify(&root));
+
+        for (position, size) in [
+            (u64::MAX, u64::MAX),
+            (u64::MAX - 1, u64::MAX),
+            (u64::MAX, proof.size),
+            (0, u64::MAX),
+            (0, 0),
+        ] {
+            let mut extreme = long.clone();
+            extreme.position = position;
+            extreme.size = size;
+            assert!(!extreme.verify(&root));
+            extreme.path.truncate(63);
+            assert!(!extreme.verify(&root));
+        }
+    }
+}
@@ -0,0 +1,50 @@
+//! Tree construction across rayon's thread pool.
+
+use cantor_core::{Hash32, TreeHasher};
+
+use crate::{map_range, MerkleDeltaTree, PaddingConvention};
+
+impl MerkleDeltaTree {
+    /// [`Self::build`] with the leaves, the padding and each level hashed
+    /// across rayon's thread pool. The tree, and so every root and proof,
+    /// is identical to the serial build's; only large trees gain, as
+    /// hashing a small level costs less than handing it out to threads.
+    pub fn build_parallel(deltas: &[&[u8]]) -> Self {
+        let leaves: Vec<Hash32> = map_range(deltas.len(), true, |i| Self::hash(deltas[i]));
+        Self::assemble(
+            leaves,
+            PaddingConvention::default(),
+            2,
+            TreeHasher::default(),
+            true,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parallel_build_matches_serial() {
+        let deltas: Vec<Vec<u8>> = (0..3000u32).map(|i| i.to_le_bytes().to_vec()).collect();
+        let refs: Vec<&[u8]> = deltas.iter().map(|d| d.as_slice()).collect();
+        for n in [0, 1, 2, 5, 1024, 3000] {
+            let serial = MerkleDeltaTree::build(&refs[..n]);
+            let parallel = MerkleDeltaTree::build_parallel(&refs[..n]);
+            assert_eq!(parallel.root(), serial.root(), "{} deltas", n);
+            assert_eq!(parallel.leaves, serial.leaves);
+            assert_eq!(parallel.tree, serial.tree);
+            for index in [0, n / 2, n.saturating_sub(1)]
+                .into_iter()
+                .filter(|&i| i < n)
+            {
+                let proof = parallel.generate_proof(index).unwrap();
+                let expected = serial.generate_proof(index).unwrap();
+                assert_eq!(proof.path, expected.path);
+                assert_eq!(proof.indices, expected.indices);
+                assert_eq!(proof.leaf_hash, expected.leaf_hash);
+            }
+        }
+    }
+}
@@ -0,0 +1,245 @@
+//! Wire format for [`MerkleDeltaTree`], so a built tree can be cached and
+//! reloaded without rehashing its deltas.
+//!
+//! The tree goes through the CANTOR wire format of
+//! [`cantor_core::serialization`] as its leaf count, every level, the
+//! root, the arity, the hasher and the padding convention; the leaves are
+//! the start of the bottom level. Loading checks the levels against each
+//! other before handing the tree out, since a corrupted cache would
+//! otherwise produce proofs for a root nobody committed to.
+//!
+//! The encoding is about 32 bytes per node, so it grows past
+//! [`MAX_SERIALIZED_LEN`](cantor_core::serialization::MAX_SERIALIZED_LEN)
+//! around 256Ki leaves. Trees set their own size limit instead: encoding
+//! allows a bound computed from the node count, and decoding allows as
+//! many bytes as it is given, which a claimed length cannot exceed.
+
+use std::borrow::Cow;
+
+use cantor_core::serialization::{from_bytes_with_limit, to_bytes_with_limit};
+use cantor_core::{CantorError, Hash32, Result, TreeHasher};
+use serde::{Deserialize, Serialize};
+
+use crate::{MerkleDeltaTree, PaddingConvention};
+
+#[derive(Serialize, Deserialize)]
+struct TreeWire<'a> {
+    leaf_count: u64,
+    tree: Cow<'a, [Vec<Hash32>]>,
+    root: Hash32,
+    arity: usize,
+    hasher: TreeHasher,
+    padding: PaddingConvention,
+}
+
+fn corrupt(detail: impl Into<String>) -> CantorError {
+    CantorError::Serialization(format!("corrupt tree: {}", detail.into()))
+}
+
+impl MerkleDeltaTree {
+    /// Encode the tree for [`Self::from_bytes`].
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        // 32 bytes a node, 8 a level for its length, and the fixed fields.
+        let nodes: usize = self.tree.iter().map(Vec::len).sum();
+        let limit = 32 * nodes as u64 + 8 * self.tree.len() as u64 + 1024;
+        to_bytes_with_limit(
+            &TreeWire {
+                leaf_count: self.leaves.len() as u64,
+                tree: Cow::Borrowed(&self.tree),
+                root: self.root,
+                arity: self.arity,
+                hasher: self.hasher,
+                padding: self.padding,
+            },
+            limit,
+        )
+    }
+
+    /// Decode a tree produced by [`Self::to_bytes`].
+    ///
+    /// Every internal node is rehashed from its children, so this costs
+    /// the node hashes of a rebuild but none of the leaf hashes. Fails
+    /// with [`CantorError::Serialization`] if the bytes do not decode, or
+    /// if the levels do not shrink by the arity down to a single root,
+    /// the leaf count exceeds the bottom level, the padding is
+    /// not what the recorded [`PaddingConvention`] puts there, or any node
+    /// or the root differs from its recomputation.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let wire: TreeWire = from_bytes_with_limit(bytes, bytes.len() as u64)?;
+        let tree = wire.tree.into_owned();
+        let leaves = match tree.first() {
+            None if wire.leaf_count == 0 => Vec::new(),
+            None => return Err(corrupt("no levels")),
+            Some(bottom) => usize::try_from(wire.leaf_count)
+                .ok()
+                .and_then(|count| bottom.get(..count))
+                .ok_or_else(|| corrupt("more leaves than the bottom level holds"))?
+                .to_vec(),
+        };
+        let tree = Self {
+            leaves,
+            tree,
+            root: wire.root,
+            arity: wire.arity,
+            hasher: wire.hasher,
+            padding: wire.padding,
+        };
+        tree.validate()?;
+        Ok(tree)
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.arity < 2 {
+            return Err(corrupt(format!("arity {}", self.arity)));
+        }
+        if self.leaves.is_empty() {
+            if !self.tree.is_empty() || self.root != Self::empty_root(self.hasher) {
+                return Err(corrupt("empty tree with nodes or a non-empty root"));
+            }
+            return Ok(());
+        }
+
+        let bottom = self.tree.first().ok_or_else(|| corrupt("no levels"))?;
+        let (real, padding) = bottom
+            .split_at_checked(self.leaves.len())
+            .ok_or_else(|| corrupt("more leaves than the bottom level holds"))?;
+        if real != self.leaves.as_slice() {
+            return Err(corrupt("leaves differ from the bottom level"));
+        }
+        let first = self.leaves.len();
+        if let Some(i) =
+            (0..padding.len()).find(|&i| padding[i] != self.expected_padding(first + i))
+        {
+            return Err(corrupt(format!("padding slot {}", first + i)));
+        }
+
+        for (height, pair) in self.tree.windows(2).enumerate() {
+            let (below, level) = (&pair[0], &pair[1]);
+            if below.len() <= 1
+                || !below.len().is_multiple_of(self.arity)
+                || level.len() != below.len() / self.arity
+            {
+                return Err(corrupt(format!(
+                    "level {} has {} nodes over {}",
+                    height + 1,
+                    level.len(),
+                    below.len()
+                )));
+            }
+            for (index, (node, children)) in level.iter().zip(below.chunks(self.arity)).enumerate()
+            {
+                if *node != self.hasher.hash_node(children) {
+                    return Err(corrupt(format!("node {} of level {}", index, height + 1)));
+                }
+            }
+        }
+
+        match self.tree.last().map(Vec::as_slice) {
+            Some([root]) if *root == self.root => Ok(()),
+            Some([_]) => Err(corrupt("root differs from the top level")),
+            _ => Err(corrupt("top level is not a single node")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cantor_core::{HashAlgorithm, TreeVersion};
+
+    fn deltas(n: usize) -> Vec<Vec<u8>> {
+        (0..n).map(|i| format!("delta{}", i).into_bytes()).collect()
+    }
+
+    #[test]
+    fn test_tree_bytes_roundtrip() {
+        let data = deltas(5);
+        let refs: Vec<&[u8]> = data.iter().map(|d| d.as_slice()).collect();
+        let trees = [
+            MerkleDeltaTree::build(&refs),
+            MerkleDeltaTree::build(&[]),
+            MerkleDeltaTree::build(&refs[..1]),
+            MerkleDeltaTree::build_kary(&refs, 4),
+            MerkleDeltaTree::build_with_padding(&refs, PaddingConvention::DuplicateLast),
+            MerkleDeltaTree::build_with_padding(&refs, PaddingConvention::ZeroHash),
+            MerkleDeltaTree::build_with_padding(&refs, PaddingConvention::Labeled),
+            MerkleDeltaTree::build_with_hasher(
+                &refs,
+                TreeHasher::new(HashAlgorithm::Blake3, TreeVersion::V2),
+            ),
+        ];
+        for tree in trees {
+            let loaded = MerkleDeltaTree::from_bytes(&tree.to_bytes().unwrap()).unwrap();
+            assert_eq!(loaded.root(), tree.root());
+            assert_eq!(loaded.arity(), tree.arity());
+            assert_eq!(loaded.hasher(), tree.hasher());
+            assert_eq!(loaded.leaves, tree.leaves);
+            assert_eq!(loaded.tree, tree.tree);
+            for index in 0..tree.leaves.len() {
+                let proof = loaded.generate_proof(index).unwrap();
+                assert!(proof.verify_with(&tree.root(), tree.hasher()));
+            }
+        }
+    }
+
+    #[test]
+    fn test_large_tree_bytes_roundtrip() {
+        // Past the default 16 MiB limit once padded to 2^18 slots.
+        let leaves: Vec<Hash32> = (0..200_000u32)
+            .map(|i| {
+                let mut leaf = [0u8; 32];
+                leaf[..4].copy_from_slice(&i.to_le_bytes());
+                Hash32(leaf)
+            })
+            .collect();
+        // BLAKE3 keeps the hashing quick in unoptimized test builds.
+        let tree = MerkleDeltaTree::from_leaf_hashes(
+            leaves,
+            PaddingConvention::default(),
+            2,
+            TreeHasher::new(HashAlgorithm::Blake3, TreeVersion::V2),
+        );
+        let bytes = tree.to_bytes().unwrap();
+        assert!(bytes.len() as u64 > cantor_core::serialization::MAX_SERIALIZED_LEN);
+        // The leaves are not stored apart from the bottom level.
+        let nodes: usize = tree.tree.iter().map(Vec::len).sum();
+        assert!(bytes.len() < 32 * nodes + 1024, "{} bytes", bytes.len());
+
+        let loaded = MerkleDeltaTree::from_bytes(&bytes).unwrap();
+        assert_eq!(loaded.root(), tree.root());
+        assert_eq!(loaded.leaves, tree.leaves);
+        assert!(loaded
+            .generate_proof(199_999)
+            .unwrap()
+            .verify_with(&tree.root(), tree.hasher()));
+    }
+
+    #[test]
+    fn test_corrupt_tree_bytes_rejected() {
+        let data = deltas(5);
+        let refs: Vec<&[u8]> = data.iter().map(|d| d.as_slice()).collect();
+        let bytes = MerkleDeltaTree::build(&refs).to_bytes().unwrap();
+
+        for i in 0..bytes.len() {
+            let mut flipped = bytes.clone();
+            flipped[i] ^= 0x01;
+            assert!(
+                matches!(
+                    MerkleDeltaTree::from_bytes(&flipped),
+                    Err(CantorError::Serialization(_))
+                ),
+                "flip at byte {} accepted",
+                i
+            );
+        }
+        assert!(MerkleDeltaTree::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+
+        // Well-formed bytes for a tree whose root was swapped out.
+        let mut tree = MerkleDeltaTree::build(&refs);
+        tree.root = Hash32::ZERO;
+        let Err(err) = MerkleDeltaTree::from_bytes(&tree.to_bytes().unwrap()) else {
+            panic!("swapped root accepted");
+        };
+        assert!(err.to_string().contains("root"), "{}", err);
+    }
+}
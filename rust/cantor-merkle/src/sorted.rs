@@ -0,0 +1,156 @@
+//! Sorted-leaf Merkle tree with non-membership proofs.
+//!
+//! Leaves are ordered by hash and bracketed by a minimum (`0x00..00`) and a
+//! maximum (`0xff..ff`) sentinel. A value is proven absent by revealing the
+//! two adjacent leaves whose hashes bracket it, together with their
+//! inclusion proofs: since the leaves are sorted and adjacent, no leaf can
+//! hash to anything in between.
+
+use cantor_core::{CantorError, Hash32, MerkleProof, Result, TreeHasher};
+
+use crate::{MerkleDeltaTree, PaddingConvention};
+
+const MIN_SENTINEL: Hash32 = Hash32([0x00; 32]);
+const MAX_SENTINEL: Hash32 = Hash32([0xff; 32]);
+
+/// Merkle tree whose leaves are sorted delta hashes.
+pub struct SortedMerkleTree {
+    sorted: Vec<Hash32>,
+    tree: MerkleDeltaTree,
+}
+
+/// Proof that a hash is not among a sorted tree's leaves.
+#[derive(Clone, Debug)]
+pub struct ExclusionProof {
+    pub lower: MerkleProof,
+    pub upper: MerkleProof,
+}
+
+impl SortedMerkleTree {
+    pub fn build(deltas: &[&[u8]]) -> Self {
+        let mut sorted: Vec<Hash32> = deltas.iter().map(|d| MerkleDeltaTree::hash(d)).collect();
+        sorted.push(MIN_SENTINEL);
+        sorted.push(MAX_SENTINEL);
+        sorted.sort_unstable();
+        sorted.dedup();
+
+        let tree = MerkleDeltaTree::from_leaf_hashes(
+            sorted.clone(),
+            PaddingConvention::default(),
+            2,
+            TreeHasher::default(),
+        );
+        Self { sorted, tree }
+    }
+
+    pub fn root(&self) -> Hash32 {
+        self.tree.root()
+    }
+
+    /// Inclusion proof for a delta that is in the tree.
+    pub fn prove_present(&self, delta: &[u8]) -> Result<MerkleProof> {
+        let target = MerkleDeltaTree::hash(delta);
+        match self.sorted.binary_search(&target) {
+            Ok(index) => self.tree.generate_proof(index),
+            Err(_) => Err(CantorError::TransactionNotFound(target.to_string())),
+        }
+    }
+
+    /// Exclusion proof for a delta that is not in the tree.
+    pub fn prove_absent(&self, delta: &[u8]) -> Result<ExclusionProof> {
+        let target = MerkleDeltaTree::hash(delta);
+        match self.sorted.binary_search(&target) {
+            Ok(_) => Err(CantorError::MerkleVerificationFailed),
+            // The sentinels guarantee 0 < upper < len for any absent hash.
+            Err(upper) => Ok(ExclusionProof {
+                lower: self.tree.generate_proof(upper - 1)?,
+                upper: self.tree.generate_proof(upper)?,
+            }),
+        }
+    }
+}
+
+impl ExclusionProof {
+    /// Check that `delta` hashes strictly between two adjacent leaves
+    /// committed under `root`.
+    ///
+    /// Each proof must have one position per level of siblings, so that
+    /// the positions compared for adjacency are the ones its root uses.
+    pub fn verify(&self, delta: &[u8], root: &Hash32) -> bool {
+        // `compute_root` stops at the shorter of path and positions.
+        let well_formed = |p: &MerkleProof| p.indices.len() * (p.arity() - 1) == p.path.len();
+        let target = MerkleDeltaTree::hash(delta);
+        let adjacent = match (self.lower.leaf_index(), self.upper.leaf_index()) {
+            (Some(lower), Some(upper)) => lower.checked_add(1) == Some(upper),
+            _ => false,
+        };
+        self.lower.leaf_hash < target
+            && target < self.upper.leaf_hash
+            && self.lower.path.len() == self.upper.path.len()
+            && well_formed(&self.lower)
+            && well_formed(&self.upper)
+            && adjacent
+            && self.lower.verify(root)
+            && self.upper.verify(root)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exclusion_proof() {
+        let deltas: Vec<&[u8]> = vec![b"delta1", b"delta2", b"delta3", b"delta4", b"delta5"];
+        let tree = SortedMerkleTree::build(&deltas);
+
+        let proof = tree.prove_absent(b"missing").unwrap();
+        assert!(proof.verify(b"missing", &tree.root()));
+
+        let other = SortedMerkleTree::build(&deltas[..3]);
+        assert!(!proof.verify(b"missing", &other.root()));
+
+        for delta in &deltas {
+            assert!(tree.prove_present(delta).unwrap().verify(&tree.root()));
+        }
+    }
+
+    #[test]
+    fn test_exclusion_rejects_present_value() {
+        let deltas: Vec<&[u8]> = vec![b"delta1", b"delta2", b"delta3", b"delta4", b"delta5"];
+        let tree = SortedMerkleTree::build(&deltas);
+
+        assert!(tree.prove_absent(b"delta3").is_err());
+
+        // Neighbors of the present leaf are not adjacent to each other.
+        let index = tree
+            .sorted
+            .binary_search(&MerkleDeltaTree::hash(b"delta3"))
+            .unwrap();
+        let forged = ExclusionProof {
+            lower: tree.tree.generate_proof(index - 1).unwrap(),
+            upper: tree.tree.generate_proof(index + 1).unwrap(),
+        };
+        assert!(!forged.verify(b"delta3", &tree.root()));
+
+        // Bracketing with the leaf itself fails the strict ordering.
+        let forged = ExclusionProof {
+            lower: tree.tree.generate_proof(index - 1).unwrap(),
+            upper: tree.tree.generate_proof(index).unwrap(),
+        };
+        assert!(!forged.verify(b"delta3", &tree.root()));
+    }
+
+    #[test]
+    fn test_exclusion_rejects_padded_indices() {
+        let deltas: Vec<&[u8]> = vec![b"delta1", b"delta2", b"delta3", b"delta4", b"delta5"];
+        let tree = SortedMerkleTree::build(&deltas);
+        let mut proof = tree.prove_absent(b"missing").unwrap();
+        // Positions past the path leave the root unchanged but would
+        // overflow the leaf index.
+        proof.lower.indices.extend([1; 80]);
+        assert!(proof.lower.verify(&tree.root()));
+        assert_eq!(proof.lower.leaf_index(), None);
+        assert!(!proof.verify(b"missing", &tree.root()));
+    }
+}
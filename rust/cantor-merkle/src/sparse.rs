@@ -0,0 +1,324 @@
+//! Sparse Merkle tree over the full 256-bit key space.
+//!
+//! Every `Hash32` key has a fixed leaf: reading the key's bits from the
+//! most significant down picks the path from the root, one bit per level.
+//! A key with a value hashes as `H(key || value)`; every other leaf is
+//! empty and hashes as the zero leaf of [`IncrementalMerkleTree`], with
+//! empty subtrees above it taking the same per-level zero hashes. Only
+//! nodes with a non-empty leaf below them are stored.
+//!
+//! Since every key has a leaf, absence is proven the same way as
+//! presence: by the path from the key's leaf to the root, with the leaf
+//! empty.
+
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use cantor_core::Hash32;
+use serde::{Deserialize, Serialize};
+
+use crate::IncrementalMerkleTree;
+
+/// Levels between a leaf and the root, one per key bit.
+pub const SPARSE_DEPTH: usize = 256;
+
+/// Zero hash of an empty subtree at each height, leaves at 0 and the root
+/// of an empty tree at [`SPARSE_DEPTH`].
+fn zeros() -> &'static [Hash32] {
+    static ZEROS: OnceLock<Vec<Hash32>> = OnceLock::new();
+    ZEROS.get_or_init(|| IncrementalMerkleTree::compute_zeros(SPARSE_DEPTH + 1))
+}
+
+fn leaf_hash(key: &Hash32, value: &Hash32) -> Hash32 {
+    IncrementalMerkleTree::hash_pair(key, value)
+}
+
+/// Whether the node at `height` on `key`'s path is a right child.
+fn is_right(key: &Hash32, height: usize) -> bool {
+    key.0[31 - height / 8] >> (height % 8) & 1 == 1
+}
+
+/// `key` with its bits below `height` cleared: the id of the node at
+/// `height` on its path.
+fn node_id(key: &Hash32, height: usize) -> Hash32 {
+    let mut id = *key;
+    let (whole, part) = (height / 8, height % 8);
+    id.0[32 - whole.min(32)..].fill(0);
+    if whole < 32 {
+        id.0[31 - whole] &= 0xff << part;
+    }
+    id
+}
+
+/// Id of the sibling of the node `id` at `height`.
+fn sibling_id(id: &Hash32, height: usize) -> Hash32 {
+    let mut sibling = *id;
+    sibling.0[31 - height / 8] ^= 1 << (height % 8);
+    sibling
+}
+
+/// Merkle tree with a leaf for every `Hash32` key, most of them empty.
+///
+/// Unlike [`MerkleDeltaTree`](crate::MerkleDeltaTree), which commits to a
+/// dense list of deltas, this commits to a key-value map: a proof shows
+/// the value a key holds, or that it holds none. Hashed with SHA-256.
+/// Each key stores the [`SPARSE_DEPTH`] nodes on its path.
+///
+/// Keys are chosen by the caller, not drawn from a hash, and node ids
+/// share their leading bytes with every key below them, so the maps use
+/// the standard library's randomly keyed SipHash rather than
+/// [`Hash32Map`](cantor_core::Hash32Map)'s prefix hasher.
+#[derive(Clone, Debug)]
+pub struct SparseMerkleTree {
+    values: HashMap<Hash32, Hash32>,
+    /// Non-empty nodes per height below the root, by node id.
+    nodes: Vec<HashMap<Hash32, Hash32>>,
+    root: Hash32,
+}
+
+/// Proof of the value a key holds in a [`SparseMerkleTree`], or that it
+/// holds none.
+///
+/// Siblings that are empty subtrees are left out of `siblings` and
+/// marked by a clear bit in `present`, so a proof's size grows with the
+/// number of keys sharing its path rather than with the depth.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SparseProof {
+    pub key: Hash32,
+    /// The key's value, or `None` for a proof of absence.
+    pub value: Option<Hash32>,
+    /// Bit `h` (little-endian, from byte 0) is set when the sibling at
+    /// height `h` is non-empty and appears in `siblings`.
+    pub present: [u8; 32],
+    /// Non-empty siblings, leaf to root.
+    pub siblings: Vec<Hash32>,
+}
+
+impl SparseMerkleTree {
+    pub fn new() -> Self {
+        Self {
+            values: HashMap::new(),
+            nodes: vec![HashMap::new(); SPARSE_DEPTH],
+            root: zeros()[SPARSE_DEPTH],
+        }
+    }
+
+    /// Set `key` to `value`, overwriting any previous value, and return
+    /// the new root.
+    pub fn insert(&mut self, key: Hash32, value: Hash32) -> Hash32 {
+        self.values.insert(key, value);
+        let zeros = zeros();
+        let mut current = leaf_hash(&key, &value);
+        for (height, level) in self.nodes.iter_mut().enumerate() {
+            let id = node_id(&key, height);
+            let sibling = level
+                .get(&sibling_id(&id, height))
+                .copied()
+                .unwrap_or(zeros[height]);
+            level.insert(id, current);
+            current = if is_right(&key, height) {
+                IncrementalMerkleTree::hash_pair(&sibling, &current)
+            } else {
+                IncrementalMerkleTree::hash_pair(&current, &sibling)
+            };
+        }
+        self.root = current;
+        current
+    }
+
+    /// Value of `key`, if it has one.
+    pub fn get(&self, key: &Hash32) -> Option<Hash32> {
+        self.values.get(key).copied()
+    }
+
+    /// Proof of `key`'s value against the current root, or of its absence.
+    pub fn get_proof(&self, key: &Hash32) -> SparseProof {
+        let mut present = [0u8; 32];
+        let mut siblings = Vec::new();
+        for (height, level) in self.nodes.iter().enumerate() {
+            if let Some(sibling) = level.get(&sibling_id(&node_id(key, height), height)) {
+                present[height / 8] |= 1 << (height % 8);
+                siblings.push(*sibling);
+            }
+        }
+        SparseProof {
+            key: *key,
+            value: self.get(key),
+            present,
+            siblings,
+        }
+    }
+
+    pub fn root(&self) -> Hash32 {
+        self.root
+    }
+
+    /// Number of keys with a value.
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+}
+
+impl Default for SparseMerkleTree {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SparseProof {
+    /// Whether the proof shows the key holding a value.
+    pub fn is_membership(&self) -> bool {
+        self.value.is_some()
+    }
+
+    /// Fold the key's leaf up the path and compare the result with
+    /// `root`. A proof of absence verifies only if the key has no value
+    /// under `root`.
+    pub fn verify(&self, root: &Hash32) -> bool {
+        self.compute_root() == Some(*root)
+    }
+
+    /// Root implied by the proof, or `None` if `present` and `siblings`
+    /// disagree on the number of non-empty siblings.
+    pub fn compute_root(&self) -> Option<Hash32> {
+        let zeros = zeros();
+        let mut siblings = self.siblings.iter();
+        let mut current = match &self.value {
+            Some(value) => leaf_hash(&self.key, value),
+            None => zeros[0],
+        };
+        for (height, zero) in zeros[..SPARSE_DEPTH].iter().enumerate() {
+            let sibling = if self.present[height / 8] >> (height % 8) & 1 == 1 {
+                siblings.next()?
+            } else {
+                zero
+            };
+            current = if is_right(&self.key, height) {
+                IncrementalMerkleTree::hash_pair(sibling, &current)
+            } else {
+                IncrementalMerkleTree::hash_pair(&current, sibling)
+            };
+        }
+        siblings.next().is_none().then_some(current)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MerkleDeltaTree;
+
+    fn key(i: u32) -> Hash32 {
+        MerkleDeltaTree::hash(&i.to_le_bytes())
+    }
+
+    #[test]
+    fn test_sparse_membership() {
+        let mut tree = SparseMerkleTree::new();
+        let empty = tree.root();
+        assert_eq!(empty, zeros()[SPARSE_DEPTH]);
+
+        for i in 0..50 {
+            assert_eq!(tree.insert(key(i), key(i + 1000)), tree.root());
+        }
+        assert_ne!(tree.root(), empty);
+        assert_eq!(tree.len(), 50);
+
+        for i in 0..50 {
+            let proof = tree.get_proof(&key(i));
+            assert_eq!(proof.value, Some(key(i + 1000)));
+            assert!(proof.is_membership());
+            assert!(proof.verify(&tree.root()));
+            assert!(!proof.verify(&empty));
+            // Most siblings of a 50-key tree are empty subtrees.
+            assert!(proof.siblings.len() < 20, "{}", proof.siblings.len());
+
+            let mut forged = proof.clone();
+            forged.value = Some(key(i + 1001));
+            assert!(!forged.verify(&tree.root()));
+            forged.value = None;
+            assert!(!forged.verify(&tree.root()));
+        }
+
+        // Keys sharing all but their last bit are siblings of each other.
+        let (mut left, mut right) = (Hash32([0xab; 32]), Hash32([0xab; 32]));
+        left.0[31] = 0x10;
+        right.0[31] = 0x11;
+        tree.insert(left, key(1));
+        tree.insert(right, key(2));
+        let proof = tree.get_proof(&right);
+        assert_eq!(proof.present[0] & 1, 1);
+        assert_eq!(proof.siblings[0], leaf_hash(&left, &key(1)));
+        assert!(proof.verify(&tree.root()));
+    }
+
+    #[test]
+    fn test_sparse_absence() {
+        let mut tree = SparseMerkleTree::new();
+        let proof = tree.get_proof(&key(7));
+        assert!(!proof.is_membership());
+        assert!(proof.siblings.is_empty());
+        assert!(proof.verify(&tree.root()));
+
+        for i in 0..50 {
+            tree.insert(key(i), key(i));
+        }
+        for i in 50..100 {
+            let proof = tree.get_proof(&key(i));
+            assert_eq!(tree.get(&key(i)), None);
+            assert!(proof.verify(&tree.root()));
+
+            // Claiming a value for an absent key fails.
+            let mut forged = proof.clone();
+            forged.value = Some(key(i));
+            assert!(!forged.verify(&tree.root()));
+        }
+
+        // An absence proof goes stale once the key is set.
+        let absent = tree.get_proof(&key(50));
+        tree.insert(key(50), key(0));
+        assert!(!absent.verify(&tree.root()));
+
+        let mut truncated = tree.get_proof(&key(3));
+        truncated.siblings.pop();
+        assert_eq!(truncated.compute_root(), None);
+        let mut padded = tree.get_proof(&key(3));
+        padded.siblings.push(Hash32::ZERO);
+        assert_eq!(padded.compute_root(), None);
+    }
+
+    #[test]
+    fn test_sparse_overwrite() {
+        let mut tree = SparseMerkleTree::new();
+        for i in 0..10 {
+            tree.insert(key(i), key(i));
+        }
+        let before = tree.root();
+        let old = tree.get_proof(&key(4));
+
+        let after = tree.insert(key(4), key(99));
+        assert_ne!(after, before);
+        assert_eq!(tree.len(), 10);
+        assert_eq!(tree.get(&key(4)), Some(key(99)));
+        assert!(!old.verify(&after));
+        let proof = tree.get_proof(&key(4));
+        assert_eq!(proof.value, Some(key(99)));
+        assert!(proof.verify(&after));
+        assert!(tree.get_proof(&key(5)).verify(&after));
+
+        // Writing the old value back restores the old root.
+        assert_eq!(tree.insert(key(4), key(4)), before);
+        assert!(old.verify(&tree.root()));
+
+        // The root depends only on the final contents, not the order.
+        let mut reversed = SparseMerkleTree::new();
+        for i in (0..10).rev() {
+            reversed.insert(key(i), key(i));
+        }
+        assert_eq!(reversed.root(), before);
+    }
+}
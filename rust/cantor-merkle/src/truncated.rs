@@ -0,0 +1,215 @@
+//! Merkle tree over truncated hashes.
+//!
+//! Halves proof size when built with [`Hash16`](cantor_core::Hash16), at the
+//! cost of reduced collision resistance; see [`cantor_core::truncated`].
+//! [`TruncatedPathTree`] keeps full leaves and root and truncates only the
+//! proof path.
+
+use cantor_core::truncated::{Hash16, HashN, MerkleProofN, TruncatedProof};
+use cantor_core::{CantorError, Hash32, Result, TreeHasher};
+use sha2::{Digest, Sha256};
+
+use crate::{build_levels, padding_label, MerkleDeltaTree};
+
+/// Merkle tree whose nodes are `N`-byte truncated SHA-256 hashes.
+///
+/// Padding slot `i` holds the truncated hash of the same preimage
+/// [`PaddingConvention::Indexed`](crate::PaddingConvention::Indexed) uses.
+pub struct TruncatedMerkleTree<const N: usize> {
+    leaf_count: usize,
+    tree: Vec<Vec<HashN<N>>>,
+    root: HashN<N>,
+}
+
+impl<const N: usize> TruncatedMerkleTree<N> {
+    /// Build a tree from delta data.
+    pub fn build(deltas: &[&[u8]]) -> Self {
+        if deltas.is_empty() {
+            return Self {
+                leaf_count: 0,
+                tree: vec![],
+                root: HashN::digest(b"empty"),
+            };
+        }
+
+        let mut padded: Vec<HashN<N>> = deltas.iter().map(|d| HashN::digest(d)).collect();
+        padded.extend(
+            (deltas.len()..deltas.len().next_power_of_two())
+                .map(|i| HashN::digest(&padding_label(i))),
+        );
+
+        let tree = build_levels(padded, 2, false, |pair| HashN::hash_pair(&pair[0], &pair[1]));
+        let root = tree.last().unwrap()[0];
+        Self {
+            leaf_count: deltas.len(),
+            tree,
+            root,
+        }
+    }
+
+    pub fn root(&self) -> HashN<N> {
+        self.root
+    }
+
+    /// Generate a proof for a specific leaf index.
+    pub fn generate_proof(&self, index: usize) -> Result<MerkleProofN<N>> {
+        if index >= self.leaf_count {
+            return Err(CantorError::TransactionNotFound(index.to_string()));
+        }
+
+        let mut path = Vec::new();
+        let mut indices = Vec::new();
+        let mut current_index = index;
+
+        for level in &self.tree[..self.tree.len() - 1] {
+            path.push(level[current_index ^ 1]);
+            indices.push((current_index % 2) as u8);
+            current_index /= 2;
+        }
+
+        Ok(MerkleProofN {
+            leaf_hash: self.tree[0][index],
+            path,
+            indices,
+        })
+    }
+
+    pub fn verify_proof(proof: &MerkleProofN<N>, root: &HashN<N>) -> bool {
+        proof.verify(root)
+    }
+}
+
+/// Binary Merkle tree whose proofs carry 16-byte sibling prefixes.
+///
+/// Leaves are the full SHA-256 of each delta, and each parent the full
+/// SHA-256 of its children's first 16 bytes, so the 32-byte root can be
+/// checked from a [`TruncatedProof`]. Roots differ from
+/// [`MerkleDeltaTree`](crate::MerkleDeltaTree)'s for the same deltas. See
+/// [`TruncatedProof`] for the collision margin this gives up.
+pub struct TruncatedPathTree {
+    leaf_count: usize,
+    tree: Vec<Vec<Hash32>>,
+    root: Hash32,
+}
+
+impl TruncatedPathTree {
+    /// Build a tree from delta data. The leaf level, padding included,
+    /// is the bottom level of `MerkleDeltaTree::build` over the same deltas.
+    pub fn build(deltas: &[&[u8]]) -> Self {
+        let hash = |data: &[u8]| Hash32(Sha256::digest(data).into());
+        if deltas.is_empty() {
+            return Self {
+                leaf_count: 0,
+                tree: vec![],
+                root: hash(b"empty"),
+            };
+        }
+
+        let mut padded: Vec<Hash32> = deltas.iter().map(|d| hash(d)).collect();
+        padded.extend(
+            (deltas.len()..deltas.len().next_power_of_two())
+                .map(|i| MerkleDeltaTree::padding_leaf(TreeHasher::default(), i)),
+        );
+
+        let tree = build_levels(padded, 2, false, |pair| {
+            TruncatedProof::hash_node(&Hash16::prefix_of(&pair[0]), &Hash16::prefix_of(&pair[1]))
+        });
+        let root = tree.last().unwrap()[0];
+        Self {
+            leaf_count: deltas.len(),
+            tree,
+            root,
+        }
+    }
+
+    pub fn root(&self) -> Hash32 {
+        self.root
+    }
+
+    /// Generate a proof for a specific leaf index.
+    pub fn generate_proof(&self, index: usize) -> Result<TruncatedProof> {
+        if index >= self.leaf_count {
+            return Err(CantorError::TransactionNotFound(index.to_string()));
+        }
+
+        let mut path = Vec::new();
+        let mut indices = Vec::new();
+        let mut current_index = index;
+
+        for level in &self.tree[..self.tree.len() - 1] {
+            path.push(Hash16::prefix_of(&level[current_index ^ 1]));
+            indices.push((current_index % 2) as u8);
+            current_index /= 2;
+        }
+
+        Ok(TruncatedProof {
+            leaf_hash: self.tree[0][index],
+            path,
+            indices,
+        })
+    }
+
+    pub fn verify_proof(proof: &TruncatedProof, root: &Hash32) -> bool {
+        proof.verify(root)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hash16_tree() {
+        let deltas: Vec<&[u8]> = vec![b"delta1", b"delta2", b"delta3", b"delta4", b"delta5"];
+        let tree = TruncatedMerkleTree::<16>::build(&deltas);
+
+        for (i, delta) in deltas.iter().enumerate() {
+            let proof = tree.generate_proof(i).unwrap();
+            assert_eq!(proof.leaf_hash, Hash16::digest(delta));
+            assert!(TruncatedMerkleTree::verify_proof(&proof, &tree.root()));
+        }
+
+        let mut tampered = tree.generate_proof(1).unwrap();
+        tampered.leaf_hash = Hash16::digest(b"forged");
+        assert!(!tampered.verify(&tree.root()));
+        assert!(tree.generate_proof(5).is_err());
+        // Padding slots differ by position.
+        assert_ne!(tree.tree[0][5], tree.tree[0][6]);
+        assert_eq!(tree.tree[0][5], Hash16::digest(b"padding\x05\0\0\0\0\0\0\0"));
+    }
+
+    #[test]
+    fn test_truncated_path_proofs() {
+        let deltas: Vec<&[u8]> = vec![b"delta1", b"delta2", b"delta3", b"delta4", b"delta5"];
+        let tree = TruncatedPathTree::build(&deltas);
+        let full = crate::MerkleDeltaTree::build(&deltas);
+        assert_eq!(tree.tree[0], full.tree[0]);
+
+        for (i, delta) in deltas.iter().enumerate() {
+            let proof = tree.generate_proof(i).unwrap();
+            assert_eq!(proof.leaf_hash.as_bytes()[..], Sha256::digest(delta)[..]);
+            assert!(TruncatedPathTree::verify_proof(&proof, &tree.root()));
+            let full_proof = full.generate_proof(i).unwrap();
+            assert_eq!(proof.path.len(), full_proof.path.len());
+            assert_eq!(proof.path.len() * 16 * 2, full_proof.path.len() * 32);
+        }
+
+        let mut tampered = tree.generate_proof(1).unwrap();
+        tampered.leaf_hash = Hash32(Sha256::digest(b"forged").into());
+        assert!(!tampered.verify(&tree.root()));
+        let mut tampered = tree.generate_proof(1).unwrap();
+        tampered.path[1].0[15] ^= 1;
+        assert!(!tampered.verify(&tree.root()));
+        assert!(tree.generate_proof(5).is_err());
+
+        // The documented tradeoff: a sibling whose full hash differs but
+        // shares the stored prefix yields the same proof, and is accepted.
+        let sibling = tree.tree[0][0];
+        let mut impostor = sibling;
+        impostor.0[16..].iter_mut().for_each(|b| *b ^= 0xff);
+        assert_ne!(impostor, sibling);
+        let mut proof = tree.generate_proof(1).unwrap();
+        proof.path[0] = Hash16::prefix_of(&impostor);
+        assert!(proof.verify(&tree.root()));
+    }
+}
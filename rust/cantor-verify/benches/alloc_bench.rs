@@ -0,0 +1,150 @@
+//! Allocations per call of the batch and context-reusing verification
+//! APIs, measured by criterion through a counting global allocator. Kept
+//! apart from `verify_bench` so the counting does not slow its timings.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use criterion::measurement::{Measurement, ValueFormatter};
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use cantor_verify::{StateVerifier, VerificationContext};
+
+mod common;
+
+use common::valid_block;
+
+/// System allocator that counts allocations.
+struct CountingAlloc;
+
+static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+/// Criterion measurement in allocations instead of wall time.
+struct Allocations;
+
+impl Measurement for Allocations {
+    type Intermediate = u64;
+    type Value = u64;
+
+    fn start(&self) -> u64 {
+        ALLOCATIONS.load(Ordering::Relaxed)
+    }
+
+    fn end(&self, start: u64) -> u64 {
+        ALLOCATIONS.load(Ordering::Relaxed) - start
+    }
+
+    fn add(&self, v1: &u64, v2: &u64) -> u64 {
+        v1 + v2
+    }
+
+    fn zero(&self) -> u64 {
+        0
+    }
+
+    fn to_f64(&self, value: &u64) -> f64 {
+        *value as f64
+    }
+
+    fn formatter(&self) -> &dyn ValueFormatter {
+        &AllocationFormatter
+    }
+}
+
+struct AllocationFormatter;
+
+impl ValueFormatter for AllocationFormatter {
+    fn scale_values(&self, _typical: f64, _values: &mut [f64]) -> &'static str {
+        "allocs"
+    }
+
+    fn scale_throughputs(
+        &self,
+        _typical: f64,
+        throughput: &Throughput,
+        values: &mut [f64],
+    ) -> &'static str {
+        let (per, unit) = match *throughput {
+            Throughput::Bytes(n) | Throughput::BytesDecimal(n) => (n, "allocs/byte"),
+            Throughput::Elements(n) => (n, "allocs/elem"),
+        };
+        for value in values {
+            *value /= per as f64;
+        }
+        unit
+    }
+
+    fn scale_for_machines(&self, _values: &mut [f64]) -> &'static str {
+        "allocs"
+    }
+}
+
+fn bench_batch_counts(c: &mut Criterion<Allocations>) {
+    let verifier = StateVerifier::new("v1.0.0");
+    let (block, predicted) = valid_block(1000);
+    let counts = verifier.verify_batch_counts(&block, &predicted).unwrap();
+    assert_eq!(counts.first_failure, None, "fixture proofs must all verify");
+
+    let mut group = c.benchmark_group("batch_counts_allocs");
+    group.throughput(Throughput::Elements(block.proofs.len() as u64));
+    group.bench_function("verify_batch_1000", |b| {
+        b.iter(|| verifier.verify_batch(black_box(&block), black_box(&predicted)));
+    });
+    group.bench_function("verify_batch_counts_1000", |b| {
+        b.iter(|| verifier.verify_batch_counts(black_box(&block), black_box(&predicted)));
+    });
+    group.finish();
+}
+
+fn bench_verify_proof_ctx(c: &mut Criterion<Allocations>) {
+    let verifier = StateVerifier::new("v1.0.0");
+    let (block, predicted) = valid_block(1000);
+    let root = block.delta_tree_root;
+    let mut ctx = VerificationContext::new();
+
+    let mut group = c.benchmark_group("verify_proof_ctx_allocs");
+    group.throughput(Throughput::Elements(block.proofs.len() as u64));
+    group.bench_function("verify_proof_1000", |b| {
+        b.iter(|| {
+            for (proof, state) in block.proofs.iter().zip(&predicted) {
+                black_box(verifier.verify_proof(proof, state, &root));
+            }
+        });
+    });
+    group.bench_function("verify_proof_ctx_1000", |b| {
+        b.iter(|| {
+            for (proof, state) in block.proofs.iter().zip(&predicted) {
+                black_box(verifier.verify_proof_ctx(&mut ctx, proof, state, &root));
+            }
+        });
+    });
+    group.finish();
+}
+
+/// Counts repeat exactly, and criterion cannot plot a sample with no
+/// spread, so reports are text only.
+fn allocations() -> Criterion<Allocations> {
+    Criterion::default()
+        .with_measurement(Allocations)
+        .without_plots()
+}
+
+criterion_group!(
+    name = benches;
+    config = allocations();
+    targets = bench_batch_counts, bench_verify_proof_ctx
+);
+criterion_main!(benches);
@@ -0,0 +1,57 @@
+//! Fixtures shared by the verification benches.
+
+use cantor_compress::{CompressionMethod, DeltaEncoder};
+use cantor_core::{
+    CompressionResult, Hash32, QuantizationParams, StateDelta, StateVector, TreeHasher,
+    VerificationProof,
+};
+use cantor_merkle::MerkleDeltaTree;
+
+/// Block of `count` valid proofs for model `v1.0.0`, so every one is
+/// decoded and reconstructed rather than failing an early check.
+pub fn valid_block(count: usize) -> (CompressionResult, Vec<Vec<f32>>) {
+    let encoder = DeltaEncoder::new(CompressionMethod::Lz4);
+    let predicted = vec![1.0f32; 64];
+    let predicted_root = StateVector::new(predicted.clone()).compute_hash();
+    let deltas: Vec<Vec<f32>> = (0..count)
+        .map(|i| (0..64).map(|d| ((i + d) % 7) as f32 * 0.5).collect())
+        .collect();
+    let encoded: Vec<Vec<u8>> = deltas.iter().map(|d| encoder.encode(d).unwrap()).collect();
+    let refs: Vec<&[u8]> = encoded.iter().map(|e| e.as_slice()).collect();
+    let tree = MerkleDeltaTree::build(&refs);
+
+    let mut block_deltas = Vec::new();
+    let mut proofs = Vec::new();
+    for (i, (delta, delta_bytes)) in deltas.iter().zip(encoded).enumerate() {
+        let actual: Vec<f32> = predicted.iter().zip(delta).map(|(p, d)| p + d).collect();
+        let tx_hash = Hash32([(i % 256) as u8; 32]);
+        let state_delta = StateDelta {
+            tx_hash,
+            predicted_root,
+            actual_root: StateVector::new(actual).compute_hash(),
+            delta_bytes: delta_bytes.into(),
+            confidence: 1.0,
+            method: CompressionMethod::Lz4,
+        };
+        proofs.push(VerificationProof {
+            tx_hash,
+            predicted_state: predicted_root,
+            delta: state_delta.clone(),
+            merkle_proof: tree.generate_proof(i).unwrap(),
+            model_version: "v1.0.0".to_string(),
+            tree_hasher: TreeHasher::default(),
+        });
+        block_deltas.push(state_delta);
+    }
+
+    let block = CompressionResult {
+        block_number: 1,
+        original_size: 0,
+        compressed_size: 0,
+        delta_tree_root: tree.root(),
+        deltas: block_deltas,
+        proofs,
+        quantization: QuantizationParams::default(),
+    };
+    (block, vec![predicted; count])
+}
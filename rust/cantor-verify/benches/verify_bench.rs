@@ -1,6 +1,11 @@
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
-use cantor_core::{Hash32, MerkleProof};
+use cantor_core::MerkleProof;
 use cantor_merkle::MerkleDeltaTree;
+use cantor_verify::{StateVerifier, VerificationContext};
+
+mod common;
+
+use common::valid_block;
 
 fn bench_merkle_verification(c: &mut Criterion) {
     let deltas: Vec<Vec<u8>> = (0..1000)
@@ -37,6 +42,50 @@ fn bench_batch_verification(c: &mut Criterion) {
     });
 }
 
-criterion_group!(benches, bench_merkle_verification, bench_batch_verification);
+fn bench_batch_counts(c: &mut Criterion) {
+    let verifier = StateVerifier::new("v1.0.0");
+    let (block, predicted) = valid_block(1000);
+
+    let mut group = c.benchmark_group("batch_counts");
+    group.bench_function("verify_batch_1000", |b| {
+        b.iter(|| verifier.verify_batch(black_box(&block), black_box(&predicted)));
+    });
+    group.bench_function("verify_batch_counts_1000", |b| {
+        b.iter(|| verifier.verify_batch_counts(black_box(&block), black_box(&predicted)));
+    });
+    group.finish();
+}
+
+fn bench_verify_proof_ctx(c: &mut Criterion) {
+    let verifier = StateVerifier::new("v1.0.0");
+    let (block, predicted) = valid_block(1000);
+    let root = block.delta_tree_root;
+    let mut ctx = VerificationContext::new();
+
+    let mut group = c.benchmark_group("verify_proof_ctx");
+    group.bench_function("verify_proof_1000", |b| {
+        b.iter(|| {
+            for (proof, state) in block.proofs.iter().zip(&predicted) {
+                black_box(verifier.verify_proof(proof, state, &root));
+            }
+        });
+    });
+    group.bench_function("verify_proof_ctx_1000", |b| {
+        b.iter(|| {
+            for (proof, state) in block.proofs.iter().zip(&predicted) {
+                black_box(verifier.verify_proof_ctx(&mut ctx, proof, state, &root));
+            }
+        });
+    });
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_merkle_verification,
+    bench_batch_verification,
+    bench_batch_counts,
+    bench_verify_proof_ctx
+);
 criterion_main!(benches);
 
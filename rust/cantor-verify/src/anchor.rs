@@ -0,0 +1,98 @@
+//! Checking block results against externally anchored roots.
+//!
+//! An [`Anchor`] is the record published on an external ledger for a block:
+//! its number, its delta tree root and `H(block_number_le || root)`.
+
+use cantor_core::{CantorError, CompressionResult, Hash32, Result};
+use sha2::{Digest, Sha256};
+
+/// A block's delta tree root as anchored on an external ledger.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Anchor {
+    pub block_number: u64,
+    pub root: Hash32,
+    pub anchor_hash: Hash32,
+}
+
+impl Anchor {
+    /// Anchor record for `root` at `block_number`.
+    pub fn new(block_number: u64, root: Hash32) -> Self {
+        Self {
+            block_number,
+            root,
+            anchor_hash: Self::compute_hash(block_number, &root),
+        }
+    }
+
+    /// `H(block_number_le || root)`.
+    pub fn compute_hash(block_number: u64, root: &Hash32) -> Hash32 {
+        let mut hasher = Sha256::new();
+        hasher.update(block_number.to_le_bytes());
+        hasher.update(root.as_ref());
+        Hash32::from_slice(&hasher.finalize()).unwrap()
+    }
+}
+
+/// Check that `result` is the block `anchor` commits to.
+///
+/// The anchor's own hash is recomputed first, so a record whose fields
+/// were edited after anchoring is rejected even if they match `result`.
+pub fn verify_anchor(anchor: &Anchor, result: &CompressionResult) -> Result<()> {
+    if Anchor::compute_hash(anchor.block_number, &anchor.root) != anchor.anchor_hash {
+        return Err(CantorError::AnchorMismatch(
+            "anchor hash does not match its block number and root".to_string(),
+        ));
+    }
+    if result.block_number != anchor.block_number {
+        return Err(CantorError::AnchorMismatch(format!(
+            "block {} checked against anchor for block {}",
+            result.block_number, anchor.block_number
+        )));
+    }
+    if result.delta_tree_root != anchor.root {
+        return Err(CantorError::AnchorMismatch(format!(
+            "root {} does not match anchored root {}",
+            result.delta_tree_root, anchor.root
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cantor_core::QuantizationParams;
+
+    fn result(block_number: u64, root: Hash32) -> CompressionResult {
+        CompressionResult {
+            block_number,
+            original_size: 0,
+            compressed_size: 0,
+            delta_tree_root: root,
+            deltas: vec![],
+            proofs: vec![],
+            quantization: QuantizationParams::default(),
+        }
+    }
+
+    #[test]
+    fn test_verify_anchor() {
+        let root = Hash32([9u8; 32]);
+        let anchor = Anchor::new(42, root);
+        verify_anchor(&anchor, &result(42, root)).unwrap();
+
+        let tampered = result(42, Hash32([8u8; 32]));
+        assert!(matches!(
+            verify_anchor(&anchor, &tampered),
+            Err(CantorError::AnchorMismatch(_))
+        ));
+        assert!(verify_anchor(&anchor, &result(43, root)).is_err());
+
+        // Root edited in the record without re-anchoring.
+        let forged = Anchor {
+            root: Hash32([8u8; 32]),
+            ..anchor
+        };
+        assert!(verify_anchor(&forged, &tampered).is_err());
+    }
+}
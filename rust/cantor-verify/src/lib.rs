@@ -1,20 +1,44 @@
 //! High-performance verification for CANTOR proofs.
 
+pub mod anchor;
+pub mod selftest;
+
+pub use anchor::*;
+pub use selftest::*;
+
 use cantor_core::{
-    Hash32, VerificationProof, CompressionResult, MerkleProof, CantorError, Result,
+    Hash32, MerkleProof, VerificationProof, CompressionResult, DeltaChain, CantorError, Result,
+    StateVector, ProofBatch, DeltaStore, hash_state_iter, model_commitment,
+    reconstruct_canonical_into, QuantizationParams,
 };
-use cantor_merkle::MerkleDeltaTree;
 use cantor_compress::{DeltaEncoder, CompressionMethod};
-use sha2::{Sha256, Digest};
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::hash::BuildHasher;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// Proofs verified between clock checks in
+/// [`StateVerifier::verify_batch_timed`].
+pub const TIMED_CHECK_INTERVAL: usize = 16;
 
 /// Verification status.
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum VerificationStatus {
     Valid,
     InvalidMerkle,
     InvalidPrediction,
     InvalidDelta,
     ModelMismatch,
+    BrokenChain,
+    /// Cryptographically valid, but the delta breaks the magnitude policy.
+    DeltaMagnitudeExceeded,
+    /// Cryptographically valid, but the reconstructed state leaves the
+    /// declared bounds.
+    StateOutOfBounds,
+    /// The proof's block is below the verifier's replay watermark; see
+    /// [`StateVerifier::set_min_block`].
+    StaleBlock,
 }
 
 /// Result of verification.
@@ -41,12 +65,135 @@ impl VerificationResult {
             message: message.into(),
         }
     }
+
+    /// Attribute this result to a transaction.
+    pub fn for_tx(mut self, tx_hash: Hash32) -> Self {
+        self.tx_hash = Some(tx_hash);
+        self
+    }
+}
+
+/// How a batch reacts to a failing proof.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BatchMode {
+    /// Stop at the first failing proof.
+    FailFast,
+    /// Verify every proof regardless of earlier failures.
+    #[default]
+    CollectAll,
+}
+
+/// Results of a batch verification.
+#[derive(Clone, Debug)]
+pub struct BatchOutcome {
+    /// One result per verified proof, in input order. Under
+    /// [`BatchMode::FailFast`] this ends at the first failure.
+    pub results: Vec<VerificationResult>,
+    pub first_failure: Option<usize>,
+}
+
+impl BatchOutcome {
+    pub fn all_valid(&self) -> bool {
+        self.first_failure.is_none()
+    }
+}
+
+/// The check a proof failed, before it is rendered into a
+/// [`VerificationResult`] message.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum CheckFailure {
+    ModelMismatch,
+    Merkle,
+    Prediction,
+    MissingDelta,
+    DeltaHashMismatch,
+    Decode,
+    Dimension,
+    ActualRoot,
+    Magnitude,
+    Bounds,
+    StaleBlock(u64),
+    MissingPrediction,
+}
+
+/// Store for proofs verified without one: every lookup misses, so a
+/// delta referenced by hash fails as [`CheckFailure::MissingDelta`].
+struct NoDeltaStore;
+
+impl DeltaStore for NoDeltaStore {
+    fn get(&self, _: &Hash32) -> Option<Cow<'_, [u8]>> {
+        None
+    }
+}
+
+impl CheckFailure {
+    fn status(self) -> VerificationStatus {
+        match self {
+            Self::ModelMismatch => VerificationStatus::ModelMismatch,
+            Self::Merkle => VerificationStatus::InvalidMerkle,
+            Self::Prediction | Self::MissingPrediction => VerificationStatus::InvalidPrediction,
+            Self::MissingDelta
+            | Self::DeltaHashMismatch
+            | Self::Decode
+            | Self::Dimension
+            | Self::ActualRoot => VerificationStatus::InvalidDelta,
+            Self::Magnitude => VerificationStatus::DeltaMagnitudeExceeded,
+            Self::Bounds => VerificationStatus::StateOutOfBounds,
+            Self::StaleBlock(_) => VerificationStatus::StaleBlock,
+        }
+    }
+}
+
+/// Per-status tallies of a batch verification.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BatchCounts {
+    pub counts: HashMap<VerificationStatus, usize>,
+    pub first_failure: Option<usize>,
+}
+
+impl BatchCounts {
+    /// Number of proofs that ended with `status`.
+    pub fn count(&self, status: &VerificationStatus) -> usize {
+        self.counts.get(status).copied().unwrap_or(0)
+    }
+
+    pub fn all_valid(&self) -> bool {
+        self.first_failure.is_none()
+    }
+}
+
+/// Scratch space reused across [`StateVerifier::verify_proof_ctx`] calls.
+///
+/// Holds the decoded-delta and reconstructed-state buffers, so a
+/// long-lived context stops allocating them once they have grown to the
+/// largest state verified. Keep one per worker thread.
+#[derive(Debug, Default)]
+pub struct VerificationContext {
+    delta: Vec<f32>,
+    reconstructed: Vec<f32>,
+}
+
+impl VerificationContext {
+    pub fn new() -> Self {
+        Self::default()
+    }
 }
 
 /// High-performance state verifier.
+///
+/// Every delta is decoded with the method it records
+/// ([`StateDelta::method`](cantor_core::StateDelta::method)), so one
+/// block may mix methods. The remaining settings come from the block's
+/// embedded [`QuantizationParams`](cantor_core::QuantizationParams) for
+/// block-level methods and are the defaults for standalone proofs; framed
+/// payloads carry their own in the header either way.
 pub struct StateVerifier {
     model_version: String,
     encoder: DeltaEncoder,
+    max_delta_magnitude: Option<f32>,
+    state_bounds: Option<(f32, f32)>,
+    /// Lowest block number block-level methods accept, once set.
+    min_block: Option<AtomicU64>,
 }
 
 impl StateVerifier {
@@ -54,9 +201,57 @@ impl StateVerifier {
         Self {
             model_version: model_version.into(),
             encoder: DeltaEncoder::new(CompressionMethod::Lz4),
+            max_delta_magnitude: None,
+            state_bounds: None,
+            min_block: None,
         }
     }
 
+    /// Reject blocks numbered below `block` as replays, and keep the
+    /// watermark at the highest block verified from then on.
+    ///
+    /// Applies to the block-level methods ([`Self::verify_batch`] and the
+    /// others taking a `CompressionResult` or `ProofBatch`), since a
+    /// standalone proof carries no block number. Every proof of a block
+    /// below the watermark reports [`VerificationStatus::StaleBlock`]
+    /// without being checked. Once every proof of a non-empty block
+    /// verifies, the watermark rises to that block, so later calls accept
+    /// it and newer blocks only. [`Self::verify_proof_batch`] checks the
+    /// watermark but never raises it: its root comes with the batch, so a
+    /// batch verifying proves nothing about the block it names.
+    pub fn set_min_block(&mut self, block: u64) {
+        self.min_block = Some(AtomicU64::new(block));
+    }
+
+    /// The current watermark, if [`Self::set_min_block`] has been called.
+    pub fn min_block(&self) -> Option<u64> {
+        self.min_block
+            .as_ref()
+            .map(|min| min.load(Ordering::Acquire))
+    }
+
+    /// Flag proofs whose decoded delta has any `|delta[i]| > max`. NaN
+    /// exceeds every limit.
+    ///
+    /// This is a policy gate on top of the cryptographic checks: it only
+    /// applies to proofs that otherwise verify, which then report
+    /// [`VerificationStatus::DeltaMagnitudeExceeded`] instead of `Valid`.
+    pub fn with_max_delta_magnitude(mut self, max: f32) -> Self {
+        self.max_delta_magnitude = Some(max);
+        self
+    }
+
+    /// Flag proofs whose reconstructed state has any element outside
+    /// `[min, max]`, e.g. `[0, 1]` for probabilities. NaN is outside.
+    ///
+    /// Like [`Self::with_max_delta_magnitude`], this is a policy gate
+    /// applied after the cryptographic checks; violating proofs report
+    /// [`VerificationStatus::StateOutOfBounds`].
+    pub fn with_state_bounds(mut self, min: f32, max: f32) -> Self {
+        self.state_bounds = Some((min, max));
+        self
+    }
+
     /// Verify a single proof.
     pub fn verify_proof(
         &self,
@@ -64,101 +259,1616 @@ impl StateVerifier {
         predicted_state: &[f32],
         expected_root: &Hash32,
     ) -> VerificationResult {
-        // Check model version
-        if proof.model_version != self.model_version {
-            return VerificationResult::invalid(
-                VerificationStatus::ModelMismatch,
-                format!(
-                    "Model version mismatch: {} != {}",
-                    proof.model_version, self.model_version
-                ),
-            );
+        self.verify_with(&self.encoder, proof, predicted_state, expected_root)
+    }
+
+    /// [`Self::verify_proof`] reusing the buffers in `ctx`, for verifying
+    /// many proofs one at a time. Results are identical.
+    pub fn verify_proof_ctx(
+        &self,
+        ctx: &mut VerificationContext,
+        proof: &VerificationProof,
+        predicted_state: &[f32],
+        expected_root: &Hash32,
+    ) -> VerificationResult {
+        match self.check_into(
+            &self.encoder,
+            &NoDeltaStore,
+            ctx,
+            proof,
+            predicted_state,
+            expected_root,
+        ) {
+            Ok(()) => VerificationResult::valid(proof.tx_hash),
+            Err(failure) => self.failure_result(failure, proof),
+        }
+    }
+
+    /// [`Self::verify_proof`] for a proof whose delta may be referenced by
+    /// hash ([`cantor_core::DeltaRef::ByHash`]), looking the bytes up in
+    /// `store`.
+    ///
+    /// The stored bytes must hash to the reference. A delta the store
+    /// lacks, or holds different bytes for, fails as
+    /// [`VerificationStatus::InvalidDelta`]; inline deltas never touch the
+    /// store.
+    pub fn verify_proof_with_store(
+        &self,
+        proof: &VerificationProof,
+        predicted_state: &[f32],
+        expected_root: &Hash32,
+        store: &dyn DeltaStore,
+    ) -> VerificationResult {
+        let mut ctx = VerificationContext::new();
+        match self.check_into(
+            &self.encoder,
+            store,
+            &mut ctx,
+            proof,
+            predicted_state,
+            expected_root,
+        ) {
+            Ok(()) => VerificationResult::valid(proof.tx_hash),
+            Err(failure) => self.failure_result(failure, proof),
+        }
+    }
+
+    fn verify_with(
+        &self,
+        encoder: &DeltaEncoder,
+        proof: &VerificationProof,
+        predicted_state: &[f32],
+        expected_root: &Hash32,
+    ) -> VerificationResult {
+        match self.reconstruct_checked(encoder, proof, predicted_state, expected_root) {
+            Ok(_) => VerificationResult::valid(proof.tx_hash),
+            Err(failure) => self.failure_result(failure, proof),
+        }
+    }
+
+    /// [`Self::verify_with`] for a proof of block `block_number`, which
+    /// must not be below the watermark.
+    fn verify_in_block(
+        &self,
+        encoder: &DeltaEncoder,
+        block_number: u64,
+        proof: &VerificationProof,
+        predicted_state: &[f32],
+        expected_root: &Hash32,
+    ) -> VerificationResult {
+        match self.check_block(block_number) {
+            Ok(()) => self.verify_with(encoder, proof, predicted_state, expected_root),
+            Err(failure) => self.failure_result(failure, proof),
+        }
+    }
+
+    fn check_block(&self, block_number: u64) -> std::result::Result<(), CheckFailure> {
+        match self.min_block() {
+            Some(min) if block_number < min => Err(CheckFailure::StaleBlock(block_number)),
+            _ => Ok(()),
         }
+    }
+
+    /// Raise the watermark, if one is set, to a block of which `valid` out
+    /// of `proofs` verified. Only a block whose proofs all verified moves
+    /// it, and an empty block proves nothing, so it never does.
+    fn advance_min_block(&self, block_number: u64, proofs: usize, valid: usize) {
+        if let Some(min) = &self.min_block {
+            if proofs > 0 && valid == proofs {
+                min.fetch_max(block_number, Ordering::AcqRel);
+            }
+        }
+    }
+
+    fn valid_count<'a>(results: impl IntoIterator<Item = &'a VerificationResult>) -> usize {
+        results
+            .into_iter()
+            .filter(|r| r.status == VerificationStatus::Valid)
+            .count()
+    }
 
-        // Verify merkle proof
-        if !MerkleDeltaTree::verify_proof(&proof.merkle_proof, expected_root) {
+    /// Verify a proof against a [`cantor_core::model_commitment`] rather
+    /// than a bare root.
+    ///
+    /// The commitment is recomputed from the proof's declared model version,
+    /// so a proof replayed under any other version fails as
+    /// [`VerificationStatus::InvalidMerkle`] even if the version strings
+    /// have been rewritten to match.
+    pub fn verify_proof_bound(
+        &self,
+        proof: &VerificationProof,
+        predicted_state: &[f32],
+        commitment: &Hash32,
+    ) -> VerificationResult {
+        let root = proof.merkle_root();
+        if model_commitment(&root, &proof.model_version) != *commitment {
             return VerificationResult::invalid(
                 VerificationStatus::InvalidMerkle,
-                "Merkle proof verification failed",
-            );
+                "Model-bound commitment verification failed",
+            )
+            .for_tx(proof.tx_hash);
         }
 
-        // Verify predicted state hash
-        let predicted_hash = Self::compute_hash(predicted_state);
-        if predicted_hash != proof.predicted_state {
+        self.verify_proof(proof, predicted_state, &root)
+    }
+
+    /// Verify a proof whose predicted state is itself committed in a
+    /// separate tree.
+    ///
+    /// `predicted_proof` must prove inclusion of `predicted_state` under
+    /// `predicted_root`, where predicted-tree leaves are the little-endian
+    /// bytes of each state (so a leaf hash equals the state hash). That
+    /// inclusion is checked before any reconstruction; a prediction that is
+    /// not committed fails as [`VerificationStatus::InvalidPrediction`].
+    pub fn verify_committed_prediction(
+        &self,
+        proof: &VerificationProof,
+        predicted_state: &[f32],
+        predicted_proof: &MerkleProof,
+        predicted_root: &Hash32,
+        delta_root: &Hash32,
+    ) -> VerificationResult {
+        if predicted_proof.leaf_hash != Self::compute_hash(predicted_state)
+            || !predicted_proof.verify(predicted_root)
+        {
             return VerificationResult::invalid(
                 VerificationStatus::InvalidPrediction,
-                "Predicted state hash mismatch",
-            );
+                "Predicted state is not committed under the predicted root",
+            )
+            .for_tx(proof.tx_hash);
         }
 
-        // Decode delta and reconstruct
-        let delta = match self.encoder.decode(&proof.delta.delta_bytes) {
-            Ok(d) => d,
-            Err(_) => {
-                return VerificationResult::invalid(
-                    VerificationStatus::InvalidDelta,
-                    "Failed to decode delta",
-                );
+        self.verify_proof(proof, predicted_state, delta_root)
+    }
+
+    /// Verify a proof and return the reconstructed actual state.
+    ///
+    /// The vector is only returned once it has been checked to hash to
+    /// `proof.delta.actual_root`; any failed check is returned as an error,
+    /// so callers never receive an unverified reconstruction.
+    pub fn verify_and_reconstruct(
+        &self,
+        proof: &VerificationProof,
+        predicted_state: &[f32],
+        expected_root: &Hash32,
+    ) -> Result<Vec<f32>> {
+        self.reconstruct_checked(&self.encoder, proof, predicted_state, expected_root)
+            .map_err(|failure| match failure {
+                CheckFailure::ModelMismatch => CantorError::ModelVersionMismatch {
+                    expected: self.model_version.clone(),
+                    actual: proof.model_version.clone(),
+                },
+                CheckFailure::Merkle => CantorError::MerkleVerificationFailed,
+                _ => CantorError::StateReconstructionFailed(
+                    self.failure_result(failure, proof).message,
+                ),
+            })
+    }
+
+    /// Run every check and return the reconstructed state, or the first
+    /// check that failed.
+    fn reconstruct_checked(
+        &self,
+        encoder: &DeltaEncoder,
+        proof: &VerificationProof,
+        predicted_state: &[f32],
+        expected_root: &Hash32,
+    ) -> std::result::Result<Vec<f32>, CheckFailure> {
+        let mut ctx = VerificationContext::new();
+        self.check_into(
+            encoder,
+            &NoDeltaStore,
+            &mut ctx,
+            proof,
+            predicted_state,
+            expected_root,
+        )?;
+        Ok(ctx.reconstructed)
+    }
+
+    /// Run every check, leaving the reconstructed state in `ctx`.
+    fn check_into(
+        &self,
+        encoder: &DeltaEncoder,
+        store: &dyn DeltaStore,
+        ctx: &mut VerificationContext,
+        proof: &VerificationProof,
+        predicted_state: &[f32],
+        expected_root: &Hash32,
+    ) -> std::result::Result<(), CheckFailure> {
+        if proof.model_version != self.model_version {
+            return Err(CheckFailure::ModelMismatch);
+        }
+
+        if !proof.verify_merkle(expected_root) {
+            return Err(CheckFailure::Merkle);
+        }
+
+        if Self::compute_hash(predicted_state) != proof.predicted_state {
+            return Err(CheckFailure::Prediction);
+        }
+
+        let delta_bytes = proof
+            .delta
+            .delta_bytes
+            .resolve(store)
+            .map_err(|e| match e {
+                CantorError::DeltaHashMismatch(_) => CheckFailure::DeltaHashMismatch,
+                _ => CheckFailure::MissingDelta,
+            })?;
+        // `encoder` supplies the settings; the method is the delta's own.
+        let recorded;
+        let decoder = if proof.delta.method == encoder.method() {
+            encoder
+        } else {
+            recorded = DeltaEncoder::from_params(QuantizationParams {
+                method: proof.delta.method,
+                ..encoder.params()
+            });
+            &recorded
+        };
+        decoder
+            .decode_into(&delta_bytes, &mut ctx.delta)
+            .map_err(|_| CheckFailure::Decode)?;
+
+        reconstruct_canonical_into(predicted_state, &ctx.delta, &mut ctx.reconstructed)
+            .map_err(|_| CheckFailure::Dimension)?;
+
+        if Self::compute_hash(&ctx.reconstructed) != proof.delta.actual_root {
+            return Err(CheckFailure::ActualRoot);
+        }
+
+        if let Some(max) = self.max_delta_magnitude {
+            if ctx.delta.iter().any(|d| d.is_nan() || d.abs() > max) {
+                return Err(CheckFailure::Magnitude);
+            }
+        }
+
+        if let Some((min, max)) = self.state_bounds {
+            if !ctx.reconstructed.iter().all(|v| (min..=max).contains(v)) {
+                return Err(CheckFailure::Bounds);
             }
+        }
+
+        Ok(())
+    }
+
+    /// Verify every step of a delta chain against a single tree root.
+    ///
+    /// `initial_state` is the predicted state of the first step; each later
+    /// step is verified against the state reconstructed by the step before
+    /// it. On success the result carries the last step's tx_hash.
+    pub fn verify_chain(
+        &self,
+        chain: &DeltaChain,
+        initial_state: &[f32],
+        expected_root: &Hash32,
+    ) -> VerificationResult {
+        let Some(last) = chain.steps.last() else {
+            return VerificationResult::invalid(VerificationStatus::BrokenChain, "Empty chain");
         };
 
-        // Reconstruct actual state
-        if delta.len() != predicted_state.len() {
+        if let Some(step) = chain.first_broken_link() {
             return VerificationResult::invalid(
-                VerificationStatus::InvalidDelta,
-                "Delta dimension mismatch",
+                VerificationStatus::BrokenChain,
+                format!("Step {} does not continue from step {}", step, step - 1),
             );
         }
 
-        let reconstructed: Vec<f32> = predicted_state
+        let mut state = initial_state.to_vec();
+        for (step, proof) in chain.steps.iter().enumerate() {
+            state = match self.reconstruct_checked(&self.encoder, proof, &state, expected_root) {
+                Ok(next) => next,
+                Err(failure) => {
+                    let mut result = self.failure_result(failure, proof);
+                    result.message = format!("Step {}: {}", step, result.message);
+                    return result;
+                }
+            };
+        }
+
+        VerificationResult::valid(last.tx_hash)
+    }
+
+    /// Verify a proof and root supplied in serialized form.
+    ///
+    /// `proof_bytes` is a `VerificationProof` in the CANTOR wire format and
+    /// `root_bytes` is the raw 32-byte root. Malformed input is reported as
+    /// an error rather than a verification status.
+    pub fn verify_serialized(
+        &self,
+        proof_bytes: &[u8],
+        predicted_state: &[f32],
+        root_bytes: &[u8],
+    ) -> Result<VerificationResult> {
+        let root = Hash32::from_slice(root_bytes)
+            .ok_or(CantorError::InvalidHashLength(root_bytes.len()))?;
+        let proof = VerificationProof::from_bytes(proof_bytes)?;
+        Ok(self.verify_proof(&proof, predicted_state, &root))
+    }
+
+    /// Batch verify multiple proofs.
+    ///
+    /// `predicted_states[i]` is the predicted state for `result.proofs[i]`,
+    /// and the returned results are index-aligned with the proofs. Inputs of
+    /// different lengths are rejected with
+    /// `CantorError::BatchLengthMismatch` rather than truncated.
+    pub fn verify_batch(
+        &self,
+        result: &CompressionResult,
+        predicted_states: &[Vec<f32>],
+    ) -> Result<Vec<VerificationResult>> {
+        Self::check_batch_len(&result.proofs, predicted_states)?;
+        let encoder = DeltaEncoder::from_params(result.quantization);
+        let results: Vec<VerificationResult> = result
+            .proofs
+            .iter()
+            .zip(predicted_states.iter())
+            .map(|(proof, predicted)| {
+                self.verify_in_block(
+                    &encoder,
+                    result.block_number,
+                    proof,
+                    predicted,
+                    &result.delta_tree_root,
+                )
+            })
+            .collect();
+        self.advance_min_block(
+            result.block_number,
+            result.proofs.len(),
+            Self::valid_count(&results),
+        );
+        Ok(results)
+    }
+
+    /// [`Self::verify_batch`] with each proof's predicted state looked up
+    /// by its `tx_hash`, so the states need not follow the proofs' order.
+    ///
+    /// A proof whose transaction has no entry in `predicted_states`
+    /// reports [`VerificationStatus::InvalidPrediction`]; entries no proof
+    /// refers to are ignored. Results are index-aligned with the proofs.
+    pub fn verify_by_tx_hash<S: BuildHasher>(
+        &self,
+        result: &CompressionResult,
+        predicted_states: &HashMap<Hash32, Vec<f32>, S>,
+    ) -> Vec<VerificationResult> {
+        let encoder = DeltaEncoder::from_params(result.quantization);
+        let results: Vec<VerificationResult> = result
+            .proofs
+            .iter()
+            .map(|proof| match predicted_states.get(&proof.tx_hash) {
+                Some(predicted) => self.verify_in_block(
+                    &encoder,
+                    result.block_number,
+                    proof,
+                    predicted,
+                    &result.delta_tree_root,
+                ),
+                None => {
+                    let failure = self
+                        .check_block(result.block_number)
+                        .err()
+                        .unwrap_or(CheckFailure::MissingPrediction);
+                    self.failure_result(failure, proof)
+                }
+            })
+            .collect();
+        self.advance_min_block(
+            result.block_number,
+            result.proofs.len(),
+            Self::valid_count(&results),
+        );
+        results
+    }
+
+    fn check_batch_len(proofs: &[VerificationProof], predicted_states: &[Vec<f32>]) -> Result<()> {
+        if proofs.len() != predicted_states.len() {
+            return Err(CantorError::BatchLengthMismatch {
+                proofs: proofs.len(),
+                predicted: predicted_states.len(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Verify every proof in `batch` against the batch's own root.
+    ///
+    /// Stale blocks are rejected as for [`Self::verify_batch`], but the
+    /// watermark stays put, since the root is not one the verifier chose.
+    /// As with [`Self::verify_batch`], `predicted_states[i]` belongs to
+    /// `batch.proofs[i]`, results are index-aligned, and inputs of
+    /// different lengths fail with `CantorError::BatchLengthMismatch`.
+    pub fn verify_proof_batch(
+        &self,
+        batch: &ProofBatch,
+        predicted_states: &[Vec<f32>],
+    ) -> Result<Vec<VerificationResult>> {
+        Self::check_batch_len(&batch.proofs, predicted_states)?;
+        let encoder = DeltaEncoder::from_params(batch.quantization);
+        let results: Vec<VerificationResult> = batch
+            .proofs
             .iter()
-            .zip(delta.iter())
-            .map(|(p, d)| p + d)
+            .zip(predicted_states)
+            .map(|(proof, predicted)| {
+                self.verify_in_block(&encoder, batch.block_number, proof, predicted, &batch.root)
+            })
             .collect();
+        Ok(results)
+    }
 
-        let reconstructed_hash = Self::compute_hash(&reconstructed);
-        if reconstructed_hash != proof.delta.actual_root {
-            return VerificationResult::invalid(
-                VerificationStatus::InvalidDelta,
-                "Reconstructed state hash mismatch",
+    /// Verify block 0 against the genesis state.
+    ///
+    /// Block 0 has no predecessor, so every one of its deltas is taken to
+    /// be relative to `genesis` (conventionally [`StateVector::zeros`]),
+    /// rather than to a caller-supplied predicted state per proof. Any
+    /// other block fails with `CantorError::NotGenesisBlock`.
+    pub fn verify_genesis_block(
+        &self,
+        result: &CompressionResult,
+        genesis: &StateVector,
+    ) -> Result<Vec<VerificationResult>> {
+        if result.block_number != 0 {
+            return Err(CantorError::NotGenesisBlock(result.block_number));
+        }
+        let encoder = DeltaEncoder::from_params(result.quantization);
+        let results: Vec<VerificationResult> = result
+            .proofs
+            .iter()
+            .map(|proof| {
+                self.verify_in_block(
+                    &encoder,
+                    result.block_number,
+                    proof,
+                    &genesis.data,
+                    &result.delta_tree_root,
+                )
+            })
+            .collect();
+        self.advance_min_block(
+            result.block_number,
+            result.proofs.len(),
+            Self::valid_count(&results),
+        );
+        Ok(results)
+    }
+
+    /// Batch verify every proof and return only per-status counts.
+    ///
+    /// Unlike [`Self::verify_batch`], no `VerificationResult` or message is
+    /// built per proof, which keeps very large blocks from churning the
+    /// allocator. Inputs must be index-aligned as for `verify_batch`.
+    pub fn verify_batch_counts(
+        &self,
+        result: &CompressionResult,
+        predicted_states: &[Vec<f32>],
+    ) -> Result<BatchCounts> {
+        Self::check_batch_len(&result.proofs, predicted_states)?;
+        let encoder = DeltaEncoder::from_params(result.quantization);
+        let mut counts = BatchCounts::default();
+        let mut ctx = VerificationContext::new();
+
+        for (i, (proof, predicted)) in result.proofs.iter().zip(predicted_states).enumerate() {
+            let checked = self.check_block(result.block_number).and_then(|()| {
+                self.check_into(
+                    &encoder,
+                    &NoDeltaStore,
+                    &mut ctx,
+                    proof,
+                    predicted,
+                    &result.delta_tree_root,
+                )
+            });
+            let status = match checked {
+                Ok(()) => VerificationStatus::Valid,
+                Err(failure) => {
+                    counts.first_failure.get_or_insert(i);
+                    failure.status()
+                }
+            };
+            *counts.counts.entry(status).or_insert(0) += 1;
+        }
+
+        self.advance_min_block(
+            result.block_number,
+            result.proofs.len(),
+            counts.count(&VerificationStatus::Valid),
+        );
+        Ok(counts)
+    }
+
+    /// Batch verify with an explicit failure policy. Inputs must be
+    /// index-aligned as for `verify_batch`.
+    pub fn verify_batch_mode(
+        &self,
+        result: &CompressionResult,
+        predicted_states: &[Vec<f32>],
+        mode: BatchMode,
+    ) -> Result<BatchOutcome> {
+        Self::check_batch_len(&result.proofs, predicted_states)?;
+        let encoder = DeltaEncoder::from_params(result.quantization);
+        let mut results = Vec::with_capacity(result.proofs.len());
+        let mut first_failure = None;
+
+        for (i, (proof, predicted)) in result.proofs.iter().zip(predicted_states).enumerate() {
+            let verification = self.verify_in_block(
+                &encoder,
+                result.block_number,
+                proof,
+                predicted,
+                &result.delta_tree_root,
             );
+            let failed = verification.status != VerificationStatus::Valid;
+            results.push(verification);
+
+            if failed && first_failure.is_none() {
+                first_failure = Some(i);
+                if mode == BatchMode::FailFast {
+                    break;
+                }
+            }
         }
 
-        VerificationResult::valid(proof.tx_hash)
+        self.advance_min_block(
+            result.block_number,
+            result.proofs.len(),
+            Self::valid_count(&results),
+        );
+        Ok(BatchOutcome {
+            results,
+            first_failure,
+        })
     }
 
-    /// Batch verify multiple proofs.
-    pub fn verify_batch(
+    /// Batch verify under a wall-clock budget.
+    ///
+    /// The clock is checked every [`TIMED_CHECK_INTERVAL`] proofs; once
+    /// `budget` has elapsed the batch stops and the results so far are
+    /// returned with `true`. The results are index-aligned with the leading
+    /// proofs, and `false` means every proof was verified. Inputs must be
+    /// index-aligned as for `verify_batch`.
+    pub fn verify_batch_timed(
         &self,
         result: &CompressionResult,
         predicted_states: &[Vec<f32>],
-    ) -> Vec<VerificationResult> {
-        result
+        budget: Duration,
+    ) -> Result<(Vec<VerificationResult>, bool)> {
+        Self::check_batch_len(&result.proofs, predicted_states)?;
+        let encoder = DeltaEncoder::from_params(result.quantization);
+        let start = Instant::now();
+        let mut results = Vec::with_capacity(result.proofs.len());
+
+        for (i, (proof, predicted)) in result.proofs.iter().zip(predicted_states).enumerate() {
+            if i > 0 && i % TIMED_CHECK_INTERVAL == 0 && start.elapsed() >= budget {
+                return Ok((results, true));
+            }
+            results.push(self.verify_in_block(
+                &encoder,
+                result.block_number,
+                proof,
+                predicted,
+                &result.delta_tree_root,
+            ));
+        }
+
+        self.advance_min_block(
+            result.block_number,
+            result.proofs.len(),
+            Self::valid_count(&results),
+        );
+        Ok((results, false))
+    }
+
+    /// Batch verify, timing each proof, to find the pathological ones
+    /// (large deltas, deep paths). Each duration covers that proof's
+    /// decode, Merkle and hash checks. Inputs must be index-aligned as for
+    /// `verify_batch`.
+    pub fn verify_batch_timed_each(
+        &self,
+        result: &CompressionResult,
+        predicted_states: &[Vec<f32>],
+    ) -> Result<Vec<(VerificationResult, Duration)>> {
+        Self::check_batch_len(&result.proofs, predicted_states)?;
+        let encoder = DeltaEncoder::from_params(result.quantization);
+        let timed: Vec<(VerificationResult, Duration)> = result
             .proofs
             .iter()
-            .zip(predicted_states.iter())
+            .zip(predicted_states)
             .map(|(proof, predicted)| {
-                self.verify_proof(proof, predicted, &result.delta_tree_root)
+                let start = Instant::now();
+                let verification = self.verify_in_block(
+                    &encoder,
+                    result.block_number,
+                    proof,
+                    predicted,
+                    &result.delta_tree_root,
+                );
+                (verification, start.elapsed())
             })
-            .collect()
+            .collect();
+        self.advance_min_block(
+            result.block_number,
+            result.proofs.len(),
+            Self::valid_count(timed.iter().map(|(r, _)| r)),
+        );
+        Ok(timed)
+    }
+
+    /// Verify proofs and predicted states drawn from two lazy sources.
+    ///
+    /// Items pair up in order and each is checked as by
+    /// [`Self::verify_proof`], without collecting either source. If one
+    /// source ends before the other, a single
+    /// `CantorError::BatchLengthMismatch` item is yielded and iteration
+    /// stops; its counts are those seen so far, so the longer side is
+    /// reported as one past the shorter.
+    pub fn verify_zip<'a, P, S>(
+        &'a self,
+        proofs: P,
+        states: S,
+        root: &'a Hash32,
+    ) -> impl Iterator<Item = Result<VerificationResult>> + 'a
+    where
+        P: Iterator<Item = VerificationProof> + 'a,
+        S: Iterator<Item = Vec<f32>> + 'a,
+    {
+        let (mut proofs, mut states) = (proofs.fuse(), states.fuse());
+        let mut paired = 0;
+        let mut done = false;
+        std::iter::from_fn(move || {
+            if done {
+                return None;
+            }
+            match (proofs.next(), states.next()) {
+                (Some(proof), Some(state)) => {
+                    paired += 1;
+                    Some(Ok(self.verify_proof(&proof, &state, root)))
+                }
+                (None, None) => {
+                    done = true;
+                    None
+                }
+                (proof, state) => {
+                    done = true;
+                    Some(Err(CantorError::BatchLengthMismatch {
+                        proofs: paired + proof.is_some() as usize,
+                        predicted: paired + state.is_some() as usize,
+                    }))
+                }
+            }
+        })
+    }
+
+    fn failure_result(&self, failure: CheckFailure, proof: &VerificationProof) -> VerificationResult {
+        let message = match failure {
+            CheckFailure::ModelMismatch => format!(
+                "Model version mismatch: {} != {}",
+                proof.model_version, self.model_version
+            ),
+            CheckFailure::Merkle => "Merkle proof verification failed".to_string(),
+            CheckFailure::Prediction => "Predicted state hash mismatch".to_string(),
+            CheckFailure::MissingDelta => format!(
+                "Delta {} not found in the delta store",
+                proof.delta.delta_bytes.content_hash()
+            ),
+            CheckFailure::DeltaHashMismatch => format!(
+                "Delta store bytes do not match {}",
+                proof.delta.delta_bytes.content_hash()
+            ),
+            CheckFailure::Decode => "Failed to decode delta".to_string(),
+            CheckFailure::Dimension => "Delta dimension mismatch".to_string(),
+            CheckFailure::ActualRoot => "Reconstructed state hash mismatch".to_string(),
+            CheckFailure::Magnitude => format!(
+                "Delta element exceeds the magnitude limit of {}",
+                self.max_delta_magnitude.unwrap_or(f32::INFINITY)
+            ),
+            CheckFailure::Bounds => {
+                let (min, max) = self.state_bounds.unwrap_or((f32::NEG_INFINITY, f32::INFINITY));
+                format!("Reconstructed state leaves the bounds [{}, {}]", min, max)
+            }
+            CheckFailure::StaleBlock(block) => format!(
+                "Block {} is below the replay watermark {}",
+                block,
+                self.min_block().unwrap_or(0)
+            ),
+            CheckFailure::MissingPrediction => {
+                format!("No predicted state for transaction {}", proof.tx_hash)
+            }
+        };
+        VerificationResult::invalid(failure.status(), message).for_tx(proof.tx_hash)
     }
 
     fn compute_hash(data: &[f32]) -> Hash32 {
-        let bytes: Vec<u8> = data.iter().flat_map(|f| f.to_le_bytes()).collect();
-        let result = Sha256::digest(&bytes);
-        Hash32::from_slice(&result).unwrap()
+        hash_state_iter(data.iter().copied())
     }
 }
 
+/// Distinct roots a set of proofs commits to.
+///
+/// A light client only needs to fetch and pin these roots to check every
+/// proof in the set.
+pub fn required_roots(proofs: &[VerificationProof]) -> HashSet<Hash32> {
+    proofs
+        .iter()
+        .map(VerificationProof::merkle_root)
+        .collect()
+}
+
+/// Failed results bucketed by status, with the affected tx_hashes.
+///
+/// Valid results and failures not attributed to a transaction are skipped.
+pub fn group_failures(results: &[VerificationResult]) -> HashMap<VerificationStatus, Vec<Hash32>> {
+    let mut groups: HashMap<VerificationStatus, Vec<Hash32>> = HashMap::new();
+    for result in results {
+        if result.status == VerificationStatus::Valid {
+            continue;
+        }
+        if let Some(tx_hash) = result.tx_hash {
+            groups.entry(result.status.clone()).or_default().push(tx_hash);
+        }
+    }
+    groups
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use cantor_core::{HashAlgorithm, QuantizationParams, StateDelta, TreeHasher, TreeVersion};
+    use cantor_merkle::MerkleDeltaTree;
 
     #[test]
     fn test_verifier_creation() {
         let verifier = StateVerifier::new("v1.0.0");
         assert_eq!(verifier.model_version, "v1.0.0");
     }
-}
 
+    #[test]
+    fn test_verify_serialized() {
+        let verifier = StateVerifier::new(MODEL);
+        let (predicted, _) = &sample_pairs(1)[0];
+        let block = build_block(&sample_pairs(1));
+        let proof = &block.proofs[0];
+
+        let proof_bytes = proof.to_bytes().unwrap();
+        let root = block.delta_tree_root;
+        let result = verifier
+            .verify_serialized(&proof_bytes, predicted, root.as_ref())
+            .unwrap();
+        assert_eq!(result.status, VerificationStatus::Valid);
+        assert_eq!(result.tx_hash, Some(proof.tx_hash));
+
+        assert!(matches!(
+            verifier.verify_serialized(&proof_bytes, predicted, &root.as_ref()[..16]),
+            Err(CantorError::InvalidHashLength(16))
+        ));
+        assert!(verifier
+            .verify_serialized(&proof_bytes[..10], predicted, root.as_ref())
+            .is_err());
+    }
+
+    #[test]
+    fn test_verify_and_reconstruct() {
+        let verifier = StateVerifier::new(MODEL);
+        let pairs = sample_pairs(2);
+        let block = build_block(&pairs);
+
+        let (predicted, delta) = &pairs[1];
+        let reconstructed = verifier
+            .verify_and_reconstruct(&block.proofs[1], predicted, &block.delta_tree_root)
+            .unwrap();
+        let expected: Vec<f32> = predicted.iter().zip(delta).map(|(p, d)| p + d).collect();
+        assert_eq!(reconstructed, expected);
+    }
+
+    #[test]
+    fn test_verify_and_reconstruct_rejects_tampered_delta() {
+        let verifier = StateVerifier::new(MODEL);
+        let pairs = sample_pairs(2);
+        let block = build_block(&pairs);
+
+        let mut tampered = block.proofs[0].clone();
+        tampered.delta.delta_bytes = DeltaEncoder::new(CompressionMethod::Lz4)
+            .encode(&[9.0, 9.0, 9.0, 9.0])
+            .unwrap()
+            .into();
+
+        let result =
+            verifier.verify_and_reconstruct(&tampered, &pairs[0].0, &block.delta_tree_root);
+        assert!(matches!(result, Err(CantorError::StateReconstructionFailed(_))));
+    }
+
+    #[test]
+    fn test_verify_chain() {
+        let verifier = StateVerifier::new(MODEL);
+        let deltas = [
+            vec![0.5f32, 0.0, -0.25, 1.0],
+            vec![0.0f32, 2.0, 0.0, -1.0],
+            vec![1.5f32, 0.0, 0.75, 0.0],
+        ];
+
+        let mut pairs = Vec::new();
+        let mut state = vec![1.0f32, 2.0, 3.0, 4.0];
+        for delta in &deltas {
+            let next = state.iter().zip(delta).map(|(s, d)| s + d).collect();
+            pairs.push((state, delta.clone()));
+            state = next;
+        }
+
+        let block = build_block(&pairs);
+        let chain = DeltaChain::new(block.proofs.clone());
+        let result = verifier.verify_chain(&chain, &pairs[0].0, &block.delta_tree_root);
+        assert_eq!(result.status, VerificationStatus::Valid);
+        assert_eq!(result.tx_hash, Some(block.proofs[2].tx_hash));
+
+        // Step 1 no longer continues from step 0.
+        let mut broken = build_block(&pairs);
+        broken.proofs[1] = build_block(&sample_pairs(2)).proofs[1].clone();
+        let chain = DeltaChain::new(broken.proofs.clone());
+        assert_eq!(chain.first_broken_link(), Some(1));
+        let result = verifier.verify_chain(&chain, &pairs[0].0, &broken.delta_tree_root);
+        assert_eq!(result.status, VerificationStatus::BrokenChain);
+    }
+
+    #[test]
+    fn test_verify_genesis_block() {
+        let verifier = StateVerifier::new(MODEL);
+        let genesis = StateVector::zeros(4);
+        let pairs: Vec<(Vec<f32>, Vec<f32>)> = (0..3)
+            .map(|i| (genesis.data.clone(), vec![i as f32, 1.5, -2.0, 0.25]))
+            .collect();
+        let mut block = build_block(&pairs);
+        block.block_number = 0;
+
+        let results = verifier.verify_genesis_block(&block, &genesis).unwrap();
+        assert_eq!(results.len(), 3);
+        assert!(results.iter().all(|r| r.status == VerificationStatus::Valid));
+
+        let results = verifier
+            .verify_genesis_block(&block, &StateVector::new(vec![1.0; 4]))
+            .unwrap();
+        assert!(results
+            .iter()
+            .all(|r| r.status == VerificationStatus::InvalidPrediction));
+
+        // Only block 0 has the genesis as its predecessor.
+        block.block_number = 1;
+        assert!(matches!(
+            verifier.verify_genesis_block(&block, &genesis),
+            Err(CantorError::NotGenesisBlock(1))
+        ));
+    }
+
+    #[test]
+    fn test_verify_proof_bound_to_model() {
+        let pairs = sample_pairs(3);
+        let block = build_block(&pairs);
+        let commitment = cantor_core::model_commitment(&block.delta_tree_root, MODEL);
+        let (predicted, _) = &pairs[1];
+
+        let verifier = StateVerifier::new(MODEL);
+        let result = verifier.verify_proof_bound(&block.proofs[1], predicted, &commitment);
+        assert_eq!(result.status, VerificationStatus::Valid);
+
+        // A proof relabelled for a newer model passes the string check but
+        // not the commitment.
+        let verifier = StateVerifier::new("v2.0.0");
+        let mut replayed = block.proofs[1].clone();
+        replayed.model_version = "v2.0.0".to_string();
+        assert_eq!(
+            verifier.verify_proof(&replayed, predicted, &block.delta_tree_root).status,
+            VerificationStatus::Valid
+        );
+        let result = verifier.verify_proof_bound(&replayed, predicted, &commitment);
+        assert_eq!(result.status, VerificationStatus::InvalidMerkle);
+        assert_eq!(result.tx_hash, Some(replayed.tx_hash));
+    }
+
+    #[test]
+    fn test_verify_batch_uses_embedded_quantization() {
+        // A producer's non-default settings; deltas are multiples of 1/4 so
+        // they survive a scale of 4 exactly.
+        let params = QuantizationParams {
+            scale: 4.0,
+            zero_threshold: 0.1,
+            method: CompressionMethod::Varint,
+        };
+        let pairs = sample_pairs(4);
+        let block = build_block_with(&pairs, params);
+        let predicted: Vec<Vec<f32>> = pairs.into_iter().map(|(p, _)| p).collect();
+
+        let verifier = StateVerifier::new(MODEL);
+        let results = verifier.verify_batch(&block, &predicted).unwrap();
+        assert!(results.iter().all(|r| r.status == VerificationStatus::Valid));
+
+        // The delta records its method and the frame header its scale, so
+        // a standalone proof decodes the same way without the params.
+        let standalone =
+            verifier.verify_proof(&block.proofs[0], &predicted[0], &block.delta_tree_root);
+        assert_eq!(standalone.status, VerificationStatus::Valid);
+
+        let decoded = CompressionResult::from_bytes(&block.to_bytes().unwrap()).unwrap();
+        assert_eq!(decoded.quantization, params);
+        let results = verifier.verify_batch(&decoded, &predicted).unwrap();
+        assert!(results.iter().all(|r| r.status == VerificationStatus::Valid));
+    }
+
+    #[test]
+    fn test_verify_committed_prediction() {
+        let verifier = StateVerifier::new(MODEL);
+        let pairs = sample_pairs(3);
+        let block = build_block(&pairs);
+
+        let state_bytes: Vec<Vec<u8>> = pairs
+            .iter()
+            .map(|(p, _)| p.iter().flat_map(|f| f.to_le_bytes()).collect())
+            .collect();
+        let refs: Vec<&[u8]> = state_bytes.iter().map(|b| b.as_slice()).collect();
+        let predicted_tree = MerkleDeltaTree::build(&refs);
+
+        let (predicted, _) = &pairs[2];
+        let result = verifier.verify_committed_prediction(
+            &block.proofs[2],
+            predicted,
+            &predicted_tree.generate_proof(2).unwrap(),
+            &predicted_tree.root(),
+            &block.delta_tree_root,
+        );
+        assert_eq!(result.status, VerificationStatus::Valid);
+
+        // Inclusion proof for a different committed state.
+        let result = verifier.verify_committed_prediction(
+            &block.proofs[2],
+            predicted,
+            &predicted_tree.generate_proof(1).unwrap(),
+            &predicted_tree.root(),
+            &block.delta_tree_root,
+        );
+        assert_eq!(result.status, VerificationStatus::InvalidPrediction);
+        assert_eq!(result.tx_hash, Some(block.proofs[2].tx_hash));
+    }
+
+    #[test]
+    fn test_deltas_decode_with_their_recorded_method() {
+        let pairs = sample_pairs(3);
+        let predicted: Vec<Vec<f32>> = pairs.iter().map(|(p, _)| p.clone()).collect();
+        let verifier = StateVerifier::new(MODEL);
+        for method in [
+            CompressionMethod::RunLength,
+            CompressionMethod::Varint,
+            CompressionMethod::SegmentedSparse,
+        ] {
+            let mut block = build_block_with(&pairs, QuantizationParams::for_method(method));
+            // Standalone proofs carry no params, only the delta's method.
+            for (proof, (predicted, _)) in block.proofs.iter().zip(&pairs) {
+                let result = verifier.verify_proof(proof, predicted, &block.delta_tree_root);
+                assert_eq!(result.status, VerificationStatus::Valid, "{:?}", method);
+            }
+            // Block params naming another method do not override it.
+            block.quantization = QuantizationParams::default();
+            let results = verifier.verify_batch(&block, &predicted).unwrap();
+            assert!(
+                results.iter().all(|r| r.status == VerificationStatus::Valid),
+                "{:?}",
+                method
+            );
+        }
+    }
+
+    #[test]
+    fn test_max_delta_magnitude_policy() {
+        let mut pairs = sample_pairs(2);
+        pairs[1].1[2] = 1e6;
+        let block = build_block(&pairs);
+        let root = &block.delta_tree_root;
+
+        let verifier = StateVerifier::new(MODEL);
+        assert_eq!(
+            verifier.verify_proof(&block.proofs[1], &pairs[1].0, root).status,
+            VerificationStatus::Valid
+        );
+
+        let verifier = StateVerifier::new(MODEL).with_max_delta_magnitude(1e3);
+        assert_eq!(
+            verifier.verify_proof(&block.proofs[0], &pairs[0].0, root).status,
+            VerificationStatus::Valid
+        );
+        let flagged = verifier.verify_proof(&block.proofs[1], &pairs[1].0, root);
+        assert_eq!(flagged.status, VerificationStatus::DeltaMagnitudeExceeded);
+        assert!(verifier
+            .verify_and_reconstruct(&block.proofs[1], &pairs[1].0, root)
+            .is_err());
+
+        // NaN compares false against the limit but must not slip past it.
+        let mut nan_pairs = sample_pairs(1);
+        nan_pairs[0].1[2] = f32::NAN;
+        let nan_block = build_block_encoded(
+            &nan_pairs,
+            QuantizationParams::for_method(CompressionMethod::RunLength),
+            DeltaEncoder::new(CompressionMethod::RunLength).with_non_finite_values(),
+        );
+        let predicted: Vec<Vec<f32>> = nan_pairs.iter().map(|(p, _)| p.clone()).collect();
+        assert_eq!(
+            verifier.verify_batch(&nan_block, &predicted).unwrap()[0].status,
+            VerificationStatus::DeltaMagnitudeExceeded
+        );
+
+        // Cryptographic failures still take precedence.
+        let mut tampered = block.proofs[1].clone();
+        tampered.merkle_proof.leaf_hash = Hash32::ZERO;
+        assert_eq!(
+            verifier.verify_proof(&tampered, &pairs[1].0, root).status,
+            VerificationStatus::InvalidMerkle
+        );
+    }
+
+    #[test]
+    fn test_state_bounds_policy() {
+        let pairs = vec![
+            (vec![0.2f32, 0.5, 0.9, 0.0], vec![0.1f32, 0.0, 0.1, 1.0]),
+            (vec![0.5f32, 0.5, 0.5, 0.5], vec![1.0f32, 0.0, -0.5, 0.0]),
+        ];
+        let block = build_block(&pairs);
+        let root = &block.delta_tree_root;
+
+        let verifier = StateVerifier::new(MODEL);
+        assert_eq!(
+            verifier.verify_proof(&block.proofs[1], &pairs[1].0, root).status,
+            VerificationStatus::Valid
+        );
+
+        // The second proof reconstructs 1.5 in its first dimension.
+        let verifier = StateVerifier::new(MODEL).with_state_bounds(0.0, 1.0);
+        assert_eq!(
+            verifier.verify_proof(&block.proofs[0], &pairs[0].0, root).status,
+            VerificationStatus::Valid
+        );
+        let flagged = verifier.verify_proof(&block.proofs[1], &pairs[1].0, root);
+        assert_eq!(flagged.status, VerificationStatus::StateOutOfBounds);
+        assert!(flagged.message.contains("[0, 1]"), "{}", flagged.message);
+        assert!(verifier
+            .verify_and_reconstruct(&block.proofs[1], &pairs[1].0, root)
+            .is_err());
+
+        let mut tampered = block.proofs[1].clone();
+        tampered.merkle_proof.leaf_hash = Hash32::ZERO;
+        assert_eq!(
+            verifier.verify_proof(&tampered, &pairs[1].0, root).status,
+            VerificationStatus::InvalidMerkle
+        );
+    }
+
+    #[test]
+    fn test_required_roots() {
+        let first = build_block(&sample_pairs(3));
+        let second = build_block(&sample_pairs(5));
+
+        let proofs: Vec<VerificationProof> =
+            first.proofs.iter().chain(&second.proofs).cloned().collect();
+        let roots = required_roots(&proofs);
+        assert_eq!(roots.len(), 2);
+        assert!(roots.contains(&first.delta_tree_root));
+        assert!(roots.contains(&second.delta_tree_root));
+    }
+
+    #[test]
+    fn test_verify_batch_fail_fast() {
+        let verifier = StateVerifier::new(MODEL);
+        let pairs = sample_pairs(8);
+        let mut block = build_block(&pairs);
+        block.proofs[3].model_version = "v0.9.0".to_string();
+        block.proofs[5].model_version = "v0.9.0".to_string();
+        let predicted: Vec<Vec<f32>> = pairs.into_iter().map(|(p, _)| p).collect();
+
+        let fast = verifier
+            .verify_batch_mode(&block, &predicted, BatchMode::FailFast)
+            .unwrap();
+        assert_eq!(fast.first_failure, Some(3));
+        assert_eq!(fast.results.len(), 4);
+        assert_eq!(fast.results[3].status, VerificationStatus::ModelMismatch);
+
+        let all = verifier
+            .verify_batch_mode(&block, &predicted, BatchMode::CollectAll)
+            .unwrap();
+        assert_eq!(all.first_failure, Some(3));
+        assert_eq!(all.results.len(), 8);
+        assert_eq!(all.results[5].status, VerificationStatus::ModelMismatch);
+        assert!(!all.all_valid());
+    }
+
+    #[test]
+    fn test_verify_batch_counts_matches_verify_batch() {
+        let verifier = StateVerifier::new(MODEL);
+        let pairs = sample_pairs(10);
+        let mut block = build_block(&pairs);
+        let mut predicted: Vec<Vec<f32>> = pairs.into_iter().map(|(p, _)| p).collect();
+        block.proofs[2].model_version = "v0.9.0".to_string();
+        block.proofs[7].model_version = "v0.9.0".to_string();
+        predicted[4][1] += 1.0;
+        block.proofs[6].delta.delta_bytes = vec![0xff].into();
+
+        let mut expected: HashMap<VerificationStatus, usize> = HashMap::new();
+        for result in verifier.verify_batch(&block, &predicted).unwrap() {
+            *expected.entry(result.status).or_insert(0) += 1;
+        }
+
+        let counts = verifier.verify_batch_counts(&block, &predicted).unwrap();
+        assert_eq!(counts.counts, expected);
+        assert_eq!(counts.first_failure, Some(2));
+        assert_eq!(counts.count(&VerificationStatus::Valid), 6);
+        assert_eq!(counts.count(&VerificationStatus::ModelMismatch), 2);
+        assert_eq!(counts.count(&VerificationStatus::BrokenChain), 0);
+    }
+
+    #[test]
+    fn test_verify_batch_rejects_length_mismatch() {
+        let verifier = StateVerifier::new(MODEL);
+        let pairs = sample_pairs(4);
+        let block = build_block(&pairs);
+        let predicted: Vec<Vec<f32>> = pairs.into_iter().map(|(p, _)| p).collect();
+
+        let results = verifier.verify_batch(&block, &predicted).unwrap();
+        assert_eq!(results.len(), 4);
+        for (proof, result) in block.proofs.iter().zip(&results) {
+            assert_eq!(result.tx_hash, Some(proof.tx_hash));
+        }
+
+        for short in [&predicted[..3], &[]] {
+            assert!(matches!(
+                verifier.verify_batch(&block, short),
+                Err(CantorError::BatchLengthMismatch { proofs: 4, .. })
+            ));
+            assert!(verifier.verify_batch_counts(&block, short).is_err());
+            assert!(verifier
+                .verify_batch_mode(&block, short, BatchMode::CollectAll)
+                .is_err());
+        }
+
+        let mut long = predicted.clone();
+        long.push(vec![0.0; 4]);
+        assert!(matches!(
+            verifier.verify_batch(&block, &long),
+            Err(CantorError::BatchLengthMismatch { proofs: 4, predicted: 5 })
+        ));
+    }
+
+    #[test]
+    fn test_proofs_verify_under_their_tree_hasher() {
+        let verifier = StateVerifier::new(MODEL);
+        let pairs = sample_pairs(5);
+        let predicted: Vec<Vec<f32>> = pairs.iter().map(|(p, _)| p.clone()).collect();
+        let mut block = build_block(&pairs);
+
+        let v2 = TreeHasher::new(HashAlgorithm::Keccak256, TreeVersion::V2);
+        let payloads: Vec<Vec<u8>> = block
+            .deltas
+            .iter()
+            .map(|d| d.delta_bytes.inline_bytes().unwrap().to_vec())
+            .collect();
+        let refs: Vec<&[u8]> = payloads.iter().map(|p| p.as_slice()).collect();
+        let tree = MerkleDeltaTree::build_with_hasher(&refs, v2);
+        block.delta_tree_root = tree.root();
+        for (i, proof) in block.proofs.iter_mut().enumerate() {
+            proof.merkle_proof = tree.generate_proof(i).unwrap();
+            proof.tree_hasher = v2;
+        }
+
+        let block = CompressionResult::from_bytes(&block.to_bytes().unwrap()).unwrap();
+        assert!(verifier
+            .verify_batch(&block, &predicted)
+            .unwrap()
+            .iter()
+            .all(|r| r.status == VerificationStatus::Valid));
+        assert_eq!(required_roots(&block.proofs), HashSet::from([tree.root()]));
+
+        // The same path folded under the default hasher misses the root.
+        let mut relabelled = block.proofs[2].clone();
+        relabelled.tree_hasher = TreeHasher::default();
+        assert_eq!(
+            verifier.verify_proof(&relabelled, &predicted[2], &tree.root()).status,
+            VerificationStatus::InvalidMerkle
+        );
+    }
+
+    #[test]
+    fn test_verify_proof_batch_end_to_end() {
+        let verifier = StateVerifier::new(MODEL);
+        let params = QuantizationParams {
+            scale: 4.0,
+            zero_threshold: 0.1,
+            method: CompressionMethod::Varint,
+        };
+        let pairs = sample_pairs(5);
+        let block = build_block_with(&pairs, params);
+        let predicted: Vec<Vec<f32>> = pairs.into_iter().map(|(p, _)| p).collect();
+
+        let batch = ProofBatch::from_bytes(&block.proof_batch().to_bytes().unwrap()).unwrap();
+        assert_eq!(batch.root, block.delta_tree_root);
+        assert_eq!(batch.block_number, block.block_number);
+        assert_eq!(batch.quantization, params);
+        let results = verifier.verify_proof_batch(&batch, &predicted).unwrap();
+        assert_eq!(results.len(), 5);
+        for (proof, result) in batch.proofs.iter().zip(&results) {
+            assert_eq!(result.status, VerificationStatus::Valid);
+            assert_eq!(result.tx_hash, Some(proof.tx_hash));
+        }
+
+        // A proof from another block does not verify under this root.
+        let other = build_block_with(&sample_pairs(3), params);
+        let mut mixed = batch.clone();
+        mixed.proofs[1] = other.proofs[2].clone();
+        let results = verifier.verify_proof_batch(&mixed, &predicted).unwrap();
+        assert_eq!(results[1].status, VerificationStatus::InvalidMerkle);
+        assert_eq!(results[0].status, VerificationStatus::Valid);
+
+        assert!(matches!(
+            verifier.verify_proof_batch(&batch, &predicted[..4]),
+            Err(CantorError::BatchLengthMismatch { proofs: 5, predicted: 4 })
+        ));
+    }
+
+    #[test]
+    fn test_group_failures() {
+        let verifier = StateVerifier::new(MODEL);
+        let pairs = sample_pairs(6);
+        let mut block = build_block(&pairs);
+        let mut predicted: Vec<Vec<f32>> = pairs.into_iter().map(|(p, _)| p).collect();
+
+        block.proofs[1].model_version = "v0.9.0".to_string();
+        block.proofs[4].model_version = "v0.9.0".to_string();
+        predicted[2][0] += 1.0;
+        block.proofs[5].merkle_proof.leaf_hash = Hash32::ZERO;
+
+        let results = verifier.verify_batch(&block, &predicted).unwrap();
+        let groups = group_failures(&results);
+        let tx = |i: usize| block.proofs[i].tx_hash;
+
+        assert_eq!(groups.len(), 3);
+        assert_eq!(groups[&VerificationStatus::ModelMismatch], vec![tx(1), tx(4)]);
+        assert_eq!(groups[&VerificationStatus::InvalidPrediction], vec![tx(2)]);
+        assert_eq!(groups[&VerificationStatus::InvalidMerkle], vec![tx(5)]);
+        assert!(!groups.contains_key(&VerificationStatus::Valid));
+    }
+
+    #[test]
+    fn test_verify_proof_ctx_matches_verify_proof() {
+        let verifier = StateVerifier::new(MODEL);
+        let pairs = sample_pairs(12);
+        let mut block = build_block(&pairs);
+        let mut predicted: Vec<Vec<f32>> = pairs.into_iter().map(|(p, _)| p).collect();
+        block.proofs[2].model_version = "v0.9.0".to_string();
+        block.proofs[5].delta.delta_bytes = vec![0xff].into();
+        block.proofs[8].merkle_proof.leaf_hash = Hash32::ZERO;
+        predicted[4][1] += 1.0;
+        // A longer state fails the dimension check after the buffer has
+        // held shorter ones, and shorter ones follow it.
+        predicted[9].push(0.0);
+
+        let mut ctx = VerificationContext::new();
+        for _ in 0..3 {
+            for (proof, state) in block.proofs.iter().zip(&predicted) {
+                let plain = verifier.verify_proof(proof, state, &block.delta_tree_root);
+                let reused = verifier.verify_proof_ctx(&mut ctx, proof, state, &block.delta_tree_root);
+                assert_eq!(reused.status, plain.status);
+                assert_eq!(reused.tx_hash, plain.tx_hash);
+                assert_eq!(reused.message, plain.message);
+            }
+        }
+        let statuses: Vec<_> = block
+            .proofs
+            .iter()
+            .zip(&predicted)
+            .map(|(p, s)| verifier.verify_proof_ctx(&mut ctx, p, s, &block.delta_tree_root).status)
+            .collect();
+        assert_eq!(statuses.iter().filter(|s| **s == VerificationStatus::Valid).count(), 7);
+    }
+
+    #[test]
+    fn test_verify_proof_with_store_resolves_by_hash() {
+        let verifier = StateVerifier::new(MODEL);
+        let pairs = sample_pairs(2);
+        let block = build_block(&pairs);
+        let (predicted, _) = &pairs[1];
+        let root = &block.delta_tree_root;
+
+        let inline = &block.proofs[1];
+        let mut proof = inline.clone();
+        proof.delta.delta_bytes = inline.delta.delta_bytes.to_by_hash();
+        assert!(proof.to_bytes().unwrap().len() < inline.to_bytes().unwrap().len());
+
+        let mut store = cantor_core::Hash32Map::default();
+        let missing = verifier.verify_proof_with_store(&proof, predicted, root, &store);
+        assert_eq!(missing.status, VerificationStatus::InvalidDelta);
+        assert!(missing.message.contains("not found"), "{}", missing.message);
+        assert_eq!(
+            verifier.verify_proof(&proof, predicted, root).status,
+            VerificationStatus::InvalidDelta
+        );
+
+        let bytes = inline.delta.delta_bytes.inline_bytes().unwrap().to_vec();
+        store.insert(proof.delta.delta_bytes.content_hash(), bytes);
+        let result = verifier.verify_proof_with_store(&proof, predicted, root, &store);
+        assert_eq!(result.status, VerificationStatus::Valid);
+        assert_eq!(
+            verifier
+                .verify_proof_with_store(inline, predicted, root, &store)
+                .status,
+            VerificationStatus::Valid
+        );
+
+        // The store cannot substitute another delta under the hash.
+        let other = block.proofs[0].delta.delta_bytes.inline_bytes().unwrap().to_vec();
+        store.insert(proof.delta.delta_bytes.content_hash(), other);
+        let swapped = verifier.verify_proof_with_store(&proof, predicted, root, &store);
+        assert_eq!(swapped.status, VerificationStatus::InvalidDelta);
+    }
+
+    #[test]
+    fn test_verify_by_tx_hash_ignores_order() {
+        let verifier = StateVerifier::new(MODEL);
+        let pairs = sample_pairs(4);
+        let block = build_block(&pairs);
+
+        // Positional verification pairs the reversed states wrongly.
+        let reversed: Vec<Vec<f32>> = pairs.iter().rev().map(|(p, _)| p.clone()).collect();
+        let positional = verifier.verify_batch(&block, &reversed).unwrap();
+        assert!(positional
+            .iter()
+            .any(|r| r.status == VerificationStatus::InvalidPrediction));
+
+        let mut by_tx: HashMap<Hash32, Vec<f32>> = block
+            .proofs
+            .iter()
+            .zip(&pairs)
+            .rev()
+            .map(|(proof, (predicted, _))| (proof.tx_hash, predicted.clone()))
+            .collect();
+        let results = verifier.verify_by_tx_hash(&block, &by_tx);
+        assert_eq!(results.len(), 4);
+        for (result, proof) in results.iter().zip(&block.proofs) {
+            assert_eq!(result.status, VerificationStatus::Valid);
+            assert_eq!(result.tx_hash, Some(proof.tx_hash));
+        }
+
+        let missing = block.proofs[2].tx_hash;
+        by_tx.remove(&missing);
+        let results = verifier.verify_by_tx_hash(&block, &by_tx);
+        assert_eq!(results[2].status, VerificationStatus::InvalidPrediction);
+        assert!(results[2].message.contains("No predicted state"));
+        assert_eq!(results[3].status, VerificationStatus::Valid);
+    }
+
+    #[test]
+    fn test_min_block_rejects_replays() {
+        let mut verifier = StateVerifier::new(MODEL);
+        let pairs = sample_pairs(3);
+        let predicted: Vec<Vec<f32>> = pairs.iter().map(|(p, _)| p.clone()).collect();
+        let block_at = |number| {
+            let mut block = build_block(&pairs);
+            block.block_number = number;
+            block
+        };
+        assert_eq!(verifier.min_block(), None);
+        verifier.set_min_block(5);
+
+        let results = verifier.verify_batch(&block_at(10), &predicted).unwrap();
+        assert!(results
+            .iter()
+            .all(|r| r.status == VerificationStatus::Valid));
+        assert_eq!(verifier.min_block(), Some(10));
+
+        let stale = verifier.verify_batch(&block_at(8), &predicted).unwrap();
+        assert!(stale
+            .iter()
+            .all(|r| r.status == VerificationStatus::StaleBlock));
+        assert!(
+            stale[0].message.contains("watermark 10"),
+            "{}",
+            stale[0].message
+        );
+        let counts = verifier
+            .verify_batch_counts(&block_at(8), &predicted)
+            .unwrap();
+        assert_eq!(counts.count(&VerificationStatus::StaleBlock), 3);
+        assert_eq!(verifier.min_block(), Some(10));
+
+        // A block that fails verification does not move the watermark.
+        let mut wrong = predicted.clone();
+        wrong[0][0] += 1.0;
+        let results = verifier.verify_batch(&block_at(12), &wrong).unwrap();
+        assert_eq!(results[0].status, VerificationStatus::InvalidPrediction);
+        assert_eq!(verifier.min_block(), Some(10));
+
+        let results = verifier.verify_batch(&block_at(11), &predicted).unwrap();
+        assert!(results
+            .iter()
+            .all(|r| r.status == VerificationStatus::Valid));
+        assert_eq!(verifier.min_block(), Some(11));
+        let outcome = verifier
+            .verify_batch_mode(&block_at(10), &predicted, BatchMode::FailFast)
+            .unwrap();
+        assert_eq!(outcome.first_failure, Some(0));
+        assert_eq!(outcome.results[0].status, VerificationStatus::StaleBlock);
+
+        // Neither an empty block nor a self-rooted batch moves it.
+        let mut empty = block_at(u64::MAX);
+        empty.proofs.clear();
+        assert!(verifier.verify_batch(&empty, &[]).unwrap().is_empty());
+        let mut genesis = empty.clone();
+        genesis.block_number = 0;
+        assert!(verifier
+            .verify_genesis_block(&genesis, &StateVector::zeros(4))
+            .unwrap()
+            .is_empty());
+        let counts = verifier.verify_batch_counts(&empty, &[]).unwrap();
+        assert_eq!(counts.first_failure, None);
+        let batch = ProofBatch {
+            block_number: u64::MAX,
+            proofs: vec![],
+            ..block_at(11).proof_batch()
+        };
+        assert!(verifier.verify_proof_batch(&batch, &[]).unwrap().is_empty());
+        let batch = ProofBatch {
+            block_number: 20,
+            ..block_at(11).proof_batch()
+        };
+        let results = verifier.verify_proof_batch(&batch, &predicted).unwrap();
+        assert_eq!(StateVerifier::valid_count(&results), 3);
+        assert_eq!(verifier.min_block(), Some(11));
+    }
+
+    const MODEL: &str = "v1.0.0";
+
+    fn sample_pairs(n: usize) -> Vec<(Vec<f32>, Vec<f32>)> {
+        (0..n)
+            .map(|i| {
+                let predicted = vec![i as f32, 1.0, 2.0, 3.0];
+                let delta = vec![0.5, 0.0, -0.25, i as f32 * 0.5];
+                (predicted, delta)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_verify_batch_timed() {
+        let verifier = StateVerifier::new(MODEL);
+        let pairs = sample_pairs(200);
+        let block = build_block(&pairs);
+        let predicted: Vec<Vec<f32>> = pairs.into_iter().map(|(p, _)| p).collect();
+
+        let (partial, exhausted) = verifier
+            .verify_batch_timed(&block, &predicted, Duration::from_nanos(1))
+            .unwrap();
+        assert!(exhausted);
+        assert_eq!(partial.len(), TIMED_CHECK_INTERVAL);
+        assert!(partial.iter().all(|r| r.status == VerificationStatus::Valid));
+
+        let (full, exhausted) = verifier
+            .verify_batch_timed(&block, &predicted, Duration::from_secs(60))
+            .unwrap();
+        assert!(!exhausted);
+        assert_eq!(full.len(), 200);
+    }
+
+    #[test]
+    fn test_verify_batch_timed_each() {
+        let verifier = StateVerifier::new(MODEL);
+        let pairs = sample_pairs(20);
+        let mut block = build_block(&pairs);
+        block.proofs[5].model_version = "v0.9.0".to_string();
+        let predicted: Vec<Vec<f32>> = pairs.into_iter().map(|(p, _)| p).collect();
+
+        let timed = verifier
+            .verify_batch_timed_each(&block, &predicted)
+            .unwrap();
+        let batch = verifier.verify_batch(&block, &predicted).unwrap();
+        assert_eq!(timed.len(), block.proofs.len());
+        for ((verification, elapsed), expected) in timed.iter().zip(&batch) {
+            assert_eq!(verification.status, expected.status);
+            assert!(*elapsed > Duration::ZERO);
+        }
+        assert!(verifier
+            .verify_batch_timed_each(&block, &predicted[1..])
+            .is_err());
+    }
+
+    #[test]
+    fn test_verify_zip_matches_batch() {
+        let verifier = StateVerifier::new(MODEL);
+        let pairs = sample_pairs(6);
+        let mut block = build_block(&pairs);
+        block.proofs[2].model_version = "v0.9.0".to_string();
+        let predicted: Vec<Vec<f32>> = pairs.into_iter().map(|(p, _)| p).collect();
+
+        let batch = verifier.verify_batch(&block, &predicted).unwrap();
+        let zipped: Vec<VerificationResult> = verifier
+            .verify_zip(
+                block.proofs.clone().into_iter(),
+                predicted.clone().into_iter(),
+                &block.delta_tree_root,
+            )
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(zipped.len(), batch.len());
+        for (z, b) in zipped.iter().zip(&batch) {
+            assert_eq!(z.status, b.status);
+        }
+
+        let mut short = verifier.verify_zip(
+            block.proofs.clone().into_iter(),
+            predicted.into_iter().take(4),
+            &block.delta_tree_root,
+        );
+        for _ in 0..4 {
+            assert!(short.next().unwrap().is_ok());
+        }
+        assert!(matches!(
+            short.next(),
+            Some(Err(CantorError::BatchLengthMismatch {
+                proofs: 5,
+                predicted: 4
+            }))
+        ));
+        assert!(short.next().is_none());
+    }
+
+    /// Build a single-tree block with one proof per `(predicted, delta)` pair.
+    fn build_block(pairs: &[(Vec<f32>, Vec<f32>)]) -> CompressionResult {
+        build_block_with(pairs, QuantizationParams::default())
+    }
+
+    fn build_block_with(
+        pairs: &[(Vec<f32>, Vec<f32>)],
+        params: QuantizationParams,
+    ) -> CompressionResult {
+        build_block_encoded(pairs, params, DeltaEncoder::from_params(params))
+    }
+
+    /// [`build_block_with`] encoding through `encoder`, which must decode
+    /// under `params`.
+    fn build_block_encoded(
+        pairs: &[(Vec<f32>, Vec<f32>)],
+        params: QuantizationParams,
+        encoder: DeltaEncoder,
+    ) -> CompressionResult {
+        let encoded: Vec<Vec<u8>> = pairs
+            .iter()
+            .map(|(_, delta)| encoder.encode(delta).unwrap())
+            .collect();
+        let refs: Vec<&[u8]> = encoded.iter().map(|e| e.as_slice()).collect();
+        let tree = MerkleDeltaTree::build(&refs);
+
+        let mut deltas = Vec::new();
+        let mut proofs = Vec::new();
+        for (i, ((predicted, delta), delta_bytes)) in pairs.iter().zip(&encoded).enumerate() {
+            let actual = cantor_core::reconstruct_canonical(predicted, delta).unwrap();
+            let tx_hash = Hash32([i as u8 + 1; 32]);
+            let state_delta = StateDelta {
+                tx_hash,
+                predicted_root: StateVerifier::compute_hash(predicted),
+                actual_root: StateVerifier::compute_hash(&actual),
+                delta_bytes: delta_bytes.clone().into(),
+                confidence: 0.9,
+                method: params.method,
+            };
+            proofs.push(VerificationProof {
+                tx_hash,
+                predicted_state: StateVerifier::compute_hash(predicted),
+                delta: state_delta.clone(),
+                merkle_proof: tree.generate_proof(i).unwrap(),
+                model_version: MODEL.to_string(),
+                tree_hasher: TreeHasher::default(),
+            });
+            deltas.push(state_delta);
+        }
+
+        CompressionResult {
+            block_number: 1,
+            original_size: pairs.iter().map(|(_, d)| d.len() * 4).sum(),
+            compressed_size: encoded.iter().map(|e| e.len()).sum(),
+            delta_tree_root: tree.root(),
+            deltas,
+            proofs,
+            quantization: params,
+        }
+    }
+}
@@ -0,0 +1,129 @@
+//! Runtime self-test of the compress → merkle → verify pipeline.
+//!
+//! [`self_test`] is meant to be run once at startup, as a power-on self
+//! test: it exercises every stage on a fixed input and compares the tree
+//! root against a pinned value, so a miscompiled or mislinked build fails
+//! loudly before it verifies anything real.
+
+use cantor_compress::{CompressionMethod, DeltaEncoder};
+use cantor_core::{
+    CantorError, Hash32, Result, StateDelta, StateVector, TreeHasher, VerificationProof,
+};
+use cantor_merkle::MerkleDeltaTree;
+
+use crate::{StateVerifier, VerificationStatus};
+
+/// Model version stamped on the self-test proofs.
+const SELF_TEST_MODEL: &str = "cantor-self-test";
+
+/// Method of the self-test deltas. Its bytes are produced in this
+/// workspace, so the pinned root moves only when CANTOR's own output does.
+const SELF_TEST_METHOD: CompressionMethod = CompressionMethod::RunLength;
+
+/// Root of the [`SELF_TEST_METHOD`] delta tree built from
+/// [`self_test_deltas`].
+const SELF_TEST_ROOT: &str =
+    "0x7ad14bd0f61eb4fd6a2a5e4f401e0fc8ec21155ac38fc550a7d16f170353e0c7";
+
+fn self_test_predicted() -> Vec<f32> {
+    vec![1.0, -2.0, 0.5, 4.0, 0.0, 8.25, -3.0, 1.5]
+}
+
+fn self_test_deltas() -> [Vec<f32>; 3] {
+    [
+        vec![0.25, 0.0, 0.0, -1.5, 0.0, 0.0, 2.0, 0.0],
+        vec![0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0],
+        vec![-0.75, 3.0, 0.125, 0.0, 1.0, -8.25, 0.0, 0.5],
+    ]
+}
+
+fn fail(stage: &str, detail: impl std::fmt::Display) -> CantorError {
+    CantorError::StateReconstructionFailed(format!("self-test {}: {}", stage, detail))
+}
+
+/// Run a fixed encode → tree → proof → verify round-trip.
+///
+/// Returns an error naming the first stage whose output diverged from the
+/// expected value. Call it once at startup and refuse to serve if it fails:
+///
+/// ```
+/// cantor_verify::self_test().expect("CANTOR self-test failed");
+/// ```
+pub fn self_test() -> Result<()> {
+    let encoder = DeltaEncoder::new(SELF_TEST_METHOD);
+    let predicted = self_test_predicted();
+    let deltas = self_test_deltas();
+
+    // LZ4's bytes belong to whichever backend was linked, and may change
+    // with its version, so it is checked by round-trip alone.
+    let lz4 = DeltaEncoder::new(CompressionMethod::Lz4);
+    let mut encoded = Vec::with_capacity(deltas.len());
+    for delta in &deltas {
+        if lz4.decode(&lz4.encode(delta)?)? != *delta {
+            return Err(fail("encode", "LZ4 round-trip changed the delta"));
+        }
+        let bytes = encoder.encode(delta)?;
+        if encoder.decode(&bytes)? != *delta {
+            return Err(fail("encode", "RunLength round-trip changed the delta"));
+        }
+        encoded.push(bytes);
+    }
+
+    let refs: Vec<&[u8]> = encoded.iter().map(|e| e.as_slice()).collect();
+    let tree = MerkleDeltaTree::build(&refs);
+    let root = tree.root();
+    if root.to_string() != SELF_TEST_ROOT {
+        return Err(fail(
+            "merkle",
+            format!("root {} != {}", root, SELF_TEST_ROOT),
+        ));
+    }
+
+    let verifier = StateVerifier::new(SELF_TEST_MODEL);
+    let predicted_root = StateVector::new(predicted.clone()).compute_hash();
+    for (i, (delta, delta_bytes)) in deltas.iter().zip(encoded).enumerate() {
+        let actual: Vec<f32> = predicted.iter().zip(delta).map(|(p, d)| p + d).collect();
+        let tx_hash = Hash32([i as u8 + 1; 32]);
+        let proof = VerificationProof {
+            tx_hash,
+            predicted_state: predicted_root,
+            delta: StateDelta {
+                tx_hash,
+                predicted_root,
+                actual_root: StateVector::new(actual.clone()).compute_hash(),
+                delta_bytes: delta_bytes.into(),
+                confidence: 1.0,
+                method: SELF_TEST_METHOD,
+            },
+            merkle_proof: tree.generate_proof(i)?,
+            model_version: SELF_TEST_MODEL.to_string(),
+            tree_hasher: TreeHasher::default(),
+        };
+
+        let result = verifier.verify_proof(&proof, &predicted, &root);
+        if result.status != VerificationStatus::Valid {
+            return Err(fail("verify", format!("proof {}: {}", i, result.message)));
+        }
+        if verifier.verify_and_reconstruct(&proof, &predicted, &root)? != actual {
+            return Err(fail("reconstruct", format!("proof {} state differs", i)));
+        }
+
+        let mut forged = proof;
+        forged.merkle_proof.leaf_hash = Hash32::ZERO;
+        if verifier.verify_proof(&forged, &predicted, &root).status == VerificationStatus::Valid {
+            return Err(fail("verify", format!("forged proof {} was accepted", i)));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_self_test_passes() {
+        self_test().unwrap();
+    }
+}